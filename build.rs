@@ -1,4 +1,8 @@
 fn main() {
+    // Host builds only compile the library for testing, they don't need linking or credentials
+    if std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() != Ok("xtensa") {
+        return;
+    }
     println!("cargo:rustc-link-arg-bins=-Tlinkall.x");
     let creds_lines = std::fs::read_to_string("wifi-creds").unwrap();
     for line in creds_lines.lines() {
@@ -7,4 +11,16 @@ fn main() {
             println!("cargo:rustc-env={val_pair}")
         }
     }
+    // Included by the firmware, the days off are fetched over `https` with the `tls` feature
+    let tls_ca_path = creds_lines
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("TLS_CA_DER="));
+    if std::env::var_os("CARGO_FEATURE_TLS").is_some() && tls_ca_path.is_none() {
+        panic!("The `tls` feature needs a `TLS_CA_DER` line in `wifi-creds`, or turn it off");
+    }
+    let tls_ca = tls_ca_path
+        .map(|path| std::fs::read(path).unwrap())
+        .unwrap_or_default();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(format!("{out_dir}/tls_ca.der"), tls_ca).unwrap();
 }
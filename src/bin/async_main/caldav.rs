@@ -0,0 +1,77 @@
+//! Events from a CalDAV server, like Radicale or Nextcloud
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use chrono::NaiveDate;
+use embedded_io_async::{Read, Write};
+use esp32_epaper_calendar::{
+    caldav::{MultistatusParser, calendar_query_body, report_request},
+    ical::{Event, IcalParser},
+};
+use log::{info, warn};
+use reqwless::{
+    request::Method,
+    response::{Response, StatusCode},
+};
+
+use crate::{HttpClientConcrete, error::NetworkError, ical::MAX_EVENTS, time::local_tz};
+
+/// URL of the calendar collection, set by a `CALDAV_URL` line in the `wifi-creds` file, like
+/// `https://dav.example.com/anna/calendar/`. Events are not fetched from CalDAV without it.
+pub const CALDAV_URL: Option<&str> = option_env!("CALDAV_URL");
+/// User for basic authentication, set by a `CALDAV_USER` line in the `wifi-creds` file
+const CALDAV_USER: Option<&str> = option_env!("CALDAV_USER");
+/// Set by a `CALDAV_PASSWORD` line in the `wifi-creds` file
+const CALDAV_PASSWORD: &str = match option_env!("CALDAV_PASSWORD") {
+    Some(password) => password,
+    None => "",
+};
+
+/// Query the calendar collection for the events that can take place on the `window` dates
+pub async fn fetch_events(
+    client: &mut HttpClientConcrete,
+    url: &str,
+    window: Range<NaiveDate>,
+) -> Result<Vec<Event>, NetworkError> {
+    info!("Querying CalDAV calendar");
+    let body = calendar_query_body(&window, local_tz());
+    let mut resource = client.resource(url).await?;
+    let credentials = CALDAV_USER.map(|user| (user, CALDAV_PASSWORD));
+    let request = report_request(resource.host, resource.base_path, credentials, &body);
+    resource
+        .conn
+        .write_all(request.as_bytes())
+        .await
+        .map_err(reqwless::Error::from)?;
+    resource.conn.flush().await.map_err(reqwless::Error::from)?;
+
+    let mut rx_buf = [0; 4096];
+    // The method is only used to know if the response has a body
+    let response = Response::read(&mut resource.conn, Method::GET, &mut rx_buf).await?;
+    match response.status {
+        StatusCode(207) => {}
+        StatusCode(401) => {
+            warn!("CalDAV server rejected the credentials");
+            return Err(NetworkError::Status(401));
+        }
+        status => {
+            warn!("Unexpected status code: {}", status.0);
+            return Err(NetworkError::Status(status.0));
+        }
+    }
+
+    let mut parser = MultistatusParser::new(IcalParser::new(window, MAX_EVENTS));
+    let mut reader = response.body().reader();
+    let mut chunk = [0; 512];
+    loop {
+        let len = reader.read(&mut chunk).await?;
+        if len == 0 {
+            break;
+        }
+        parser.push(&chunk[..len]);
+    }
+    let events = parser.finish();
+    info!("Got {} events", events.len());
+    Ok(events)
+}
@@ -0,0 +1,177 @@
+//! Days off of the shown months, fetched a year at a time from a [`DaysOffProvider`] and cached
+//!
+//! The cache is also kept in flash and loaded at boot, so the days off are known even if the network
+//! is down after a reboot.
+
+use chrono::{Months, NaiveDate};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use esp_storage::FlashStorage;
+use log::{error, info, warn};
+
+use esp32_epaper_calendar::{
+    calendar_utils::{CalendarMonth, MonthDate},
+    days_off::DaysOffProvider,
+    days_off_cache::{CachedYear, DaysOffCache, Lookup},
+    days_off_store::{DaysOffStore, StoreError},
+    partition_table,
+};
+
+use crate::{
+    DAYS_OFF_REVALIDATE_AFTER, HttpClientConcrete, WEEK_CONFIG,
+    error::{HolidaysError, NetworkError},
+};
+
+/// Label of the data partition of the store in `partitions.csv`
+const DAYS_OFF_PARTITION: &str = "calendar";
+/// Subtype of the data partition of the store in `partitions.csv`
+const DAYS_OFF_PARTITION_SUBTYPE: u8 = 0x40;
+
+static DAYS_OFF_CACHE: Mutex<CriticalSectionRawMutex, DaysOffCache> =
+    Mutex::new(DaysOffCache::new(DAYS_OFF_REVALIDATE_AFTER));
+
+/// `None` when the flash range is not usable
+static DAYS_OFF_STORE: Mutex<CriticalSectionRawMutex, Option<DaysOffStore<FlashStorage>>> =
+    Mutex::new(None);
+
+/// Open the days off store in flash and fill the cache with the years it has for the region of
+/// `provider`, the years shown around `current_month` first
+///
+/// The store is in the partition found in the partition table. The cache starts empty when the
+/// store fails.
+pub async fn load_cache(
+    provider: &impl DaysOffProvider,
+    current_month: Option<MonthDate>,
+) -> Result<(), HolidaysError> {
+    let mut flash = FlashStorage::new();
+    let range = partition_table::find(
+        &mut flash,
+        partition_table::DATA,
+        DAYS_OFF_PARTITION_SUBTYPE,
+        DAYS_OFF_PARTITION,
+    )
+    .map_err(StoreError::from)
+    .inspect_err(|e| error!("Failed to read partition table: {e:?}"))?
+    .ok_or(HolidaysError::NoPartition)
+    .inspect_err(|_| error!("No {DAYS_OFF_PARTITION} partition for the days off store"))?;
+    let mut store = DaysOffStore::new(flash, range)
+        .inspect_err(|e| error!("Failed to open days off store: {e:?}"))?;
+    let years = store.load(provider.region());
+    // Saving can still work when loading failed
+    *DAYS_OFF_STORE.lock().await = Some(store);
+    let years = years.inspect_err(|e| error!("Failed to load days off store: {e:?}"))?;
+    let mut cache = DAYS_OFF_CACHE.lock().await;
+    // The loaded years that are not shown are dropped by the first rotation
+    for year in cache.fill(&years, current_month) {
+        info!("No room for the stored days off of year {year}");
+    }
+    for cached in cache.iter() {
+        info!(
+            "Loaded days off of year {} fetched on {}",
+            cached.days_off.year(),
+            cached.fetched_on
+        );
+    }
+    Ok(())
+}
+
+/// Cache the year in RAM and in flash
+///
+/// The year is still cached in RAM when storing it in flash fails.
+async fn insert_cache(region: &str, cached: CachedYear) -> Result<(), HolidaysError> {
+    let year = cached.days_off.year();
+    if DAYS_OFF_CACHE.lock().await.insert(cached).is_err() {
+        error!("Failed to populate cache year {year}, attempt to insert over capacity");
+    }
+    if let Some(store) = DAYS_OFF_STORE.lock().await.as_mut() {
+        store
+            .save(region, cached)
+            .inspect_err(|e| error!("Failed to store days off of year {year}: {e:?}"))?;
+    }
+    Ok(())
+}
+
+/// Fetch the years of the previous, current and next months that are not cached or are due for
+/// revalidation
+///
+/// A year that fails to be fetched keeps its stale data, it's tried again on the next call. The
+/// other years are still fetched, and the last error is returned.
+pub async fn populate_cache(
+    client: &mut HttpClientConcrete,
+    provider: &impl DaysOffProvider,
+    current_month: MonthDate,
+    today: NaiveDate,
+) -> Result<(), HolidaysError> {
+    let mut res = Ok(());
+    let to_refresh = DAYS_OFF_CACHE.lock().await.to_refresh(current_month, today);
+    for year in to_refresh {
+        match provider
+            .fetch_year_days_off(client, year, WEEK_CONFIG)
+            .await
+        {
+            Ok(Some(days_off)) => {
+                info!("Fetched days off of year {year}");
+                let cached = CachedYear {
+                    days_off,
+                    fetched_on: today,
+                };
+                if let Err(e) = insert_cache(provider.region(), cached).await {
+                    res = Err(e);
+                }
+            }
+            // Data for the next year is not available until it's published
+            Ok(None) => warn!("No days off data for year {year}"),
+            Err(e) => {
+                error!("Failed to fetch days off of year {year}: {e:?}");
+                res = Err(HolidaysError::Fetch {
+                    year,
+                    error: NetworkError::from(e),
+                });
+            }
+        }
+    }
+    res
+}
+
+/// Drop the years that are no longer shown, once the month rolled over
+pub async fn rotate_cache(current_month: MonthDate) {
+    for year in DAYS_OFF_CACHE.lock().await.rotate(current_month) {
+        info!("Dropped days off of year {year} from the cache");
+    }
+}
+
+/// Get the previous, current and next months
+pub fn get_months_triplet(current_month: MonthDate) -> [MonthDate; 3] {
+    [
+        current_month - Months::new(1),
+        current_month,
+        current_month + Months::new(1),
+    ]
+}
+
+/// Set the days off of the calendar from the cache, stale data included
+///
+/// Falls back to the offline days off of the provider when the year is not cached.
+pub async fn update_days_off_mask(
+    provider: &impl DaysOffProvider,
+    calendar: &mut CalendarMonth,
+    today: NaiveDate,
+) {
+    let month = calendar.month_date();
+    let (year, month_number) = (month.year(), month.month().number_from_month());
+    let lookup = DAYS_OFF_CACHE.lock().await.lookup(month, today);
+    let mask = match lookup {
+        Lookup::Fresh { mask, age } => {
+            info!("Days off cache hit for {year}-{month_number:02}, {age} days old");
+            mask
+        }
+        Lookup::Stale { mask, age } => {
+            warn!("Days off cache stale for {year}-{month_number:02}, {age} days old");
+            mask
+        }
+        Lookup::Miss => {
+            info!("Days off cache miss for {year}-{month_number:02}, using the offline days off");
+            provider.offline_days_off(month, calendar.week())
+        }
+    };
+    calendar.set_days_off(mask);
+}
@@ -0,0 +1,85 @@
+use alloc::{format, string::String};
+
+use chrono::{Datelike, NaiveDate, Timelike};
+use embedded_graphics::{
+    prelude::{DrawTarget, Point},
+    text::{Alignment, Text, TextStyle},
+    Drawable,
+};
+use esp32_epaper_calendar::{ical::Occurrence, locale::Locale};
+use weact_studio_epd::TriColor;
+
+use super::{mini_calendar::MINI_CALENDAR_WIDTH, text_styles::*};
+
+const AGENDA_TIME_STYLE: LocaleStyleType = LOCALE_STYLE_RED_7;
+const AGENDA_SUMMARY_STYLE: LocaleStyleType = LOCALE_STYLE_BLACK_7;
+
+/// Takes the place of the mini calendars
+pub const AGENDA_WIDTH: i32 = MINI_CALENDAR_WIDTH;
+/// Entries that fit the height of the display
+pub const AGENDA_MAX_ENTRIES: usize = 5;
+
+const AGENDA_ROW_HEIGHT: i32 = 10;
+/// Two rows and some spacing
+const AGENDA_ENTRY_HEIGHT: i32 = AGENDA_ROW_HEIGHT * 2 + 5;
+/// Characters of the 6 pixels wide font that fit the width
+const AGENDA_LINE_LEN: usize = (AGENDA_WIDTH / 6) as usize;
+
+/// Draw a list of events, each with its start and summary, as many as fit in `height`
+pub fn draw_agenda<D: DrawTarget<Color = TriColor>>(
+    agenda: &[Occurrence],
+    locale: Locale,
+    top_left: Point,
+    height: i32,
+    display: &mut D,
+) -> Result<(), D::Error> {
+    let entries = AGENDA_MAX_ENTRIES.min((height / AGENDA_ENTRY_HEIGHT) as usize);
+    for (idx, occurrence) in agenda.iter().take(entries).enumerate() {
+        let pos = top_left + Point::new(2, AGENDA_ENTRY_HEIGHT * idx as i32 + AGENDA_ROW_HEIGHT - 1);
+
+        let start = short_date(occurrence.start.date(), locale);
+        let when = if occurrence.is_all_day() {
+            let dates = occurrence.dates();
+            let last = dates.end.pred_opt().unwrap_or(dates.start);
+            if last > dates.start {
+                format!("{start} - {:02}.{:02}", last.day(), last.month())
+            } else {
+                start
+            }
+        } else {
+            format!(
+                "{start} {:02}:{:02}",
+                occurrence.start.hour(),
+                occurrence.start.minute()
+            )
+        };
+        let _ = Text::with_text_style(
+            &when,
+            pos,
+            AGENDA_TIME_STYLE,
+            TextStyle::with_alignment(Alignment::Left),
+        )
+        .draw(display)?;
+
+        let summary: String = occurrence.event.summary.chars().take(AGENDA_LINE_LEN).collect();
+        let _ = Text::with_text_style(
+            &summary,
+            pos + Point::new(0, AGENDA_ROW_HEIGHT),
+            AGENDA_SUMMARY_STYLE,
+            TextStyle::with_alignment(Alignment::Left),
+        )
+        .draw(display)?;
+    }
+
+    Ok(())
+}
+
+/// Weekday, day and month, like `пн 27.01`
+fn short_date(date: NaiveDate, locale: Locale) -> String {
+    format!(
+        "{} {:02}.{:02}",
+        locale.weekday_short_name(date.weekday()),
+        date.day(),
+        date.month()
+    )
+}
@@ -0,0 +1,69 @@
+use alloc::{format, string::ToString};
+
+use embedded_graphics::{
+    prelude::{DrawTarget, Point},
+    text::{Alignment, Text, TextStyle},
+    Drawable,
+};
+use esp32_epaper_calendar::{calendar_utils::CalendarMonth, locale::Locale};
+use weact_studio_epd::TriColor;
+
+use super::text_styles::*;
+
+const MINI_TITLE_STYLE: LocaleStyleType = LOCALE_STYLE_BLACK_7;
+const MINI_DAY_STYLE_BLACK: StyleType = STYLE_BLACK_7;
+const MINI_DAY_STYLE_RED: StyleType = STYLE_RED_7;
+
+/// Width of a grid column, two digits and some padding
+pub const MINI_COLUMN_WIDTH: i32 = MINI_DAY_STYLE_BLACK.font.character_size.width as i32 * 2 + 5;
+/// Up to six rows of days under the title, one line of text per row
+pub const MINI_CALENDAR_HEIGHT: i32 = MINI_ROW_HEIGHT * 7;
+pub const MINI_CALENDAR_WIDTH: i32 = MINI_COLUMN_WIDTH * 7;
+
+const MINI_ROW_HEIGHT: i32 = 9;
+
+/// Draw a compact month grid without weekday names, with days off in red
+pub fn draw_mini_calendar<D: DrawTarget<Color = TriColor>>(
+    calendar: &CalendarMonth,
+    locale: Locale,
+    top_left: Point,
+    display: &mut D,
+) -> Result<(), D::Error> {
+    let title = format!("{} {}", locale.month_name(calendar.month()), calendar.year());
+    let title_pos = top_left + Point::new(MINI_CALENDAR_WIDTH / 2, MINI_ROW_HEIGHT - 1);
+    let _ = Text::with_text_style(
+        &title,
+        title_pos,
+        MINI_TITLE_STYLE,
+        TextStyle::with_alignment(Alignment::Center),
+    )
+    .draw(display)?;
+
+    let grid_anchor = title_pos + Point::new(0, MINI_ROW_HEIGHT + 1)
+        - Point::new(MINI_CALENDAR_WIDTH / 2 - MINI_COLUMN_WIDTH / 2, 0);
+    let start_offset = calendar.start_column();
+    for (day, kind) in calendar.days_iter() {
+        let column = (day + start_offset) % 7;
+        let row = (day + start_offset) / 7;
+
+        let pos = grid_anchor
+            + Point::new(
+                MINI_COLUMN_WIDTH * column as i32,
+                MINI_ROW_HEIGHT * row as i32,
+            );
+        let text = (day + 1).to_string();
+        let _ = Text::with_text_style(
+            &text,
+            pos,
+            if kind.is_day_off() {
+                MINI_DAY_STYLE_RED
+            } else {
+                MINI_DAY_STYLE_BLACK
+            },
+            TextStyle::with_alignment(Alignment::Center),
+        )
+        .draw(display)?;
+    }
+
+    Ok(())
+}
@@ -1,53 +1,160 @@
-use alloc::string::ToString;
+use alloc::{format, string::ToString};
 
 use chrono::{DateTime, Datelike};
-use chrono_tz::Tz;
 use embedded_graphics::{
+    mono_font::MonoTextStyleBuilder,
     prelude::{DrawTarget, Point, Primitive, Size},
     primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle},
     text::{Alignment, Text, TextStyle},
     Drawable,
 };
+use esp32_epaper_calendar::{
+    calendar_utils::{CalendarMonth, DayKind},
+    ical::Occurrence,
+    locale::Locale,
+    posix_tz::PosixTz,
+};
 use weact_studio_epd::TriColor;
 
-use crate::calendar_utils::{all_weekdays_short_en, CalendarMonth};
-
+mod agenda;
+mod mini_calendar;
 mod text_styles;
+pub use agenda::AGENDA_MAX_ENTRIES;
+use agenda::{draw_agenda, AGENDA_WIDTH};
+use mini_calendar::{draw_mini_calendar, MINI_CALENDAR_HEIGHT, MINI_CALENDAR_WIDTH};
 use text_styles::*;
 
-const WEEKDAY_TEXT_STYLE_BLACK: StyleType = STYLE_BLACK_9;
-const WEEKDAY_TEXT_STYLE_RED: StyleType = STYLE_RED_9;
+const WEEKDAY_TEXT_STYLE_BLACK: LocaleStyleType = LOCALE_STYLE_BLACK_9;
+const WEEKDAY_TEXT_STYLE_RED: LocaleStyleType = LOCALE_STYLE_RED_9;
+const MONTH_NAME_STYLE: LocaleStyleType = LOCALE_STYLE_BLACK_18;
 const GRID_DAY_STYLE_BLACK: StyleType = STYLE_BLACK_12;
 const GRID_DAY_STYLE_RED: StyleType = STYLE_RED_12;
+/// Drawn on top of a red filled cell
+const GRID_DAY_STYLE_HOLIDAY: StyleType = STYLE_WHITE_12;
+const WEEK_NUM_STYLE: StyleType = STYLE_BLACK_9;
+const WEEK_LABEL_STYLE: LocaleStyleType = LOCALE_STYLE_BLACK_9;
+const ADJACENT_DAY_STYLE_BLACK: StyleType = STYLE_BLACK_9;
+const ADJACENT_DAY_STYLE_RED: StyleType = STYLE_RED_9;
+/// Letters of the failed subsystems
+const INDICATOR_STYLE: StyleType = STYLE_RED_9;
+const TIME_UNKNOWN_STYLE: LocaleStyleType = LOCALE_STYLE_BLACK_18;
+const TEMPERATURE_STYLE: StyleType = STYLE_BLACK_7;
+/// Bottom line of the side panel the temperature takes
+const TEMPERATURE_LINE_HEIGHT: i32 = TEMPERATURE_STYLE.font.character_size.height as i32 + 1;
+const GRID_DAY_STYLE_SHORTENED: StyleType = MonoTextStyleBuilder::new()
+    .font(GRID_DAY_STYLE_BLACK.font)
+    .text_color(TriColor::Black)
+    .underline()
+    .build();
+
+/// Style a grid day is drawn with, depending on its kind
+const fn grid_day_style(kind: DayKind) -> StyleType {
+    match kind {
+        DayKind::Workday => GRID_DAY_STYLE_BLACK,
+        DayKind::Weekend => GRID_DAY_STYLE_RED,
+        DayKind::PublicHoliday => GRID_DAY_STYLE_HOLIDAY,
+        DayKind::Shortened => GRID_DAY_STYLE_SHORTENED,
+    }
+}
+
+/// How the calendar is drawn and what optional parts it has
+#[derive(Debug, Clone, Copy)]
+pub struct CalendarLayout {
+    /// Language of the month and weekday names
+    pub locale: Locale,
+    /// Show the ISO week number of each grid row in a column to the left of the grid
+    pub week_numbers: bool,
+    /// What is shown to the right of the grid
+    pub side_panel: SidePanel,
+    /// Fill the grid cells before the first and after the last day of the month with the days of
+    /// the previous and next months
+    pub adjacent_days: bool,
+    /// Show the temperature of the room at the bottom of the side panel, in place of an agenda
+    /// entry or of the mini calendar of the previous month
+    pub temperature: bool,
+}
+
+/// Content of the area to the right of the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidePanel {
+    None,
+    /// Compact grids of the previous and next months
+    MiniCalendars,
+    /// The next events of the calendar feed, the mini calendars when there are none
+    Agenda,
+}
+
+/// Months before and after the drawn month
+#[derive(Debug, Clone, Copy)]
+pub struct NeighbourMonths {
+    pub previous: CalendarMonth,
+    pub next: CalendarMonth,
+}
+
+/// What is drawn around the month, gathered anew on each refresh
+#[derive(Debug, Clone, Copy)]
+pub struct CalendarExtras<'a> {
+    pub neighbours: NeighbourMonths,
+    /// The next events, shown by [`SidePanel::Agenda`]
+    pub agenda: &'a [Occurrence<'a>],
+    /// Letters of the issues of the refresh, nothing is drawn when empty
+    pub indicators: &'a str,
+    /// Label of the temperature of the room, `None` when it's not known
+    pub temperature: Option<&'a str>,
+}
 
 pub async fn draw_calendar<D: DrawTarget<Color = TriColor>>(
-    time: &DateTime<Tz>,
+    time: &DateTime<PosixTz>,
     calendar: CalendarMonth,
+    extras: CalendarExtras<'_>,
+    layout: CalendarLayout,
     display: &mut D,
 ) -> Result<(), D::Error> {
+    let CalendarExtras {
+        neighbours,
+        agenda,
+        indicators,
+        temperature,
+    } = extras;
     let column_spacing = Point::new(1, 0) + GRID_DAY_STYLE_BLACK.font.character_size.x_axis() * 3;
     let row_spacing = GRID_DAY_STYLE_BLACK.font.character_size.y_axis();
 
     let local_date_naive = time.naive_local().date();
 
     let today = local_date_naive.day0() as u8;
-    let days_grid_anchor = Point::new(14, 48);
+    // Two digits and some padding
+    let week_num_column_width = if layout.week_numbers {
+        WEEK_NUM_STYLE.font.character_size.width as i32 * 2 + 8
+    } else {
+        0
+    };
+    let days_grid_anchor = Point::new(14 + week_num_column_width, 48);
     let weekday_anchor = days_grid_anchor + Point::new(0, -14);
 
     const HIGHLIGHT_STYLE: PrimitiveStyle<TriColor> = PrimitiveStyleBuilder::new()
         .stroke_color(TriColor::Red)
         .stroke_width(2)
         .build();
+    const HOLIDAY_FILL_STYLE: PrimitiveStyle<TriColor> = PrimitiveStyle::with_fill(TriColor::Red);
+    // Holidays already have a red background
+    const HIGHLIGHT_STYLE_HOLIDAY: PrimitiveStyle<TriColor> = PrimitiveStyleBuilder::new()
+        .stroke_color(TriColor::Black)
+        .stroke_width(2)
+        .build();
+    const EVENT_MARKER_STYLE: PrimitiveStyle<TriColor> = PrimitiveStyle::with_fill(TriColor::Black);
+    const EVENT_MARKER_STYLE_HOLIDAY: PrimitiveStyle<TriColor> =
+        PrimitiveStyle::with_fill(TriColor::White);
 
-    for (i, day_of_week) in all_weekdays_short_en().into_iter().enumerate() {
+    let week = calendar.week();
+    for (i, day_of_week) in week.weekdays().into_iter().enumerate() {
         let pos = weekday_anchor + column_spacing * (i as i32);
-        let style = if i > 4 {
+        let style = if week.is_weekend(day_of_week) {
             WEEKDAY_TEXT_STYLE_RED
         } else {
             WEEKDAY_TEXT_STYLE_BLACK
         };
         let _ = Text::with_text_style(
-            day_of_week,
+            layout.locale.weekday_short_name(day_of_week),
             pos,
             style,
             TextStyle::with_alignment(Alignment::Center),
@@ -55,50 +162,119 @@ pub async fn draw_calendar<D: DrawTarget<Color = TriColor>>(
         .draw(display)?;
     }
 
-    let start_offset = calendar.start_weekday().num_days_from_monday() as u8;
-    for (day, is_day_off) in calendar.days_iter() {
-        let column = (day + start_offset) % 7;
-        let row = (day + start_offset) / 7;
+    if layout.week_numbers {
+        let week_num_anchor = Point::new(4 + week_num_column_width / 2, days_grid_anchor.y);
+        let _ = Text::with_text_style(
+            layout.locale.week_label(),
+            Point::new(week_num_anchor.x, weekday_anchor.y),
+            WEEK_LABEL_STYLE,
+            TextStyle::with_alignment(Alignment::Center),
+        )
+        .draw(display)?;
+        for row in 0..calendar.rows_amount() {
+            let text = calendar.row_week_num(row).to_string();
+            let _ = Text::with_text_style(
+                &text,
+                week_num_anchor + row_spacing * row as u32,
+                WEEK_NUM_STYLE,
+                TextStyle::with_alignment(Alignment::Center),
+            )
+            .draw(display)?;
+        }
+    }
+
+    let cell_pos = |cell: u8| {
+        let column = cell % 7;
+        let row = cell / 7;
+        days_grid_anchor + column_spacing * (column as i32) + row_spacing * row as u32
+    };
 
-        let pos = days_grid_anchor + column_spacing * (column as i32) + row_spacing * row as u32;
+    let start_offset = calendar.start_column();
+    for (day, kind) in calendar.days_iter() {
+        let pos = cell_pos(day + start_offset);
 
         let text = (day + 1).to_string();
 
+        let cell = Rectangle::with_center(
+            pos + Point::new(-1, -4),
+            Size {
+                width: column_spacing.x as u32,
+                height: GRID_DAY_STYLE_RED.font.character_size.height,
+            },
+        );
+
+        if kind == DayKind::PublicHoliday {
+            cell.into_styled(HOLIDAY_FILL_STYLE).draw(display)?;
+        }
+
         let _ = Text::with_text_style(
             &text,
             pos,
-            if is_day_off {
-                GRID_DAY_STYLE_RED
-            } else {
-                GRID_DAY_STYLE_BLACK
-            },
+            grid_day_style(kind),
             TextStyle::with_alignment(Alignment::Center),
         )
         .draw(display)?;
 
+        if calendar.has_events_day0(day) {
+            // Small square in the bottom right corner of the cell, below the digits
+            let marker = Rectangle::new(
+                cell.top_left + Point::new(cell.size.width as i32 - 4, cell.size.height as i32 - 4),
+                Size::new_equal(2),
+            );
+            marker
+                .into_styled(if kind == DayKind::PublicHoliday {
+                    EVENT_MARKER_STYLE_HOLIDAY
+                } else {
+                    EVENT_MARKER_STYLE
+                })
+                .draw(display)?;
+        }
+
         if day == today {
-            Rectangle::with_center(
-                pos + Point::new(-1, -4),
-                Size {
-                    width: column_spacing.x as u32,
-                    height: GRID_DAY_STYLE_RED.font.character_size.height,
+            cell.into_styled(if kind == DayKind::PublicHoliday {
+                HIGHLIGHT_STYLE_HOLIDAY
+            } else {
+                HIGHLIGHT_STYLE
+            })
+            .draw(display)?;
+        }
+    }
+
+    if layout.adjacent_days {
+        let leading_cells = calendar.leading_cells();
+        let previous_days = neighbours.previous.days_amount();
+        let last_cell = leading_cells + calendar.days_amount();
+        let adjacent_days = (0..leading_cells)
+            .map(|cell| (cell, neighbours.previous, previous_days - leading_cells + cell))
+            .chain(
+                (0..calendar.trailing_cells()).map(|day| (last_cell + day, neighbours.next, day)),
+            );
+        for (cell, month, day) in adjacent_days {
+            let text = (day + 1).to_string();
+            let _ = Text::with_text_style(
+                &text,
+                cell_pos(cell),
+                if month.day0_kind(day).is_day_off() {
+                    ADJACENT_DAY_STYLE_RED
+                } else {
+                    ADJACENT_DAY_STYLE_BLACK
                 },
+                TextStyle::with_alignment(Alignment::Center),
             )
-            .into_styled(HIGHLIGHT_STYLE)
             .draw(display)?;
         }
     }
 
     let month = calendar.month();
     let year = calendar.year().to_string();
-    let month_name = month.name();
+    let month_name = layout.locale.month_name(month);
 
     let month_name_pos = Point::new(4, 19);
     let year_pos = Point::new(116, 19);
     let _ = Text::with_text_style(
         month_name,
         month_name_pos,
-        STYLE_BLACK_18,
+        MONTH_NAME_STYLE,
         TextStyle::with_alignment(Alignment::Left),
     )
     .draw(display)?;
@@ -109,6 +285,93 @@ pub async fn draw_calendar<D: DrawTarget<Color = TriColor>>(
         TextStyle::with_alignment(Alignment::Left),
     )
     .draw(display)?;
+    // Right of the year, over the last grid column
+    let indicators_pos = Point::new(
+        days_grid_anchor.x + column_spacing.x * 6 + column_spacing.x / 2,
+        month_name_pos.y,
+    );
+    draw_indicators(indicators, indicators_pos, display)?;
+
+    let display_size = display.bounding_box().size;
+    let display_width = display_size.width as i32;
+    let temperature = temperature.filter(|_| layout.temperature);
+    let panel_height = match temperature {
+        Some(_) => display_size.height as i32 - TEMPERATURE_LINE_HEIGHT,
+        None => display_size.height as i32,
+    };
+    match layout.side_panel {
+        SidePanel::Agenda if !agenda.is_empty() => {
+            let agenda_anchor = Point::new(display_width - AGENDA_WIDTH, 0);
+            draw_agenda(agenda, layout.locale, agenda_anchor, panel_height, display)?;
+        }
+        SidePanel::Agenda | SidePanel::MiniCalendars => {
+            let mini_anchor = Point::new(display_width - MINI_CALENDAR_WIDTH, 0);
+            if panel_height > MINI_CALENDAR_HEIGHT * 2 {
+                draw_mini_calendar(&neighbours.previous, layout.locale, mini_anchor, display)?;
+                draw_mini_calendar(
+                    &neighbours.next,
+                    layout.locale,
+                    mini_anchor + Point::new(0, MINI_CALENDAR_HEIGHT + 1),
+                    display,
+                )?;
+            } else {
+                // Only one fits, the month ahead is the one to plan for
+                draw_mini_calendar(&neighbours.next, layout.locale, mini_anchor, display)?;
+            }
+        }
+        SidePanel::None => {}
+    }
+    if let Some(temperature) = temperature {
+        let pos = Point::new(
+            display_width - 4,
+            display_size.height as i32 - TEMPERATURE_STYLE.font.character_size.height as i32
+                + TEMPERATURE_STYLE.font.baseline as i32,
+        );
+        let _ = Text::with_text_style(
+            temperature,
+            pos,
+            TEMPERATURE_STYLE,
+            TextStyle::with_alignment(Alignment::Right),
+        )
+        .draw(display)?;
+    }
 
     Ok(())
 }
+
+/// Draw the `message` shown instead of the calendar when the date is not known
+pub fn draw_time_unknown<D: DrawTarget<Color = TriColor>>(
+    message: &str,
+    indicators: &str,
+    display: &mut D,
+) -> Result<(), D::Error> {
+    let bounds = display.bounding_box();
+    let _ = Text::with_text_style(
+        message,
+        bounds.center(),
+        TIME_UNKNOWN_STYLE,
+        TextStyle::with_alignment(Alignment::Center),
+    )
+    .draw(display)?;
+    let indicators_pos = Point::new(bounds.size.width as i32 - 4, 19);
+    draw_indicators(indicators, indicators_pos, display)
+}
+
+/// Draw the letters of the failed subsystems right-aligned at `pos`, nothing when none failed
+fn draw_indicators<D: DrawTarget<Color = TriColor>>(
+    indicators: &str,
+    pos: Point,
+    display: &mut D,
+) -> Result<(), D::Error> {
+    if indicators.is_empty() {
+        return Ok(());
+    }
+    let _ = Text::with_text_style(
+        &format!("!{indicators}"),
+        pos,
+        INDICATOR_STYLE,
+        TextStyle::with_alignment(Alignment::Right),
+    )
+    .draw(display)?;
+    Ok(())
+}
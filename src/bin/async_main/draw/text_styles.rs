@@ -1,8 +1,12 @@
 use embedded_graphics::mono_font::MonoTextStyle;
 use paste::paste;
+use u8g2_fonts::U8g2TextStyle;
 use weact_studio_epd::TriColor;
 
 pub type StyleType = MonoTextStyle<'static, TriColor>;
+/// Style for localized text. Profont only has latin glyphs, so text that can be in other scripts
+/// uses the X11 fixed fonts, they cover cyrillic including the kazakh letters.
+pub type LocaleStyleType = U8g2TextStyle<TriColor>;
 
 macro_rules! make_styles_color {
     ($color:expr, $color_ident:ident, [$($size:literal),+]) => {
@@ -24,6 +28,40 @@ macro_rules! make_styles {
 }
 
 make_styles!(
-    [(TriColor::Black, BLACK), (TriColor::Red, RED)],
+    [(TriColor::Black, BLACK), (TriColor::Red, RED), (TriColor::White, WHITE)],
     [7, 9, 10, 12, 14, 18, 24]
 );
+
+macro_rules! make_locale_styles_color {
+    ($color:expr, $color_ident:ident, [$(($size:literal, $font:ident)),+]) => {
+        $(
+            paste! {
+                #[allow(dead_code)]
+                pub const [<LOCALE_STYLE_ $color_ident _ $size>]: LocaleStyleType = LocaleStyleType::new(u8g2_fonts::fonts::$font, $color);
+            }
+        )+
+    }
+}
+
+macro_rules! make_locale_styles {
+    ([$(($color:expr, $color_ident:ident)),+], $sizes:tt) => {
+        $(
+            make_locale_styles_color!($color, $color_ident, $sizes);
+        )+
+    }
+}
+
+// Closest matches to the profont sizes with the same name. The smaller fixed fonts lack some of
+// the kazakh letters, and there is nothing bigger than 10x20 that has them.
+make_locale_styles!(
+    [(TriColor::Black, BLACK), (TriColor::Red, RED), (TriColor::White, WHITE)],
+    [
+        (7, u8g2_font_6x12_t_cyrillic),
+        (9, u8g2_font_6x13_t_cyrillic),
+        (10, u8g2_font_7x13_t_cyrillic),
+        (12, u8g2_font_9x15_t_cyrillic),
+        (14, u8g2_font_10x20_t_cyrillic),
+        (18, u8g2_font_10x20_t_cyrillic),
+        (24, u8g2_font_10x20_t_cyrillic)
+    ]
+);
@@ -0,0 +1,183 @@
+//! Errors of the subsystems, and of the app as a whole
+//!
+//! None of them stop the daily refresh. It falls back to other data for what failed, and the
+//! failures are drawn as small indicators on the screen.
+
+use core::fmt::Write;
+
+use embassy_net::udp::BindError;
+use embedded_storage::nor_flash::ErrorType;
+use esp_storage::FlashStorage;
+use esp32_epaper_calendar::{days_off_store::StoreError, sntp::SntpError};
+use heapless::{String, Vec};
+use log::error;
+
+use crate::time::RtcClockError;
+
+/// Most refreshes have no issues, one per subsystem is plenty
+const MAX_ISSUES: usize = 8;
+/// The letters of all the issues and a sync age like ` 12d`
+const INDICATORS_LEN: usize = MAX_ISSUES + 8;
+
+/// Letter of the time failures, the sync age is shown after it
+const TIME_INDICATOR: char = 'T';
+
+pub type FlashError = <FlashStorage as ErrorType>::Error;
+
+#[derive(Debug)]
+pub enum TimeError {
+    // The fields are used when debug-printing on error
+    Rtc(#[allow(dead_code)] RtcClockError),
+    /// The network didn't come up in time for NTP
+    NetworkDown,
+    /// None of the NTP servers could be resolved
+    NtpUnresolved,
+    NtpBind(#[allow(dead_code)] BindError),
+    /// No NTP server gave a valid time, the last one failed with this error
+    Ntp(#[allow(dead_code)] SntpError),
+    /// The NTP time is out of the range of the RTC
+    NtpOutOfRange,
+    /// The seconds of the RTC didn't tick
+    RtcStopped,
+    /// The RTC lost its time, its oscillator stopped, and it wasn't synchronized since
+    RtcNotSet,
+    /// Neither the RTC nor NTP gave the time since boot
+    Unknown,
+}
+
+#[derive(Debug)]
+pub enum HolidaysError {
+    /// The stale or offline days off of the year are shown instead
+    Fetch {
+        #[allow(dead_code)]
+        year: u16,
+        #[allow(dead_code)]
+        error: NetworkError,
+    },
+    Store(#[allow(dead_code)] StoreError<FlashError>),
+    /// The partition table has no partition for the days off store
+    NoPartition,
+}
+
+#[derive(Debug)]
+pub enum DisplayError {
+    Epd(#[allow(dead_code)] display_interface::DisplayError),
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Http(#[allow(dead_code)] reqwless::Error),
+    /// The server answered with an unexpected HTTP status code
+    Status(#[allow(dead_code)] u16),
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    Time(TimeError),
+    Holidays(HolidaysError),
+    Display(DisplayError),
+    Network(NetworkError),
+}
+
+impl AppError {
+    /// Letter the failed subsystem is shown with on the screen
+    pub const fn indicator(&self) -> char {
+        match self {
+            Self::Time(_) => TIME_INDICATOR,
+            Self::Holidays(_) => 'H',
+            Self::Display(_) => 'D',
+            Self::Network(_) => 'N',
+        }
+    }
+}
+
+impl From<RtcClockError> for TimeError {
+    fn from(e: RtcClockError) -> Self {
+        Self::Rtc(e)
+    }
+}
+
+impl From<StoreError<FlashError>> for HolidaysError {
+    fn from(e: StoreError<FlashError>) -> Self {
+        Self::Store(e)
+    }
+}
+
+impl From<display_interface::DisplayError> for DisplayError {
+    fn from(e: display_interface::DisplayError) -> Self {
+        Self::Epd(e)
+    }
+}
+
+impl From<reqwless::Error> for NetworkError {
+    fn from(e: reqwless::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl From<TimeError> for AppError {
+    fn from(e: TimeError) -> Self {
+        Self::Time(e)
+    }
+}
+
+impl From<HolidaysError> for AppError {
+    fn from(e: HolidaysError) -> Self {
+        Self::Holidays(e)
+    }
+}
+
+impl From<DisplayError> for AppError {
+    fn from(e: DisplayError) -> Self {
+        Self::Display(e)
+    }
+}
+
+impl From<NetworkError> for AppError {
+    fn from(e: NetworkError) -> Self {
+        Self::Network(e)
+    }
+}
+
+/// What failed during a refresh
+#[derive(Debug, Default)]
+pub struct Issues {
+    errors: Vec<AppError, MAX_ISSUES>,
+    /// Seconds since the time was last synchronized, shown with the time failures
+    sync_age: Option<u64>,
+}
+
+impl Issues {
+    /// Log the error and keep it to be shown
+    pub fn push(&mut self, e: impl Into<AppError>) {
+        let e = e.into();
+        error!("{e:?}");
+        // The indicators of the first ones are enough
+        let _ = self.errors.push(e);
+    }
+
+    pub fn set_sync_age(&mut self, sync_age: Option<u64>) {
+        self.sync_age = sync_age;
+    }
+
+    /// Get the indicator letters of the failed subsystems, once each, and how long ago the time
+    /// was synchronized when that failed
+    pub fn indicators(&self) -> String<INDICATORS_LEN> {
+        let mut res = String::new();
+        for e in &self.errors {
+            if !res.contains(e.indicator()) {
+                let _ = res.push(e.indicator());
+            }
+        }
+        if let Some(age) = self.sync_age
+            && res.contains(TIME_INDICATOR)
+        {
+            let hours = age / 3600;
+            let _ = match hours {
+                0..48 => write!(res, " {hours}h"),
+                _ => write!(res, " {}d", hours / 24),
+            };
+        }
+        res
+    }
+}
@@ -0,0 +1,88 @@
+//! Events from an iCalendar feed, like the ICS export of a shared calendar
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use chrono::{Days, NaiveDate};
+use embedded_io_async::Read;
+use esp32_epaper_calendar::ical::{Event, IcalParser};
+use log::{error, info, warn};
+use reqwless::request::Method;
+
+use crate::{HttpClientConcrete, error::NetworkError};
+
+/// URL of the iCalendar feed, set by an `ICAL_URL` line in the `wifi-creds` file. Events are not
+/// shown without it.
+///
+/// Feed URLs usually contain a secret token, so it's kept with the other credentials. `https` URLs
+/// need the `TLS_CA_DER` of the server.
+pub const ICAL_URL: Option<&str> = option_env!("ICAL_URL");
+
+/// How far ahead events are listed in the agenda
+pub const AGENDA_HORIZON: Days = Days::new(31);
+
+/// Limits the heap used for the events of big calendars
+pub const MAX_EVENTS: usize = 64;
+
+/// Get the events of every configured calendar that can take place on the `window` dates
+///
+/// Calendars that fail to load are left out, the error of the last one is returned along with the
+/// events of the others.
+pub async fn get_events(
+    client: &mut HttpClientConcrete,
+    window: Range<NaiveDate>,
+) -> (Vec<Event>, Result<(), NetworkError>) {
+    let mut events = Vec::new();
+    let mut res = Ok(());
+    if let Some(url) = ICAL_URL {
+        match fetch_events(client, url, window.clone()).await {
+            Ok(feed_events) => events.extend(feed_events),
+            Err(e) => {
+                error!("Failed to fetch the iCalendar feed: {e:?}");
+                res = Err(e);
+            }
+        }
+    }
+    #[cfg(feature = "caldav")]
+    if let Some(url) = crate::caldav::CALDAV_URL {
+        match crate::caldav::fetch_events(client, url, window).await {
+            Ok(caldav_events) => events.extend(caldav_events),
+            Err(e) => {
+                error!("Failed to query the CalDAV calendar: {e:?}");
+                res = Err(e);
+            }
+        }
+    }
+    (events, res)
+}
+
+/// Fetch the events of the feed that can take place on the `window` dates
+pub async fn fetch_events(
+    client: &mut HttpClientConcrete,
+    url: &str,
+    window: Range<NaiveDate>,
+) -> Result<Vec<Event>, NetworkError> {
+    info!("Fetching iCalendar feed");
+    let mut rx_buf = [0; 4096];
+    let mut request = client.request(Method::GET, url).await?;
+    let response = request.send(&mut rx_buf).await?;
+    if !response.status.is_successful() {
+        warn!("Unexpected status code: {}", response.status.0);
+        return Err(NetworkError::Status(response.status.0));
+    }
+
+    // Feeds can be much bigger than the memory, so they are parsed while being received
+    let mut parser = IcalParser::new(window, MAX_EVENTS);
+    let mut reader = response.body().reader();
+    let mut chunk = [0; 512];
+    loop {
+        let len = reader.read(&mut chunk).await?;
+        if len == 0 {
+            break;
+        }
+        parser.push(&chunk[..len]);
+    }
+    let events = parser.finish();
+    info!("Got {} events", events.len());
+    Ok(events)
+}
@@ -3,13 +3,20 @@
 
 use core::cell::RefCell;
 
-use calendar_utils::CalendarMonth;
-use chrono::{Days, NaiveTime};
+use chrono::{DateTime, Days, Months, NaiveTime, Utc};
+use days_off::{
+    get_months_triplet, load_cache, populate_cache, rotate_cache, update_days_off_mask,
+};
 use display_interface_spi::SPIInterface;
-use draw::draw_calendar;
+use draw::{
+    AGENDA_MAX_ENTRIES, CalendarExtras, CalendarLayout, NeighbourMonths, SidePanel, draw_calendar,
+    draw_time_unknown,
+};
 use ds323x::{Ds323x, ic::DS3231, interface::I2cInterface};
 use embassy_embedded_hal::shared_bus::{asynch::spi::SpiDevice, blocking::i2c::I2cDevice};
 use embassy_executor::Spawner;
+#[cfg(not(feature = "deep-sleep"))]
+use embassy_futures::select::select;
 use embassy_net::{
     DhcpConfig, StackResources,
     dns::DnsSocket,
@@ -19,8 +26,26 @@ use embassy_sync::{
     blocking_mutex::{self, raw::CriticalSectionRawMutex},
     mutex::Mutex,
 };
+use embassy_time::Instant;
+#[cfg(not(feature = "deep-sleep"))]
 use embassy_time::Timer;
+use error::{DisplayError, Issues, TimeError};
 use esp_backtrace as _;
+#[cfg(feature = "tls")]
+use esp32_epaper_calendar::http::{TLS_READ_BUFFER_SIZE, TlsConfig};
+use esp32_epaper_calendar::{
+    calendar_utils::{CalendarMonth, MonthDate, WeekConfig},
+    http::HttpClient,
+    ical::{event_days, upcoming},
+    isdayoff::{IsdayoffProvider, TargetCountry},
+    locale::Locale,
+    posix_tz::PosixTz,
+    refresh_schedule::next_refresh,
+    sleep_state::SleepState,
+    temperature_log::{TemperatureLog, quarters_from_celsius},
+};
+#[cfg(feature = "deep-sleep")]
+use esp32_epaper_calendar::power_budget::DEVKIT;
 use esp_hal::{
     Async, Blocking,
     clock::CpuClock,
@@ -32,13 +57,17 @@ use esp_hal::{
     spi::master::{Config, Spi, SpiDmaBus},
     time::RateExtU32,
 };
+#[cfg(feature = "deep-sleep")]
+use esp_hal::rtc_cntl::{Rtc, wakeup_cause};
 use esp_hal_embassy::main;
 use esp_wifi::{EspWifiController, wifi::WifiStaDevice};
-use isdayoff::{HttpClientConcrete, update_days_off_mask};
+use ical::{AGENDA_HORIZON, get_events};
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
-use reqwless::client::HttpClient;
-use time::{RTC_CLOCK, get_local_rtc_time, synchronize_ntp_time_to_rtc};
+use time::{
+    RTC_CLOCK, TimeKeeper, TimeQuality, clear_rtc_alarm, get_rtc_temperature, set_rtc_alarm,
+    synchronize_ntp_time_to_rtc,
+};
 
 extern crate alloc;
 
@@ -46,12 +75,19 @@ use weact_studio_epd::{
     TriColor, WeActStudio290TriColorDriver,
     graphics::{Display290TriColor, DisplayRotation},
 };
+#[cfg(feature = "deep-sleep")]
+use wifi::stop_wifi;
 use wifi::{connection_handler_task, net_runner_task};
 
-mod calendar_utils;
+#[cfg(feature = "caldav")]
+mod caldav;
+mod days_off;
 mod draw;
-#[cfg(feature = "isdayoff")]
-mod isdayoff;
+mod error;
+#[cfg(feature = "ical")]
+mod ical;
+#[cfg(feature = "deep-sleep")]
+mod sleep;
 mod time;
 mod wifi;
 
@@ -63,6 +99,67 @@ pub type Ds323xTypeConcrete = Ds323x<
     DS3231,
 >;
 pub type RtcDs323x = blocking_mutex::Mutex<CriticalSectionRawMutex, RefCell<Ds323xTypeConcrete>>;
+pub type TcpClientConcrete = TcpClient<'static, 1, 4096, 4096>;
+pub type HttpClientConcrete = HttpClient<'static, TcpClientConcrete, DnsSocket<'static>>;
+
+/// Change this value to change the first day of the week and what days are the weekend
+///
+/// Used for the calendar grid layout and the default days off
+const WEEK_CONFIG: WeekConfig = WeekConfig::ISO;
+
+/// Change this value to change where the days off are fetched from
+///
+/// isdayoff only covers Belarus, Kazakhstan, Russia and Ukraine. For other countries use Nager.Date
+/// with the ISO code of the country, like `NagerProvider::new("DE").with_subdivision("DE-BY")`,
+/// changing the type of the constant too. With the `tls` feature the days off are fetched over
+/// `https`, from a server verified by [`TLS_CA`].
+const DAYS_OFF_PROVIDER: IsdayoffProvider = IsdayoffProvider::new(TargetCountry::Russia);
+
+/// Change this value to change how old the cached days off get before they are fetched again
+///
+/// Governments do move holidays mid-year. The cached days off are still used while the refresh
+/// fails.
+const DAYS_OFF_REVALIDATE_AFTER: Days = Days::new(30);
+
+/// Change this value to change the calendar language and what optional parts of it are drawn
+const CALENDAR_LAYOUT: CalendarLayout = CalendarLayout {
+    locale: Locale::Russian,
+    week_numbers: true,
+    side_panel: SidePanel::Agenda,
+    adjacent_days: true,
+    temperature: true,
+};
+
+/// Change this value to change the local times of the day the calendar is refreshed at
+///
+/// One is a bit after midnight so the RTC is surely on the new day. Times skipped by a DST
+/// transition are moved forward by the skip, repeated ones only refresh the first time.
+const REFRESH_TIMES: &[NaiveTime] = &[NaiveTime::from_hms_opt(0, 0, 5).unwrap()];
+/// How soon the refresh is tried again when the time is unknown or the display failed
+const RETRY_AFTER_SECS: i64 = 10 * 60;
+/// The refresh is woken by the RTC alarm, the uptime timer only wakes it this much later in case
+/// the alarm doesn't come
+const ALARM_FALLBACK_SECS: u64 = 60;
+/// The timer of the deep sleep runs on an RC oscillator that can be a few percent off, so it only
+/// wakes the refresh this share of the wait later
+#[cfg(feature = "deep-sleep")]
+const SLEEP_CLOCK_TOLERANCE_DIV: u64 = 16;
+/// How often the temperature of the room is read from the RTC. In deep sleep a reading is a wake-up
+/// of its own, a short one without the Wi-Fi and the display, so it's taken less often.
+#[cfg(not(feature = "deep-sleep"))]
+const TEMPERATURE_INTERVAL_SECS: u64 = 10 * 60;
+#[cfg(feature = "deep-sleep")]
+const TEMPERATURE_INTERVAL_SECS: u64 = 30 * 60;
+
+/// CA certificate the `https` servers are verified with, DER encoded. Set by a `TLS_CA_DER` line
+/// in the `wifi-creds` file with the path of the certificate, the build fails without it.
+///
+/// Only TLS 1.3 servers are supported.
+#[cfg(feature = "tls")]
+const TLS_CA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/tls_ca.der"));
+/// Fits the requests, the CalDAV query is the biggest one
+#[cfg(feature = "tls")]
+const TLS_WRITE_BUFFER_SIZE: usize = 4096;
 
 macro_rules! mk_static {
     ($t:ty,$val:expr) => {{
@@ -92,6 +189,76 @@ async fn main(spawner: Spawner) {
 
     info!("Embassy initialized!");
 
+    info!("Initializing I2C");
+
+    let i2c_bus = mk_static!(I2cBusMutex, {
+        let i2c_bus = I2c::new(
+            peripherals.I2C0,
+            i2c::master::Config::default().with_frequency(400_u32.kHz()),
+        )
+        .unwrap()
+        .with_sda(peripherals.GPIO11)
+        .with_scl(peripherals.GPIO12);
+        blocking_mutex::Mutex::<CriticalSectionRawMutex, _>::new(RefCell::new(i2c_bus))
+    });
+    let i2c_dev_ds323x = I2cDevice::new(&*i2c_bus);
+
+    info!("Initializing DS3231 external RTC");
+
+    // At this point the RTC_CLOCK is not yet initialized, guranteed to be initialized HERE. Any
+    // usage must be AFTER this.
+    let rtc = RTC_CLOCK.get_or_init(|| {
+        let mut rtc = Ds323x::new_ds3231(i2c_dev_ds323x);
+        // Reading the time fails too if the RTC is not there, that's handled by the refresh
+        if let Err(e) = rtc.enable().and_then(|()| rtc.disable_32khz_output()) {
+            error!("Failed to set up the RTC: {e:?}");
+        }
+        if let Ok(true) = rtc.has_been_stopped() {
+            warn!("RTC lost its time, it's not used until the time is synchronized");
+        }
+        blocking_mutex::Mutex::new(RefCell::new(rtc))
+    });
+    // The INT/SQW output of the RTC is open drain, low while the alarm is on
+    #[cfg_attr(feature = "deep-sleep", allow(unused_mut))]
+    let mut rtc_alarm = Input::new(peripherals.GPIO13, Pull::Up);
+
+    #[cfg(feature = "deep-sleep")]
+    let mut lp_rtc = Rtc::new(peripherals.LPWR);
+    #[cfg(feature = "deep-sleep")]
+    let (state, clock_offset) = {
+        info!("Woken by {:?}", wakeup_cause());
+        // The sleep clock at the start of the uptime
+        let offset = sleep::sleep_clock_secs(&lp_rtc).saturating_sub(Instant::now().as_secs());
+        (sleep::load_state(), offset)
+    };
+    // Staying awake, the state is the one of a fresh boot
+    #[cfg(not(feature = "deep-sleep"))]
+    let (state, clock_offset) = (SleepState::default(), 0);
+    // Seconds of the clock the refreshes and the time synchronizations are scheduled on
+    let now_secs = move || clock_offset + Instant::now().as_secs();
+
+    // A wake-up for the temperature alone is over before the Wi-Fi and the display are started
+    #[cfg(feature = "deep-sleep")]
+    if !(state.alarm_set && rtc_alarm.is_low())
+        && now_secs() < state.refresh_at
+        && !state.sync.is_due(now_secs())
+    {
+        let mut state = state;
+        read_temperature(&mut state.temperatures, &mut TimeKeeper::default());
+        state.temperature_at = now_secs() + TEMPERATURE_INTERVAL_SECS;
+        let wake_at = state.refresh_at.min(state.sync.next_at()).min(state.temperature_at);
+        sleep::deep_sleep(&mut lp_rtc, &state, wake_at, rtc_alarm);
+    }
+
+    info!("Loading days off cache from flash");
+    let current_month = TimeKeeper::default()
+        .local_time()
+        .ok()
+        .map(|time| MonthDate::new_from_date(time.date_naive()));
+    if let Err(e) = load_cache(&DAYS_OFF_PROVIDER, current_month).await {
+        error!("Starting with an empty days off cache: {e:?}");
+    }
+
     info!("RNG init");
 
     let mut rng = Rng::new(peripherals.RNG);
@@ -135,34 +302,10 @@ async fn main(spawner: Spawner) {
     });
     let dns_socket = mk_static!(DnsSocket<'static>, DnsSocket::new(net_stack));
 
-    let http_client = mk_static!(HttpClientConcrete, {
-        HttpClient::new(&*tcp_client, &*dns_socket)
-    });
-
-    info!("Initializing I2C");
-
-    let i2c_bus = mk_static!(I2cBusMutex, {
-        let i2c_bus = I2c::new(
-            peripherals.I2C0,
-            i2c::master::Config::default().with_frequency(400_u32.kHz()),
-        )
-        .unwrap()
-        .with_sda(peripherals.GPIO11)
-        .with_scl(peripherals.GPIO12);
-        blocking_mutex::Mutex::<CriticalSectionRawMutex, _>::new(RefCell::new(i2c_bus))
-    });
-    let i2c_dev_ds323x = I2cDevice::new(&*i2c_bus);
-
-    info!("Initializing DS3231 external RTC");
-
-    // At this point the RTC_CLOCK is not yet initialized, guranteed to be initialized HERE. Any
-    // usage must be AFTER this.
-    let rtc = RTC_CLOCK.get_or_init(|| {
-        let mut rtc = Ds323x::new_ds3231(i2c_dev_ds323x);
-        rtc.enable().unwrap();
-        rtc.disable_32khz_output().unwrap();
-        blocking_mutex::Mutex::new(RefCell::new(rtc))
-    });
+    let http_client = mk_static!(
+        HttpClientConcrete,
+        new_http_client(tcp_client, dns_socket, &mut rng)
+    );
 
     info!("Initializing spi pins");
 
@@ -205,7 +348,12 @@ async fn main(spawner: Spawner) {
     info!("Initializing epd");
 
     let mut driver = WeActStudio290TriColorDriver::new(spi_interface, busy_in, rst, delay);
-    driver.init().await.unwrap();
+    // Tried again before each refresh until it works
+    let mut epd_ready = driver
+        .init()
+        .await
+        .inspect_err(|e| error!("Failed to initialize the epd: {e:?}"))
+        .is_ok();
 
     info!("buffer init");
 
@@ -219,44 +367,287 @@ async fn main(spawner: Spawner) {
 
     info!("Loop starting");
 
+    let SleepState {
+        sync: mut sync_scheduler,
+        drift,
+        mut temperatures,
+        mut temperature_at,
+        mut refresh_at,
+        mut alarm_set,
+    } = state;
+    let mut time_keeper = TimeKeeper::with_drift(drift);
+    // Failure of the last time synchronization, shown by the next refresh
+    let mut sync_error = None;
+
     loop {
-        // todo: time sync
-        // todo: fetch and use isdayoff
-
-        info!("NTP time sync");
-        synchronize_ntp_time_to_rtc(net_stack).await;
-
-        info!("Getting time");
-        let local_time = get_local_rtc_time().unwrap();
-        let mut calendar = CalendarMonth::from_date(local_time.date_naive());
-
-        info!("Getting isdayoff data");
-        update_days_off_mask(http_client, &mut calendar)
-            .await
-            .unwrap();
-
-        info!("Drawing calendar");
-        display.clear(TriColor::White);
-        draw_calendar(&local_time, calendar, &mut display)
-            .await
-            .unwrap();
-        driver.wake_up().await.unwrap();
-        driver.full_update(&display).await.unwrap();
-        driver.sleep().await.unwrap();
-
-        info!("Getting time and sleeping");
-        let local_time = get_local_rtc_time().unwrap();
-
-        // Wait until 00:00:05 of the next day
-        let wait_time = ((local_time + Days::new(1))
-            .with_time(NaiveTime::from_hms_opt(0, 0, 5).unwrap())
-            .unwrap()
-            - local_time)
-            .num_seconds();
-
-        Timer::after_secs(wait_time.try_into().unwrap()).await;
-        info!("Wake up from waiting");
+        if now_secs() >= temperature_at {
+            read_temperature(&mut temperatures, &mut time_keeper);
+            temperature_at = now_secs() + TEMPERATURE_INTERVAL_SECS;
+        }
+
+        if sync_scheduler.is_due(now_secs()) {
+            info!("NTP time sync");
+            match synchronize_ntp_time_to_rtc(net_stack, &mut time_keeper).await {
+                Ok(rtc_error) => {
+                    sync_scheduler.succeeded(now_secs(), rtc_error);
+                    sync_error = None;
+                }
+                Err(e) => {
+                    sync_scheduler.failed(now_secs(), rng.random());
+                    sync_error = Some(e);
+                }
+            }
+            info!(
+                "Next NTP time sync in {} s",
+                sync_scheduler.next_at().saturating_sub(now_secs())
+            );
+        }
+
+        if alarm_set && rtc_alarm.is_low() {
+            info!("RTC alarm went off");
+            alarm_set = false;
+            // A failure only keeps the pin low until the next alarm is set
+            let _ = clear_rtc_alarm();
+            refresh_at = now_secs();
+        }
+
+        if now_secs() >= refresh_at {
+            let mut issues = Issues::default();
+            if let Some(e) = sync_error.take() {
+                issues.push(e);
+            }
+            let sync_age = sync_scheduler.sync_age(now_secs());
+            match sync_age {
+                Some(age) => info!("Time last synchronized {age} s ago"),
+                None => warn!("Time not synchronized since the boot"),
+            }
+            issues.set_sync_age(sync_age);
+
+            info!("Getting time");
+            let local_time = time_keeper.local_time().or_else(|e| {
+                issues.push(e);
+                warn!("Using the time estimated since it was last known");
+                time_keeper.estimate().ok_or(TimeError::Unknown)
+            });
+            let quality = time_keeper.quality(sync_age);
+            info!("Time quality: {quality:?}");
+
+            display.clear(TriColor::White);
+            // Nothing useful is on the screen until the time is known
+            let mut retry_soon = local_time.is_err();
+            match local_time {
+                Ok(local_time) => {
+                    info!("Drawing calendar");
+                    let temperature = temperatures.label(Some(local_time.naive_utc()));
+                    draw_daily(
+                        http_client,
+                        &local_time,
+                        temperature.as_deref(),
+                        &mut issues,
+                        &mut display,
+                    )
+                    .await;
+                }
+                Err(e) => {
+                    issues.push(e);
+                    info!("Drawing time unknown screen");
+                    // Rather than a calendar of the year 2000
+                    let message = match quality {
+                        TimeQuality::Invalid => CALENDAR_LAYOUT.locale.time_not_set_label(),
+                        _ => CALENDAR_LAYOUT.locale.time_unknown_label(),
+                    };
+                    let Ok(()) = draw_time_unknown(
+                        message,
+                        &issues.indicators(),
+                        &mut display,
+                    );
+                }
+            }
+            // Everything is fetched, the long e-paper update goes without the Wi-Fi
+            #[cfg(feature = "deep-sleep")]
+            stop_wifi().await;
+
+            let shown: Result<(), DisplayError> = async {
+                if !epd_ready {
+                    driver.init().await?;
+                    epd_ready = true;
+                }
+                driver.wake_up().await?;
+                driver.full_update(&display).await?;
+                driver.sleep().await?;
+                Ok(())
+            }
+            .await;
+            if let Err(e) = shown {
+                epd_ready = false;
+                retry_soon = true;
+                issues.push(e);
+            }
+
+            info!("Getting time of the next refresh");
+            let local_time = time_keeper
+                .local_time()
+                .ok()
+                .or_else(|| time_keeper.estimate());
+            let next = local_time.filter(|_| !retry_soon).and_then(|local_time| {
+                let now = local_time.with_timezone(&Utc);
+                Some((now, next_refresh(&now, REFRESH_TIMES, &local_time.timezone())?))
+            });
+            alarm_set = false;
+            refresh_at = match next {
+                Some((now, next)) => {
+                    let wait = (next - now).num_seconds().max(1) as u64;
+                    // The RTC keeps the UTC time, it's the one that tells when the day is over
+                    alarm_set = set_rtc_alarm(&next.naive_utc())
+                        .inspect_err(|_e| warn!("Waiting for the refresh without the RTC alarm"))
+                        .is_ok();
+                    #[cfg(feature = "deep-sleep")]
+                    let fallback_secs = ALARM_FALLBACK_SECS.max(wait / SLEEP_CLOCK_TOLERANCE_DIV);
+                    #[cfg(not(feature = "deep-sleep"))]
+                    let fallback_secs = ALARM_FALLBACK_SECS;
+                    if alarm_set {
+                        now_secs() + wait + fallback_secs
+                    } else {
+                        now_secs() + wait
+                    }
+                }
+                None => now_secs() + RETRY_AFTER_SECS as u64,
+            };
+        }
+
+        // Until the refresh, a time synchronization or a temperature reading, whichever is sooner
+        let wake_at = refresh_at
+            .min(sync_scheduler.next_at())
+            .min(temperature_at);
+        #[cfg(feature = "deep-sleep")]
+        {
+            stop_wifi().await;
+            let syncs_a_day = (24 * 3600_u64).div_ceil(sync_scheduler.interval_secs()) as u32;
+            let readings_a_day = (24 * 3600 / TEMPERATURE_INTERVAL_SECS) as u32;
+            info!(
+                "Estimated battery use {} uAh a day",
+                DEVKIT.daily_uah(REFRESH_TIMES.len() as u32, syncs_a_day, readings_a_day)
+            );
+            let state = SleepState {
+                sync: sync_scheduler,
+                drift: time_keeper.into_drift(),
+                temperatures,
+                temperature_at,
+                refresh_at,
+                alarm_set,
+            };
+            sleep::deep_sleep(&mut lp_rtc, &state, wake_at, rtc_alarm);
+        }
+        #[cfg(not(feature = "deep-sleep"))]
+        {
+            let timer = Timer::at(Instant::from_secs(wake_at));
+            if alarm_set {
+                select(rtc_alarm.wait_for_low(), timer).await;
+            } else {
+                timer.await;
+            }
+            info!("Wake up from waiting");
+        }
     }
 
     // for inspiration have a look at the examples at https://github.com/esp-rs/esp-hal/tree/v0.22.0/examples/src/bin
 }
+
+/// Draw the calendar of the month of `local_time` with its days off, events and the `temperature`
+///
+/// Data that fails to load is replaced by the cached or offline data, or left out.
+async fn draw_daily(
+    http_client: &mut HttpClientConcrete,
+    local_time: &DateTime<PosixTz>,
+    temperature: Option<&str>,
+    issues: &mut Issues,
+    display: &mut Display290TriColor,
+) {
+    let today = local_time.date_naive();
+    let current_month = MonthDate::new_from_date(today);
+    let mut calendars = get_months_triplet(current_month)
+        .map(|month| CalendarMonth::from_date(month.to_start_day_naive(), WEEK_CONFIG));
+
+    info!("Getting days off data");
+    rotate_cache(current_month).await;
+    if let Err(e) = populate_cache(http_client, &DAYS_OFF_PROVIDER, current_month, today).await {
+        issues.push(e);
+    }
+    for calendar in &mut calendars {
+        update_days_off_mask(&DAYS_OFF_PROVIDER, calendar, today).await;
+    }
+    let [previous, mut calendar, next] = calendars;
+
+    info!("Getting events");
+    let window_end = (current_month + Months::new(1))
+        .to_start_day_naive()
+        .max(today + AGENDA_HORIZON);
+    let (events, events_res) =
+        get_events(http_client, current_month.to_start_day_naive()..window_end).await;
+    if let Err(e) = events_res {
+        issues.push(e);
+    }
+    calendar.set_event_days(event_days(&events, current_month, local_time.timezone()));
+    let agenda = upcoming(
+        &events,
+        local_time.timezone(),
+        local_time.naive_local(),
+        AGENDA_HORIZON,
+        AGENDA_MAX_ENTRIES,
+    );
+
+    let Ok(()) = draw_calendar(
+        local_time,
+        calendar,
+        CalendarExtras {
+            neighbours: NeighbourMonths { previous, next },
+            agenda: &agenda,
+            indicators: &issues.indicators(),
+            temperature,
+        },
+        CALENDAR_LAYOUT,
+        display,
+    )
+    .await;
+}
+
+/// Read the temperature of the room from the RTC into the `log`, with the UTC time if it's known
+fn read_temperature(log: &mut TemperatureLog, time_keeper: &mut TimeKeeper) {
+    let Ok(celsius) = get_rtc_temperature()
+        .inspect_err(|e| warn!("Failed to read the RTC temperature: {e:?}"))
+    else {
+        return;
+    };
+    info!("Temperature {celsius} C");
+    let at = time_keeper
+        .local_time()
+        .ok()
+        .or_else(|| time_keeper.estimate())
+        .map(|time| time.naive_utc());
+    log.record(at, quarters_from_celsius(celsius));
+}
+
+/// Create the client of the web services, with `https`
+#[cfg(feature = "tls")]
+fn new_http_client(
+    tcp_client: &'static TcpClientConcrete,
+    dns_socket: &'static DnsSocket<'static>,
+    rng: &mut Rng,
+) -> HttpClientConcrete {
+    let mut seed = [0; 32];
+    rng.read(&mut seed);
+    let read_buffer = mk_static!([u8; TLS_READ_BUFFER_SIZE], [0; TLS_READ_BUFFER_SIZE]);
+    let write_buffer = mk_static!([u8; TLS_WRITE_BUFFER_SIZE], [0; TLS_WRITE_BUFFER_SIZE]);
+    let tls = TlsConfig::new(seed, read_buffer, write_buffer, TLS_CA);
+    HttpClient::new_with_tls(tcp_client, dns_socket, tls)
+}
+
+/// Create the client of the web services, `http` only
+#[cfg(not(feature = "tls"))]
+fn new_http_client(
+    tcp_client: &'static TcpClientConcrete,
+    dns_socket: &'static DnsSocket<'static>,
+    _rng: &mut Rng,
+) -> HttpClientConcrete {
+    HttpClient::new(tcp_client, dns_socket)
+}
@@ -0,0 +1,52 @@
+//! Deep sleep between the wake-ups, to run on a battery
+//!
+//! Only the RTC domain of the ESP32 stays powered in the deep sleep, the firmware boots again on
+//! every wake-up. What it needs to go on is kept in the RTC memory, the days off cache is in flash
+//! already. The INT/SQW pin of the DS3231 needs a pull-up resistor of its own, the one of the GPIO
+//! is off in the deep sleep.
+
+use core::time::Duration;
+
+use esp_hal::{
+    gpio::Input,
+    ram,
+    rtc_cntl::{
+        Rtc,
+        sleep::{Ext0WakeupSource, TimerWakeupSource, WakeupLevel},
+    },
+};
+use esp32_epaper_calendar::sleep_state::{STATE_LEN, SleepState};
+use log::{info, warn};
+
+#[ram(rtc_fast, persistent)]
+static mut SLEEP_STATE: [u8; STATE_LEN] = [0; STATE_LEN];
+
+/// Get the state the previous wake-up left, the one of a fresh boot after a power loss
+pub fn load_state() -> SleepState {
+    // Only the main task accesses it, at the start and right before the deep sleep
+    let buf = unsafe { (&raw const SLEEP_STATE).read() };
+    SleepState::decode(&buf).unwrap_or_else(|| {
+        warn!("No state in the RTC memory, starting fresh");
+        SleepState::default()
+    })
+}
+
+/// Get the seconds of the RTC timer of the ESP32, it keeps counting through the deep sleep
+pub fn sleep_clock_secs(rtc: &Rtc<'_>) -> u64 {
+    rtc.time_since_boot().to_secs()
+}
+
+/// Keep the `state` and deep sleep until `wake_at` of the sleep clock, or until the RTC alarm pulls
+/// `rtc_alarm` low when it's set
+pub fn deep_sleep(rtc: &mut Rtc<'_>, state: &SleepState, wake_at: u64, rtc_alarm: Input<'_>) -> ! {
+    unsafe { (&raw mut SLEEP_STATE).write(state.encode()) };
+    let secs = wake_at.saturating_sub(sleep_clock_secs(rtc)).max(1);
+    info!("Deep sleeping for {secs} s");
+    let timer = TimerWakeupSource::new(Duration::from_secs(secs));
+    if state.alarm_set {
+        let alarm = Ext0WakeupSource::new(rtc_alarm, WakeupLevel::Low);
+        rtc.sleep_deep(&[&timer, &alarm])
+    } else {
+        rtc.sleep_deep(&[&timer])
+    }
+}
@@ -1,25 +1,45 @@
 use core::{net::SocketAddr, ops::DerefMut};
 
-use chrono::{DateTime, NaiveDateTime, TimeDelta};
-use chrono_tz::Tz;
-use ds323x::DateTimeAccess;
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeDelta, Timelike};
+use ds323x::{Alarm1Matching, DateTimeAccess, DayAlarm1, Hours};
 use embassy_net::{
     udp::{PacketMetadata, UdpSocket},
     Stack,
 };
-use embassy_sync::once_lock::OnceLock;
-use log::error;
+use embassy_sync::{lazy_lock::LazyLock, once_lock::OnceLock};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+use esp32_epaper_calendar::{
+    posix_tz::PosixTz,
+    rtc_drift::DriftEstimator,
+    sntp::{self, MAX_SERVERS, NTP_PORT, Sample, SntpClock},
+};
+use log::{error, info, warn};
 use smoltcp::wire::DnsQueryType;
-use sntpc::{NtpContext, NtpTimestampGenerator};
 
-use crate::{Ds323xTypeConcrete, RtcDs323x};
+use crate::{Ds323xTypeConcrete, RtcDs323x, error::TimeError};
 
 pub static RTC_CLOCK: OnceLock<RtcDs323x> = OnceLock::new();
 
-/// Change this value to change the local timezone
+/// Local time zone as a POSIX TZ string, set by a `POSIX_TZ` line in the `wifi-creds` file, like
+/// `POSIX_TZ=CET-1CEST,M3.5.0,M10.5.0/3`. Moscow time without it.
 ///
 /// Used to synchronize the day roll-over time
-pub const LOCAL_TZ: chrono_tz::Tz = chrono_tz::Europe::Moscow;
+const POSIX_TZ: &str = match option_env!("POSIX_TZ") {
+    Some(tz) => tz,
+    None => "MSK-3",
+};
+
+static LOCAL_TZ: LazyLock<PosixTz> = LazyLock::new(|| {
+    POSIX_TZ.parse().unwrap_or_else(|e| {
+        error!("Invalid POSIX_TZ {POSIX_TZ:?}: {e:?}, using UTC");
+        PosixTz::UTC
+    })
+});
+
+/// Get the local time zone
+pub fn local_tz() -> PosixTz {
+    *LOCAL_TZ.get()
+}
 
 #[derive(Debug)]
 pub enum RtcClockError {
@@ -55,52 +75,168 @@ pub fn get_rtc_time() -> Result<NaiveDateTime, RtcClockError> {
     access_rtc_clock(|rtc| rtc.datetime())
 }
 
-/// Get local tiem from RTC
-pub fn get_local_rtc_time() -> Result<DateTime<Tz>, RtcClockError> {
-    get_rtc_time().map(|dt| dt.and_utc().with_timezone(&LOCAL_TZ))
+/// Set the RTC module time, it's valid from then on
+pub fn set_rtc_clock(new_datetime: &NaiveDateTime) -> Result<(), RtcClockError> {
+    access_rtc_clock(|rtc| {
+        rtc.set_datetime(new_datetime)?;
+        rtc.clear_has_been_stopped_flag()
+    })
 }
 
-/// Set the RTC module time
-pub fn set_rtc_clock(new_datetime: &NaiveDateTime) -> Result<(), RtcClockError> {
-    access_rtc_clock(|rtc| rtc.set_datetime(new_datetime))
+/// Check the oscillator stop flag of the RTC, it's set when the RTC lost power, even its backup
+/// battery, and its time is not valid until it's set again
+pub fn rtc_stopped() -> Result<bool, RtcClockError> {
+    access_rtc_clock(|rtc| rtc.has_been_stopped())
 }
 
-#[derive(Clone, Copy, Default)]
-pub struct TimestampGenerator {
-    timestamp: NaiveDateTime,
+/// Read the temperature sensor of the RTC in degrees Celsius, it's updated every 64 s
+pub fn get_rtc_temperature() -> Result<f32, RtcClockError> {
+    access_rtc_clock(|rtc| rtc.temperature())
+}
+
+/// Make the RTC pull its INT/SQW pin low at the UTC time `at`
+///
+/// Alarm 1 matches the day of the month too, so it can be set up to a month ahead. A time that
+/// already passed only fires a month later. The flag of the previous alarm is cleared, so the pin
+/// is released until this one fires.
+pub fn set_rtc_alarm(at: &NaiveDateTime) -> Result<(), RtcClockError> {
+    let alarm = DayAlarm1 {
+        day: at.day() as u8,
+        hour: Hours::H24(at.hour() as u8),
+        minute: at.minute() as u8,
+        second: at.second() as u8,
+    };
+    access_rtc_clock(|rtc| {
+        rtc.set_alarm1_day(alarm, Alarm1Matching::AllMatch)?;
+        rtc.clear_alarm1_matched_flag()?;
+        rtc.use_int_sqw_output_as_interrupt()?;
+        rtc.enable_alarm1_interrupts()
+    })
+}
+
+/// Release the INT/SQW pin after the alarm fired
+pub fn clear_rtc_alarm() -> Result<(), RtcClockError> {
+    access_rtc_clock(|rtc| rtc.clear_alarm1_matched_flag())
+}
+
+/// How far the time can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeQuality {
+    /// The RTC lost its time and it wasn't synchronized since
+    Invalid,
+    /// Kept by the RTC, it wasn't synchronized since the RTC was set before
+    RtcOnly,
+    /// Synchronized over NTP this many seconds ago
+    Synced { age: u64 },
+}
+
+/// Keeps the time going from the last known time while the RTC can't be read
+#[derive(Debug, Default)]
+pub struct TimeKeeper {
+    /// UTC time and the uptime it was known at, only from valid times
+    last_known: Option<(NaiveDateTime, Instant)>,
+    /// Drift of the RTC since the boot
+    drift: DriftEstimator,
+    /// The RTC had lost its time when it was last read
+    rtc_stopped: bool,
+}
+
+impl TimeKeeper {
+    /// Create a time keeper that goes on with the drift tracked before the deep sleep, a fresh
+    /// one after a plain boot
+    pub fn with_drift(drift: DriftEstimator) -> Self {
+        Self {
+            last_known: None,
+            drift,
+            rtc_stopped: false,
+        }
+    }
+
+    /// Get the drift tracked, to keep it over the deep sleep
+    #[cfg(feature = "deep-sleep")]
+    pub fn into_drift(self) -> DriftEstimator {
+        self.drift
+    }
+
+    /// Remember a time known from elsewhere, like NTP
+    pub fn set(&mut self, time: NaiveDateTime) {
+        self.last_known = Some((time, Instant::now()));
+    }
+
+    /// Get the local time from the RTC, unless it lost its time
+    pub fn local_time(&mut self) -> Result<DateTime<PosixTz>, TimeError> {
+        self.rtc_stopped = rtc_stopped()?;
+        if self.rtc_stopped {
+            return Err(TimeError::RtcNotSet);
+        }
+        let time = get_rtc_time()?;
+        self.set(time);
+        Ok(time.and_utc().with_timezone(&local_tz()))
+    }
+
+    /// Get how far the time can be trusted, `sync_age` is the seconds since the time was last
+    /// synchronized
+    pub const fn quality(&self, sync_age: Option<u64>) -> TimeQuality {
+        match sync_age {
+            Some(age) => TimeQuality::Synced { age },
+            None if self.rtc_stopped => TimeQuality::Invalid,
+            None => TimeQuality::RtcOnly,
+        }
+    }
+
+    /// Estimate the local time from the last known time and the uptime since, `None` if the time
+    /// was never known
+    pub fn estimate(&self) -> Option<DateTime<PosixTz>> {
+        let (time, known_at) = self.last_known?;
+        let elapsed = TimeDelta::microseconds(known_at.elapsed().as_micros() as i64);
+        Some((time + elapsed).and_utc().with_timezone(&local_tz()))
+    }
 }
 
-impl NtpTimestampGenerator for TimestampGenerator {
-    fn init(&mut self) {
-        self.timestamp = get_rtc_time().unwrap();
+/// Uptime clock the NTP offsets are measured from
+struct UptimeClock;
+
+impl SntpClock for UptimeClock {
+    fn now_micros(&self) -> u64 {
+        Instant::now().as_micros()
     }
 
-    fn timestamp_sec(&self) -> u64 {
-        self.timestamp.and_utc().timestamp().try_into().unwrap()
+    async fn timeout<F: Future>(&self, micros: u64, fut: F) -> Option<F::Output> {
+        with_timeout(Duration::from_micros(micros), fut).await.ok()
     }
 
-    fn timestamp_subsec_micros(&self) -> u32 {
-        self.timestamp.and_utc().timestamp_subsec_micros()
+    async fn sleep(&self, micros: u64) {
+        Timer::after_micros(micros).await;
     }
 }
 
-const NTP_SERVER_POOL: &[&str] = &["pool.ntp.org"];
-const NTP_PORT: u16 = 123;
+/// Every name is resolved to a single address, the pool gives different servers for them
+const NTP_SERVER_POOL: &[&str] = &["0.pool.ntp.org", "1.pool.ntp.org", "2.pool.ntp.org"];
+/// The RTC ticks every second, it's stopped if it doesn't within this time
+const RTC_TICK_TIMEOUT: Duration = Duration::from_millis(1100);
+/// How often the RTC is read while waiting for its tick, the error is measured with this accuracy
+const RTC_TICK_POLL: Duration = Duration::from_millis(2);
+/// How long to wait for the network to come up, so the calendar is still drawn without it
+const NETWORK_UP_TIMEOUT: Duration = Duration::from_secs(30);
 
-pub async fn try_resolve_from_pool(
+/// Resolve the addresses of the NTP servers of the pool, the names that fail are skipped
+pub async fn resolve_pool(
     stack: Stack<'_>,
     pool: &'static [&'static str],
-) -> Option<(
-    &'static str,
-    heapless::Vec<smoltcp::wire::IpAddress, { smoltcp::config::DNS_MAX_RESULT_COUNT }>,
-)> {
+) -> heapless::Vec<SocketAddr, MAX_SERVERS> {
+    let mut servers = heapless::Vec::new();
     for address in pool {
         match stack.dns_query(address, DnsQueryType::A).await {
             Ok(res) => {
                 if res.is_empty() {
                     log::warn!("No IP addresses returned for NTP server `{address}`");
-                } else {
-                    return Some((address, res));
+                }
+                for ip in res {
+                    let server = SocketAddr::from((ip, NTP_PORT));
+                    if !servers.contains(&server) {
+                        // The rest of the servers are not needed
+                        let _ = servers.push(server);
+                    }
                 }
             }
             Err(e) => {
@@ -108,13 +244,18 @@ pub async fn try_resolve_from_pool(
             }
         }
     }
-    None
+    servers
 }
 
-/// Get time from an NTP server
-pub async fn get_ntp_time(stack: Stack<'_>) -> Option<NaiveDateTime> {
-    stack.wait_config_up().await;
-    let (ntp_server_name, ntp_addresses) = try_resolve_from_pool(stack, NTP_SERVER_POOL).await?;
+/// Measure the offset of the uptime clock from the NTP servers
+pub async fn get_ntp_time(stack: Stack<'_>) -> Result<Sample, TimeError> {
+    with_timeout(NETWORK_UP_TIMEOUT, stack.wait_config_up())
+        .await
+        .map_err(|_| TimeError::NetworkDown)?;
+    let servers = resolve_pool(stack, NTP_SERVER_POOL).await;
+    if servers.is_empty() {
+        return Err(TimeError::NtpUnresolved);
+    }
 
     let mut rx_meta = [PacketMetadata::EMPTY; 16];
     let mut rx_buffer = [0; 4096];
@@ -128,38 +269,97 @@ pub async fn get_ntp_time(stack: Stack<'_>) -> Option<NaiveDateTime> {
         &mut tx_meta,
         &mut tx_buffer,
     );
-    socket.bind(NTP_PORT).unwrap();
-
-    let ntp_context = NtpContext::new(TimestampGenerator::default());
-
-    for address in ntp_addresses {
-        let ntp_result =
-            sntpc::get_time(SocketAddr::from((address, NTP_PORT)), &socket, ntp_context).await;
-
-        match ntp_result {
-            Ok(time) => {
-                let new_datetime = NaiveDateTime::UNIX_EPOCH
-                    + TimeDelta::new(
-                        time.sec().into(),
-                        sntpc::fraction_to_nanoseconds(time.sec_fraction()),
-                    )
-                    .unwrap();
-                return Some(new_datetime);
-            }
-            Err(e) => {
-                error!("Failed to synchronize time from server `{ntp_server_name}` at IP `{address}`: {e:?}");
-            }
+    socket.bind(NTP_PORT).map_err(TimeError::NtpBind)?;
+
+    let sample = sntp::synchronize(&socket, &servers, &UptimeClock)
+        .await
+        .inspect_err(|e| error!("Failed to synchronize time from servers {servers:?}: {e:?}"))
+        .map_err(TimeError::Ntp)?;
+    info!(
+        "NTP server {} is {} us off the uptime, {} us away",
+        sample.server, sample.offset, sample.delay
+    );
+    Ok(sample)
+}
+
+/// Measure how far the RTC is off the NTP time, at the tick of its seconds
+///
+/// Returns the UTC time of the tick and the RTC time minus it.
+async fn measure_rtc_error(sample: &Sample) -> Result<(NaiveDateTime, TimeDelta), TimeError> {
+    let start = get_rtc_time()?;
+    let give_up_at = Instant::now() + RTC_TICK_TIMEOUT;
+    while Instant::now() < give_up_at {
+        Timer::after(RTC_TICK_POLL).await;
+        let rtc_time = get_rtc_time()?;
+        if rtc_time != start {
+            let utc = sample
+                .utc_at(Instant::now().as_micros())
+                .ok_or(TimeError::NtpOutOfRange)?;
+            return Ok((utc, rtc_time - utc));
         }
     }
-    None
+    Err(TimeError::RtcStopped)
 }
 
-/// Set RTC time to what we get from an NTP server
-pub async fn synchronize_ntp_time_to_rtc(net_stack: Stack<'_>) {
-    let network_time = get_ntp_time(net_stack).await;
-    if let Some(new_time) = network_time {
-        set_rtc_clock(&new_time).unwrap();
-    } else {
-        error!("Failed to synchronize time over the network");
+/// Record how far the RTC drifted since it was last set, and compensate the drift with its aging
+/// offset once it's known
+///
+/// Returns how far the RTC was off.
+async fn track_rtc_drift(
+    sample: &Sample,
+    drift: &mut DriftEstimator,
+) -> Result<TimeDelta, TimeError> {
+    let (utc, rtc_error) = measure_rtc_error(sample).await?;
+    info!("RTC is {} ms off", rtc_error.num_milliseconds());
+    drift.record(utc, rtc_error);
+    let current = access_rtc_clock(|rtc| rtc.aging_offset())?;
+    if let Some(offset) = drift.aging_offset(current) {
+        info!(
+            "RTC drifts {} ppb, changing its aging offset from {current} to {offset}",
+            drift.drift_ppb().unwrap_or_default()
+        );
+        access_rtc_clock(|rtc| rtc.set_aging_offset(offset))?;
+        drift.restart();
     }
+    Ok(rtc_error)
+}
+
+/// Set RTC time to what we get from an NTP server
+///
+/// The time is also given to `time_keeper`, so it's known even when the RTC fails. Returns how
+/// far off the RTC was, if that could be measured.
+pub async fn synchronize_ntp_time_to_rtc(
+    net_stack: Stack<'_>,
+    time_keeper: &mut TimeKeeper,
+) -> Result<Option<TimeDelta>, TimeError> {
+    let sample = get_ntp_time(net_stack)
+        .await
+        .inspect_err(|_e| error!("Failed to synchronize time over the network"))?;
+    // The calendar is right without it
+    let rtc_error = match rtc_stopped() {
+        Ok(false) => track_rtc_drift(&sample, &mut time_keeper.drift)
+            .await
+            .inspect_err(|e| error!("Failed to track the RTC drift: {e:?}"))
+            .ok(),
+        // The error of an RTC that lost its time says nothing about its drift
+        Ok(true) => {
+            warn!("RTC lost its time, setting it");
+            None
+        }
+        Err(_) => None,
+    };
+    // The RTC counts the seconds from when it's set, so it's set at the start of one
+    let now = sample
+        .utc_at(Instant::now().as_micros())
+        .ok_or(TimeError::NtpOutOfRange)?;
+    let to_next_second = 1_000_000 - u64::from(now.and_utc().timestamp_subsec_micros());
+    let new_time = (now + TimeDelta::microseconds(to_next_second as i64))
+        .with_nanosecond(0)
+        .ok_or(TimeError::NtpOutOfRange)?;
+    Timer::after_micros(to_next_second).await;
+    time_keeper.set(new_time);
+    set_rtc_clock(&new_time)?;
+    time_keeper.rtc_stopped = false;
+    time_keeper.drift.rtc_set(new_time);
+    Ok(rtc_error)
 }
@@ -1,4 +1,11 @@
+#[cfg(feature = "deep-sleep")]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "deep-sleep")]
+use embassy_futures::select::select;
 use embassy_net::Runner;
+#[cfg(feature = "deep-sleep")]
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
 use embassy_time::Timer;
 use esp_wifi::wifi::{
     ClientConfiguration, Configuration, WifiController, WifiDevice, WifiEvent, WifiStaDevice,
@@ -10,10 +17,46 @@ use log::{debug, error, info, trace, warn};
 const SSID: &str = env!("SSID");
 const WIFI_PASSWORD: &str = env!("WIFI_PASSWORD");
 
+/// Signaled by [`stop_wifi`] to stop the connection handler, then back by it once stopped
+#[cfg(feature = "deep-sleep")]
+static WIFI_STOP: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+#[cfg(feature = "deep-sleep")]
+static WIFI_STOPPED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+/// The connection handler is gone once stopped, nothing would answer a second stop
+#[cfg(feature = "deep-sleep")]
+static WIFI_IS_STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// Disconnect and stop the Wi-Fi, it's not started again. Returns right away when it's stopped
+/// already.
+#[cfg(feature = "deep-sleep")]
+pub async fn stop_wifi() {
+    if WIFI_IS_STOPPED.load(Ordering::Acquire) {
+        return;
+    }
+    WIFI_STOP.signal(());
+    WIFI_STOPPED.wait().await;
+    WIFI_IS_STOPPED.store(true, Ordering::Release);
+}
+
 #[embassy_executor::task]
 pub async fn connection_handler_task(mut controller: WifiController<'static>) {
     info!("Starting wifi connection handler task");
     info!("Device capabilities: {:?}", controller.capabilities());
+    #[cfg(feature = "deep-sleep")]
+    {
+        select(WIFI_STOP.wait(), keep_connected(&mut controller)).await;
+        info!("Stopping wifi");
+        if let Err(e) = controller.stop_async().await {
+            error!("Failed to stop wifi: {e:?}");
+        }
+        WIFI_STOPPED.signal(());
+    }
+    #[cfg(not(feature = "deep-sleep"))]
+    keep_connected(&mut controller).await;
+}
+
+/// Connect the Wi-Fi, and connect it again whenever it's lost
+async fn keep_connected(controller: &mut WifiController<'static>) -> ! {
     loop {
         if esp_wifi::wifi::wifi_state() == WifiState::StaConnected {
             controller.wait_for_event(WifiEvent::StaDisconnected).await;
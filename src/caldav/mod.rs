@@ -0,0 +1,377 @@
+//! CalDAV (RFC 4791) calendar queries
+//!
+//! The HTTP client has no `REPORT` method, so the request is built here as raw HTTP and the events
+//! are taken from the `calendar-data` elements of the multistatus response.
+
+use alloc::{format, string::String, vec::Vec};
+use core::ops::Range;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+
+use crate::ical::{Event, IcalParser};
+
+/// Longest tag name kept, longer tags are never `calendar-data`
+const MAX_TAG_LEN: usize = 64;
+/// Longest entity kept, like `&#x10FFFF;`
+const MAX_ENTITY_LEN: usize = 10;
+
+/// Build the body of a `calendar-query` report for the events that overlap the `window` dates in
+/// the local time of `tz`
+pub fn calendar_query_body(window: &Range<NaiveDate>, tz: impl TimeZone) -> String {
+    let utc = |date: NaiveDate| {
+        let local = date.and_time(NaiveTime::MIN);
+        let utc = tz
+            .from_local_datetime(&local)
+            .earliest()
+            .map_or(local, |time| time.naive_utc());
+        format_utc(utc)
+    };
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">"#,
+            "<D:prop><C:calendar-data/></D:prop>",
+            "<C:filter>",
+            r#"<C:comp-filter name="VCALENDAR">"#,
+            r#"<C:comp-filter name="VEVENT">"#,
+            r#"<C:time-range start="{}" end="{}"/>"#,
+            "</C:comp-filter>",
+            "</C:comp-filter>",
+            "</C:filter>",
+            "</C:calendar-query>",
+        ),
+        utc(window.start),
+        utc(window.end),
+    )
+}
+
+/// Build a raw HTTP `REPORT` request for the calendar collection at `path`
+///
+/// `credentials` are the user name and password for basic authentication.
+pub fn report_request(
+    host: &str,
+    path: &str,
+    credentials: Option<(&str, &str)>,
+    body: &str,
+) -> String {
+    let auth = match credentials {
+        Some((user, password)) => format!(
+            "Authorization: Basic {}\r\n",
+            base64_encode(format!("{user}:{password}").as_bytes())
+        ),
+        None => String::new(),
+    };
+    format!(
+        concat!(
+            "REPORT {path} HTTP/1.1\r\n",
+            "Host: {host}\r\n",
+            "{auth}",
+            "Depth: 1\r\n",
+            "Content-Type: application/xml; charset=utf-8\r\n",
+            "Content-Length: {len}\r\n",
+            "\r\n",
+            "{body}",
+        ),
+        path = path,
+        host = host,
+        auth = auth,
+        len = body.len(),
+        body = body,
+    )
+}
+
+/// Streaming parser of a multistatus response, passing the contents of its `calendar-data`
+/// elements to an [`IcalParser`]
+pub struct MultistatusParser {
+    ical: IcalParser,
+    state: State,
+    /// Tag or entity being read
+    buf: Vec<u8>,
+    /// Closing brackets seen in a CDATA section, they may be its end
+    cdata_brackets: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Outside,
+    /// Reading a tag outside of calendar data
+    OutsideTag,
+    Data,
+    /// Reading a tag inside calendar data
+    DataTag,
+    Entity,
+    CData,
+}
+
+impl MultistatusParser {
+    pub fn new(ical: IcalParser) -> Self {
+        Self {
+            ical,
+            state: State::Outside,
+            buf: Vec::new(),
+            cdata_brackets: 0,
+        }
+    }
+
+    /// Parse the next chunk of the response
+    pub fn push(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.push_byte(byte);
+        }
+    }
+
+    /// Finish parsing and get the events
+    pub fn finish(self) -> Vec<Event> {
+        self.ical.finish()
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        match (self.state, byte) {
+            (State::Outside, b'<') => self.start_buf(State::OutsideTag),
+            (State::Outside, _) => {}
+            (State::OutsideTag, b'>') => {
+                if is_calendar_data_start(&self.buf) {
+                    self.state = State::Data;
+                } else {
+                    self.state = State::Outside;
+                }
+            }
+            (State::Data, b'<') => self.start_buf(State::DataTag),
+            (State::Data, b'&') => self.start_buf(State::Entity),
+            (State::Data, byte) => self.ical.push(&[byte]),
+            (State::DataTag, b'>') => {
+                if is_calendar_data_end(&self.buf) {
+                    // Each element holds a whole calendar object, end its last line
+                    self.ical.push(b"\r\n");
+                    self.state = State::Outside;
+                } else {
+                    self.state = State::Data;
+                }
+            }
+            (State::Entity, b';') => {
+                let mut utf8 = [0; 4];
+                match decode_entity(&self.buf) {
+                    Some(c) => self.ical.push(c.encode_utf8(&mut utf8).as_bytes()),
+                    None => {
+                        self.ical.push(b"&");
+                        self.ical.push(&self.buf);
+                        self.ical.push(b";");
+                    }
+                }
+                self.state = State::Data;
+            }
+            (State::CData, b']') => self.cdata_brackets = self.cdata_brackets.saturating_add(1),
+            (State::CData, b'>') if self.cdata_brackets >= 2 => {
+                for _ in 2..self.cdata_brackets {
+                    self.ical.push(b"]");
+                }
+                self.state = State::Data;
+            }
+            (State::CData, byte) => {
+                for _ in 0..self.cdata_brackets {
+                    self.ical.push(b"]");
+                }
+                self.cdata_brackets = 0;
+                self.ical.push(&[byte]);
+            }
+            (State::OutsideTag | State::DataTag | State::Entity, byte) => {
+                let max_len = if self.state == State::Entity {
+                    MAX_ENTITY_LEN
+                } else {
+                    MAX_TAG_LEN
+                };
+                if self.buf.len() < max_len {
+                    self.buf.push(byte);
+                } else if let Some(last) = self.buf.last_mut() {
+                    // Keep the last byte, it tells if the tag is self-closing
+                    *last = byte;
+                }
+                if self.state == State::DataTag && self.buf == b"![CDATA[" {
+                    self.cdata_brackets = 0;
+                    self.state = State::CData;
+                }
+            }
+        }
+    }
+
+    fn start_buf(&mut self, state: State) {
+        self.buf.clear();
+        self.state = state;
+    }
+}
+
+/// Get the local name of a tag, without the namespace prefix and attributes
+fn tag_name(tag: &[u8]) -> &[u8] {
+    let name = tag
+        .split(|byte| byte.is_ascii_whitespace() || *byte == b'/')
+        .next()
+        .unwrap_or_default();
+    match name.iter().rposition(|byte| *byte == b':') {
+        Some(colon) => &name[colon + 1..],
+        None => name,
+    }
+}
+
+fn is_calendar_data_start(tag: &[u8]) -> bool {
+    // Self-closing elements have no data
+    !tag.starts_with(b"/") && !tag.ends_with(b"/") && tag_name(tag) == b"calendar-data"
+}
+
+fn is_calendar_data_end(tag: &[u8]) -> bool {
+    tag.strip_prefix(b"/")
+        .is_some_and(|tag| tag_name(tag) == b"calendar-data")
+}
+
+/// Decode the contents of an entity, like `amp` or `#13`
+fn decode_entity(entity: &[u8]) -> Option<char> {
+    match entity {
+        b"lt" => Some('<'),
+        b"gt" => Some('>'),
+        b"amp" => Some('&'),
+        b"quot" => Some('"'),
+        b"apos" => Some('\''),
+        [b'#', b'x' | b'X', hex @ ..] => {
+            char::from_u32(u32::from_str_radix(core::str::from_utf8(hex).ok()?, 16).ok()?)
+        }
+        [b'#', dec @ ..] => char::from_u32(core::str::from_utf8(dec).ok()?.parse().ok()?),
+        _ => None,
+    }
+}
+
+fn format_utc(time: NaiveDateTime) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        time.year(),
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0_u32, |acc, (idx, byte)| {
+            acc | u32::from(*byte) << (16 - idx * 8)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                res.push(ALPHABET[(bits >> (18 - idx * 6)) as usize & 0b11_1111] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, NaiveDate};
+    use chrono_tz::Europe;
+
+    use super::{MultistatusParser, base64_encode, calendar_query_body, report_request};
+    use crate::ical::{Event, IcalParser};
+
+    const RADICALE_REPORT: &str = include_str!("testdata/radicale_report.xml");
+    const NEXTCLOUD_REPORT: &str = include_str!("testdata/nextcloud_report.xml");
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn parse(report: &str, chunk_size: usize) -> Vec<Event> {
+        let mut parser =
+            MultistatusParser::new(IcalParser::new(date(2025, 1, 1)..date(2025, 2, 1), 100));
+        for chunk in report.as_bytes().chunks(chunk_size) {
+            parser.push(chunk);
+        }
+        parser.finish()
+    }
+
+    fn summaries(events: &[Event]) -> Vec<&str> {
+        let mut res: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+        res.sort();
+        res
+    }
+
+    #[test]
+    fn query_body_uses_utc_range() {
+        let body = calendar_query_body(&(date(2025, 1, 1)..date(2025, 2, 1)), Europe::Moscow);
+        assert!(
+            body.contains(r#"<C:time-range start="20241231T210000Z" end="20250131T210000Z"/>"#)
+        );
+        assert!(body.contains(r#"<C:comp-filter name="VEVENT">"#));
+    }
+
+    #[test]
+    fn request_has_auth_and_length() {
+        let request = report_request(
+            "dav.example.com",
+            "/anna/calendar/",
+            Some(("anna", "secret")),
+            "<body/>",
+        );
+        assert_eq!(
+            request,
+            "REPORT /anna/calendar/ HTTP/1.1\r\n\
+             Host: dav.example.com\r\n\
+             Authorization: Basic YW5uYTpzZWNyZXQ=\r\n\
+             Depth: 1\r\n\
+             Content-Type: application/xml; charset=utf-8\r\n\
+             Content-Length: 7\r\n\
+             \r\n\
+             <body/>"
+        );
+        let request = report_request("dav.example.com", "/cal/", None, "");
+        assert!(!request.contains("Authorization"));
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(base64_encode("пароль".as_bytes()), "0L/QsNGA0L7Qu9GM");
+    }
+
+    #[test]
+    fn radicale_report_events() {
+        let events = parse(RADICALE_REPORT, 4096);
+        assert_eq!(
+            summaries(&events),
+            ["Planning <Q1> & budget", "Team lunch", "Weekly sync"]
+        );
+        let sync = events
+            .iter()
+            .find(|event| event.summary == "Weekly sync")
+            .unwrap();
+        assert!(sync.rule.is_some());
+    }
+
+    #[test]
+    fn nextcloud_report_with_cdata() {
+        let events = parse(NEXTCLOUD_REPORT, 4096);
+        assert_eq!(summaries(&events), ["Code review ]]", "Demo day"]);
+        let demo = events
+            .iter()
+            .find(|event| event.summary == "Demo day")
+            .unwrap();
+        assert_eq!(demo.start.wall().date().day(), 24);
+    }
+
+    #[test]
+    fn chunked_report_gives_same_events() {
+        for report in [RADICALE_REPORT, NEXTCLOUD_REPORT] {
+            let expected = parse(report, 4096);
+            for chunk_size in [1, 2, 5, 13] {
+                assert_eq!(parse(report, chunk_size), expected);
+            }
+        }
+    }
+}
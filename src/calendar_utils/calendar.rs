@@ -0,0 +1,397 @@
+use core::ops::Range;
+
+use chrono::{Datelike, Days, Month, Months, NaiveDate, Weekday};
+
+use super::{DayKind, DaysOffMask, EventDaysMask, MonthDate, WeekConfig};
+
+pub struct DaysIter {
+    range: Range<u8>,
+    days_off_mask: DaysOffMask,
+}
+
+impl DaysIter {
+    const fn new(calendar: &CalendarMonth) -> Self {
+        Self {
+            range: 0..calendar.days_amount(),
+            days_off_mask: calendar.days_off_mask,
+        }
+    }
+}
+
+impl Iterator for DaysIter {
+    type Item = (u8, DayKind);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next()?;
+        let kind = self.days_off_mask.day0_kind(idx);
+        let res = (idx, kind);
+        Some(res)
+    }
+}
+
+/// Data used to describe a calendar month
+#[derive(Debug, Clone, Copy)]
+pub struct CalendarMonth {
+    date: MonthDate,
+    days_off_mask: DaysOffMask,
+    event_days: EventDaysMask,
+    week: WeekConfig,
+}
+
+impl CalendarMonth {
+    /// By default uses the days off mask of the weekend days of `week` always being days off
+    pub fn from_date(date: NaiveDate, week: WeekConfig) -> Self {
+        // Assume year is in CE
+        let (_, year) = date.year_ce();
+        let month = date.month();
+        let date = date.with_day0(0).unwrap();
+        let weekday = date.weekday();
+        Self {
+            days_off_mask: DaysOffMask::default_days_off(weekday, week),
+            date: MonthDate::new(year as u16, Month::try_from(month as u8).unwrap()),
+            event_days: EventDaysMask::NONE,
+            week,
+        }
+    }
+
+    #[inline(always)]
+    pub fn month_date(self) -> MonthDate {
+        self.date
+    }
+
+    pub const fn new_raw(date: MonthDate, day_off_mask: DaysOffMask, week: WeekConfig) -> Self {
+        Self {
+            date,
+            days_off_mask: day_off_mask,
+            event_days: EventDaysMask::NONE,
+            week,
+        }
+    }
+
+    /// Get the week layout this month is displayed with
+    pub const fn week(&self) -> WeekConfig {
+        self.week
+    }
+
+    pub fn days_iter(&self) -> DaysIter {
+        DaysIter::new(self)
+    }
+
+    pub const fn start_date(&self) -> NaiveDate {
+        self.date.to_start_day_naive()
+    }
+
+    /// Get teh amount of days in this month
+    pub const fn days_amount(&self) -> u8 {
+        let start = self.start_date();
+        let end = start.checked_add_months(Months::new(1)).unwrap();
+        let result = end.signed_duration_since(start).num_days();
+        result as u8
+    }
+
+    /// Get the day of teh week this month starts on
+    pub fn start_weekday(&self) -> Weekday {
+        self.start_date().weekday()
+    }
+
+    /// Get the grid column the first day of this month is in
+    pub fn start_column(&self) -> u8 {
+        self.week.column(self.start_weekday())
+    }
+
+    /// Get the ISO week number of the first grid row of this month
+    pub fn start_week_num(&self) -> u8 {
+        self.row_week_num(0)
+    }
+
+    /// Get the amount of grid rows this month takes
+    pub fn rows_amount(&self) -> u8 {
+        (self.start_column() + self.days_amount()).div_ceil(7)
+    }
+
+    /// Get the amount of empty grid cells before the first day of this month
+    pub fn leading_cells(&self) -> u8 {
+        self.start_column()
+    }
+
+    /// Get the amount of empty grid cells after the last day of this month, up to the end of the
+    /// last row
+    pub fn trailing_cells(&self) -> u8 {
+        self.rows_amount() * 7 - self.start_column() - self.days_amount()
+    }
+
+    /// Get the ISO week number of a grid row.
+    ///
+    /// When the week doesn't start on monday a row spans two ISO weeks, the week that has more days
+    /// in the row is used. That is the week of the row's thursday.
+    pub fn row_week_num(&self, row: u8) -> u8 {
+        let row_start = self.start_date() - Days::new(self.start_column().into())
+            + Days::new(u64::from(row) * 7);
+        let thursday = row_start + Days::new(Weekday::Thu.days_since(self.week.first_day()).into());
+        thursday.iso_week().week() as u8
+    }
+
+    /// Get the month this month is from
+    pub fn month(&self) -> Month {
+        self.date.month()
+    }
+
+    /// Get the year this month is from, clamped to CE and up to 4095
+    pub fn year(&self) -> u16 {
+        self.date.year()
+    }
+
+    /// Get the kind of a day of this month, counting from 0
+    pub const fn day0_kind(&self, day: u8) -> DayKind {
+        self.days_off_mask.day0_kind(day)
+    }
+
+    /// Set the days off
+    pub const fn set_days_off(&mut self, days_off_mask: DaysOffMask) {
+        self.days_off_mask = days_off_mask.truncate(self.days_amount());
+    }
+
+    /// Check if a day of this month has events, counting from 0
+    pub const fn has_events_day0(&self, day: u8) -> bool {
+        self.event_days.has_events_day0(day)
+    }
+
+    /// Set the days that have events
+    pub const fn set_event_days(&mut self, event_days: EventDaysMask) {
+        self.event_days = event_days.truncate(self.days_amount());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Month, NaiveDate, Weekday};
+
+    use super::CalendarMonth;
+    use crate::calendar_utils::{DayKind, DaysOffMask, MonthDate, WeekConfig};
+
+    fn calendar(year: i32, month: u32) -> CalendarMonth {
+        CalendarMonth::from_date(
+            NaiveDate::from_ymd_opt(year, month, 15).unwrap(),
+            WeekConfig::ISO,
+        )
+    }
+
+    #[test]
+    fn from_date_uses_month_of_date() {
+        let calendar = calendar(2025, 3);
+        assert_eq!(calendar.year(), 2025);
+        assert_eq!(calendar.month(), Month::March);
+        assert_eq!(
+            calendar.start_date(),
+            NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()
+        );
+        assert_eq!(calendar.month_date(), MonthDate::new(2025, Month::March));
+    }
+
+    #[test]
+    fn days_amount() {
+        assert_eq!(calendar(2025, 1).days_amount(), 31);
+        assert_eq!(calendar(2025, 2).days_amount(), 28);
+        assert_eq!(calendar(2024, 2).days_amount(), 29);
+        assert_eq!(calendar(1900, 2).days_amount(), 28);
+        assert_eq!(calendar(2000, 2).days_amount(), 29);
+        assert_eq!(calendar(2025, 4).days_amount(), 30);
+        assert_eq!(calendar(2025, 12).days_amount(), 31);
+    }
+
+    #[test]
+    fn start_weekday() {
+        assert_eq!(calendar(2025, 1).start_weekday(), Weekday::Wed);
+        assert_eq!(calendar(2025, 6).start_weekday(), Weekday::Sun);
+        assert_eq!(calendar(2024, 9).start_weekday(), Weekday::Sun);
+        assert_eq!(calendar(2025, 9).start_weekday(), Weekday::Mon);
+    }
+
+    #[test]
+    fn default_days_off_are_weekends() {
+        for month in 1..=12 {
+            let calendar = calendar(2025, month);
+            for (day, kind) in calendar.days_iter() {
+                let weekday = NaiveDate::from_ymd_opt(2025, month, day as u32 + 1)
+                    .unwrap()
+                    .weekday();
+                assert_eq!(
+                    kind.is_day_off(),
+                    matches!(weekday, Weekday::Sat | Weekday::Sun),
+                    "2025-{month}-{}",
+                    day + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn default_days_off_follow_week_config() {
+        let week = WeekConfig::new(Weekday::Sun, &[Weekday::Fri, Weekday::Sat]);
+        let calendar =
+            CalendarMonth::from_date(NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(), week);
+        for (day, kind) in calendar.days_iter() {
+            let weekday = NaiveDate::from_ymd_opt(2025, 1, day as u32 + 1)
+                .unwrap()
+                .weekday();
+            assert_eq!(
+                kind.is_day_off(),
+                matches!(weekday, Weekday::Fri | Weekday::Sat)
+            );
+        }
+    }
+
+    #[test]
+    fn start_column_follows_week_config() {
+        // Starts on wednesday
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        assert_eq!(
+            CalendarMonth::from_date(date, WeekConfig::ISO).start_column(),
+            2
+        );
+        let sunday_first = WeekConfig::new(Weekday::Sun, &[Weekday::Sat, Weekday::Sun]);
+        assert_eq!(
+            CalendarMonth::from_date(date, sunday_first).start_column(),
+            3
+        );
+        // Starts on sunday
+        let date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        assert_eq!(
+            CalendarMonth::from_date(date, WeekConfig::ISO).start_column(),
+            6
+        );
+        assert_eq!(
+            CalendarMonth::from_date(date, sunday_first).start_column(),
+            0
+        );
+    }
+
+    #[test]
+    fn rows_amount() {
+        // 28 days starting on monday
+        assert_eq!(calendar(2021, 2).rows_amount(), 4);
+        assert_eq!(calendar(2025, 1).rows_amount(), 5);
+        // 31 days starting on saturday
+        assert_eq!(calendar(2025, 3).rows_amount(), 6);
+    }
+
+    #[test]
+    fn leading_and_trailing_cells() {
+        // Starts on wednesday, ends on friday
+        let january = calendar(2025, 1);
+        assert_eq!(january.leading_cells(), 2);
+        assert_eq!(january.trailing_cells(), 2);
+        // Starts on monday, ends on sunday
+        let february = calendar(2021, 2);
+        assert_eq!(february.leading_cells(), 0);
+        assert_eq!(february.trailing_cells(), 0);
+        // Starts on sunday, ends on tuesday
+        let december = calendar(2024, 12);
+        assert_eq!(december.leading_cells(), 6);
+        assert_eq!(december.trailing_cells(), 5);
+        for calendar in [january, february, december] {
+            assert_eq!(
+                (calendar.leading_cells() + calendar.days_amount() + calendar.trailing_cells()) % 7,
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn week_numbers_within_year() {
+        let calendar = calendar(2025, 5);
+        assert_eq!(calendar.start_week_num(), 18);
+        let weeks: Vec<u8> = (0..calendar.rows_amount())
+            .map(|row| calendar.row_week_num(row))
+            .collect();
+        assert_eq!(weeks, [18, 19, 20, 21, 22]);
+    }
+
+    #[test]
+    fn week_numbers_at_year_start() {
+        // 2021-01-01 is a friday in week 53 of 2020
+        let weeks: Vec<u8> = (0..5)
+            .map(|row| calendar(2021, 1).row_week_num(row))
+            .collect();
+        assert_eq!(weeks, [53, 1, 2, 3, 4]);
+        // 2023-01-01 is a sunday in week 52 of 2022
+        assert_eq!(calendar(2023, 1).start_week_num(), 52);
+        assert_eq!(calendar(2023, 1).row_week_num(1), 1);
+        // 2025-01-01 is a wednesday in week 1 of 2025
+        assert_eq!(calendar(2025, 1).start_week_num(), 1);
+    }
+
+    #[test]
+    fn week_numbers_at_year_end() {
+        // 2024-12-30 is a monday in week 1 of 2025
+        let december = calendar(2024, 12);
+        assert_eq!(december.rows_amount(), 6);
+        assert_eq!(december.row_week_num(4), 52);
+        assert_eq!(december.row_week_num(5), 1);
+        // 2020 has 53 weeks
+        let december = calendar(2020, 12);
+        assert_eq!(december.row_week_num(december.rows_amount() - 1), 53);
+    }
+
+    #[test]
+    fn week_numbers_sunday_first() {
+        let sunday_first = WeekConfig::new(Weekday::Sun, &[Weekday::Sat, Weekday::Sun]);
+        // 2023-01-01 is a sunday, the rest of its row is in week 1
+        let calendar =
+            CalendarMonth::from_date(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), sunday_first);
+        assert_eq!(calendar.start_column(), 0);
+        assert_eq!(calendar.start_week_num(), 1);
+        assert_eq!(calendar.row_week_num(1), 2);
+    }
+
+    #[test]
+    fn days_iter_covers_whole_month() {
+        let calendar = calendar(2024, 2);
+        assert_eq!(calendar.days_iter().count(), 29);
+        assert_eq!(calendar.days_iter().last().map(|(day, _)| day), Some(28));
+    }
+
+    #[test]
+    fn set_days_off_truncates_to_month_length() {
+        let mut calendar = calendar(2025, 2);
+        calendar.set_days_off(DaysOffMask::from_days_off(u32::MAX));
+        assert_eq!(
+            calendar
+                .days_iter()
+                .filter(|(_, kind)| kind.is_day_off())
+                .count(),
+            28
+        );
+    }
+
+    #[test]
+    fn days_iter_yields_day_kinds() {
+        let mut calendar = calendar(2025, 5);
+        calendar.set_days_off(
+            DaysOffMask::default()
+                .with_day0(0, DayKind::PublicHoliday)
+                .with_day0(6, DayKind::Shortened)
+                .with_day0(9, DayKind::Weekend),
+        );
+        let kinds: Vec<DayKind> = calendar
+            .days_iter()
+            .map(|(_, kind)| kind)
+            .take(10)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                DayKind::PublicHoliday,
+                DayKind::Workday,
+                DayKind::Workday,
+                DayKind::Workday,
+                DayKind::Workday,
+                DayKind::Workday,
+                DayKind::Shortened,
+                DayKind::Workday,
+                DayKind::Workday,
+                DayKind::Weekend,
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::Weekday;
+
+use super::WeekConfig;
+
+/// What kind of day a calendar day is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DayKind {
+    #[default]
+    Workday = 0,
+    /// Regular day off
+    Weekend = 1,
+    /// Official public holiday
+    PublicHoliday = 2,
+    /// Pre-holiday workday, shortened by one hour
+    Shortened = 3,
+}
+
+impl DayKind {
+    pub const fn is_day_off(self) -> bool {
+        matches!(self, Self::Weekend | Self::PublicHoliday)
+    }
+
+    const fn from_bits(bits: u64) -> Self {
+        match bits & DaysOffMask::DAY_MASK {
+            0 => Self::Workday,
+            1 => Self::Weekend,
+            2 => Self::PublicHoliday,
+            _ => Self::Shortened,
+        }
+    }
+}
+
+/// Kinds of the days of a month, two bits per day with the first day in the lowest bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DaysOffMask(u64);
+
+impl DaysOffMask {
+    const DAY_BITS: u8 = 2;
+    const DAY_MASK: u64 = 0b11;
+
+    /// Make a mask from one bit per day, set bits are days off
+    pub const fn from_days_off(val: u32) -> Self {
+        let mut res = Self(0);
+        let mut day = 0;
+        while day < 32 {
+            if (val >> day) & 0b1 != 0 {
+                res = res.with_day0(day, DayKind::Weekend);
+            }
+            day += 1;
+        }
+        res
+    }
+
+    /// Make a mask from its raw representation, as returned by [`Self::to_bits`]
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Get the raw representation of the mask, for storage
+    pub const fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    /// Get the mask with the kind of a day changed
+    pub const fn with_day0(self, day: u8, kind: DayKind) -> Self {
+        let shift = day * Self::DAY_BITS;
+        Self((self.0 & !(Self::DAY_MASK << shift)) | ((kind as u64) << shift))
+    }
+
+    pub const fn truncate(self, days: u8) -> Self {
+        Self(self.0 & ((1_u64 << (days * Self::DAY_BITS)) - 1))
+    }
+
+    /// Mask with the weekend days of the week off, for a month starting on `starts_on`
+    pub(crate) const fn default_days_off(starts_on: Weekday, week: WeekConfig) -> Self {
+        let mut res = Self(0);
+        let mut weekday = starts_on;
+        let mut day = 0;
+        while day < 31 {
+            if week.is_weekend(weekday) {
+                res = res.with_day0(day, DayKind::Weekend);
+            }
+            weekday = weekday.succ();
+            day += 1;
+        }
+        res
+    }
+
+    pub const fn day0_kind(self, day: u8) -> DayKind {
+        DayKind::from_bits(self.0 >> (day * Self::DAY_BITS))
+    }
+
+    /// Get the kind of the `day` of the month counted from 1, like the dates
+    ///
+    /// Day 0 is the last day of the previous month, the caller has its kind in the mask of that
+    /// month. Here it's a workday, like the days past the end of the month.
+    pub const fn day1_kind(self, day: u8) -> DayKind {
+        match day.checked_sub(1) {
+            Some(day0) => self.day0_kind(day0),
+            None => DayKind::Workday,
+        }
+    }
+
+    pub const fn is_day0_off(self, day: u8) -> bool {
+        self.day0_kind(day).is_day_off()
+    }
+
+    pub const fn is_day1_off(self, day: u8) -> bool {
+        self.day1_kind(day).is_day_off()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use super::{DayKind, DaysOffMask};
+    use crate::calendar_utils::WeekConfig;
+
+    #[test]
+    fn default_days_off_month_starting_monday() {
+        let mask = DaysOffMask::default_days_off(Weekday::Mon, WeekConfig::ISO);
+        let off: Vec<u8> = (0..31).filter(|d| mask.is_day0_off(*d)).collect();
+        assert_eq!(off, [5, 6, 12, 13, 19, 20, 26, 27]);
+    }
+
+    #[test]
+    fn default_days_off_month_starting_sunday() {
+        let mask = DaysOffMask::default_days_off(Weekday::Sun, WeekConfig::ISO);
+        let off: Vec<u8> = (0..31).filter(|d| mask.is_day0_off(*d)).collect();
+        assert_eq!(off, [0, 6, 7, 13, 14, 20, 21, 27, 28]);
+    }
+
+    #[test]
+    fn default_days_off_month_starting_saturday() {
+        let mask = DaysOffMask::default_days_off(Weekday::Sat, WeekConfig::ISO);
+        let off: Vec<u8> = (0..31).filter(|d| mask.is_day0_off(*d)).collect();
+        assert_eq!(off, [0, 1, 7, 8, 14, 15, 21, 22, 28, 29]);
+    }
+
+    #[test]
+    fn default_days_off_friday_saturday_weekend() {
+        let week = WeekConfig::new(Weekday::Sun, &[Weekday::Fri, Weekday::Sat]);
+        let mask = DaysOffMask::default_days_off(Weekday::Wed, week);
+        let off: Vec<u8> = (0..31).filter(|d| mask.is_day0_off(*d)).collect();
+        assert_eq!(off, [2, 3, 9, 10, 16, 17, 23, 24, 30]);
+    }
+
+    #[test]
+    fn default_days_off_are_weekends() {
+        let mask = DaysOffMask::default_days_off(Weekday::Mon, WeekConfig::ISO);
+        assert_eq!(mask.day0_kind(0), DayKind::Workday);
+        assert_eq!(mask.day0_kind(5), DayKind::Weekend);
+    }
+
+    #[test]
+    fn truncate_drops_days_past_month_end() {
+        let mask = DaysOffMask::from_days_off(u32::MAX).truncate(28);
+        assert!(mask.is_day0_off(27));
+        assert!(!mask.is_day0_off(28));
+        assert!(!mask.is_day0_off(30));
+    }
+
+    #[test]
+    fn day1_zero_is_outside_the_month() {
+        let mask = DaysOffMask::from_days_off(u32::MAX);
+        assert_eq!(mask.day1_kind(0), DayKind::Workday);
+        assert!(!mask.is_day1_off(0));
+        assert_eq!(mask.day1_kind(1), DayKind::Weekend);
+    }
+
+    #[test]
+    fn with_day0_replaces_kind() {
+        let mask = DaysOffMask::default()
+            .with_day0(0, DayKind::PublicHoliday)
+            .with_day0(30, DayKind::Shortened)
+            .with_day0(4, DayKind::Weekend)
+            .with_day0(4, DayKind::Shortened);
+        assert_eq!(mask.day0_kind(0), DayKind::PublicHoliday);
+        assert_eq!(mask.day0_kind(1), DayKind::Workday);
+        assert_eq!(mask.day0_kind(4), DayKind::Shortened);
+        assert_eq!(mask.day0_kind(30), DayKind::Shortened);
+        assert!(mask.is_day0_off(0));
+        assert!(!mask.is_day0_off(4));
+    }
+}
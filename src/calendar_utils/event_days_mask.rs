@@ -0,0 +1,20 @@
+/// Days of a month that have events, one bit per day with the first day in the lowest bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventDaysMask(u32);
+
+impl EventDaysMask {
+    pub const NONE: Self = Self(0);
+
+    /// Get the mask with a day marked as having events
+    pub const fn with_day0(self, day: u8) -> Self {
+        Self(self.0 | (1 << day))
+    }
+
+    pub const fn truncate(self, days: u8) -> Self {
+        Self(self.0 & (u32::MAX >> (32 - days as u32)))
+    }
+
+    pub const fn has_events_day0(self, day: u8) -> bool {
+        (self.0 >> day) & 0b1 != 0
+    }
+}
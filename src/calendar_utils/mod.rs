@@ -2,14 +2,18 @@
 
 pub mod calendar;
 pub mod daysoff_mask;
+pub mod event_days_mask;
 mod month_date;
-use core::ops::{Add, Range, Sub};
+pub mod week;
+pub mod year_days_off;
 
 pub use calendar::CalendarMonth;
-use chrono::{Datelike, Month, Months, NaiveDate, Weekday};
-pub use daysoff_mask::DaysOffMask;
+use chrono::Weekday;
+pub use daysoff_mask::{DayKind, DaysOffMask};
+pub use event_days_mask::EventDaysMask;
 pub use month_date::MonthDate;
-use num_traits::FromPrimitive;
+pub use week::WeekConfig;
+pub use year_days_off::YearDaysOff;
 
 pub const fn weekday_short_name(val: Weekday) -> &'static str {
     all_weekdays_short_en()[val.num_days_from_monday() as usize]
@@ -0,0 +1,12 @@
+// Both backends are always built for tests so they can be checked against each other
+#[cfg(any(test, not(feature = "monthdate-packed")))]
+mod naive;
+#[cfg(not(feature = "monthdate-packed"))]
+pub use naive::MonthDate;
+#[cfg(any(test, feature = "monthdate-packed"))]
+mod packed;
+#[cfg(feature = "monthdate-packed")]
+pub use packed::MonthDate;
+
+#[cfg(test)]
+mod tests;
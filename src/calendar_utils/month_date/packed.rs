@@ -1,6 +1,6 @@
-use core::ops::{Add, Range, Sub};
+use core::ops::{Add, Sub};
 
-use chrono::{Datelike, Month, Months, NaiveDate, Weekday};
+use chrono::{Datelike, Month, Months, NaiveDate};
 use num_traits::FromPrimitive;
 
 /// CE era month
@@ -46,24 +46,25 @@ impl MonthDate {
 
         Self::new(year as u16, month)
     }
+
+    /// Amount of months since the start of year 0
+    const fn month_index(self) -> u32 {
+        self.year() as u32 * 12 + self.month().number_from_month() - 1
+    }
+
+    fn from_month_index(index: u32) -> Self {
+        Self::new(
+            (index / 12) as u16,
+            Month::from_u8((index % 12) as u8 + 1).unwrap(),
+        )
+    }
 }
 
 impl Add<Months> for MonthDate {
     type Output = Self;
 
     fn add(self, rhs: Months) -> Self::Output {
-        let rhs = rhs.as_u32() as u8;
-        let lhs = (self.0 & 0b1111) as u8 - 1;
-
-        let sum_months = lhs + rhs;
-
-        let add_years = sum_months / 12;
-        let months_remainder = sum_months % 12;
-
-        MonthDate::new(
-            self.year() + add_years as u16,
-            Month::from_u8(months_remainder + 1).unwrap(),
-        )
+        Self::from_month_index(self.month_index() + rhs.as_u32())
     }
 }
 
@@ -71,17 +72,6 @@ impl Sub<Months> for MonthDate {
     type Output = Self;
 
     fn sub(self, rhs: Months) -> Self::Output {
-        let rhs = rhs.as_u32() as u8;
-        let lhs = (self.0 & 0b1111) as u8 - 1;
-
-        if rhs > lhs {
-            let sub_years = ((rhs - lhs) / 12) + 1;
-            MonthDate::new(
-                self.year() - sub_years as u16,
-                Month::from_u8(lhs + 1 + (sub_years * 12) - rhs).unwrap(),
-            )
-        } else {
-            MonthDate::new(self.year(), Month::from_u8(lhs - rhs + 1).unwrap())
-        }
+        Self::from_month_index(self.month_index() - rhs.as_u32())
     }
 }
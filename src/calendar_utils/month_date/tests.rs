@@ -0,0 +1,126 @@
+use chrono::{Datelike, Month, Months, NaiveDate};
+use num_traits::FromPrimitive;
+use proptest::prelude::*;
+
+use super::{naive, packed};
+
+const MAX_YEAR: u16 = 4095;
+
+fn all_months() -> impl Iterator<Item = (u16, Month)> {
+    (1..=MAX_YEAR).flat_map(|year| (1..=12).map(move |m| (year, Month::from_u8(m).unwrap())))
+}
+
+/// Months since the start of year 0, used to keep the arithmetic within the supported range
+fn month_index(year: u16, month: Month) -> u32 {
+    year as u32 * 12 + month.number_from_month() - 1
+}
+
+fn assert_same(naive: naive::MonthDate, packed: packed::MonthDate) {
+    assert_eq!(naive.year(), packed.year());
+    assert_eq!(naive.month(), packed.month());
+    assert_eq!(naive.to_start_day_naive(), packed.to_start_day_naive());
+}
+
+#[test]
+fn backends_agree_on_construction() {
+    for (year, month) in all_months() {
+        let naive = naive::MonthDate::new(year, month);
+        let packed = packed::MonthDate::new(year, month);
+        assert_same(naive, packed);
+        assert_eq!(naive.year(), year);
+        assert_eq!(naive.month(), month);
+    }
+}
+
+#[test]
+fn backends_agree_on_neighbour_months() {
+    let first = month_index(1, Month::January);
+    let last = month_index(MAX_YEAR, Month::December);
+    for (year, month) in all_months() {
+        let naive = naive::MonthDate::new(year, month);
+        let packed = packed::MonthDate::new(year, month);
+        let index = month_index(year, month);
+        for delta in [1, 2, 11, 12, 13, 25] {
+            if index + delta <= last {
+                assert_same(naive + Months::new(delta), packed + Months::new(delta));
+            }
+            if index >= first + delta {
+                assert_same(naive - Months::new(delta), packed - Months::new(delta));
+            }
+        }
+    }
+}
+
+#[test]
+fn backends_agree_on_ordering() {
+    let mut prev: Option<(naive::MonthDate, packed::MonthDate)> = None;
+    for (year, month) in all_months() {
+        let current = (
+            naive::MonthDate::new(year, month),
+            packed::MonthDate::new(year, month),
+        );
+        if let Some(prev) = prev {
+            assert!(prev.0 < current.0);
+            assert!(prev.1 < current.1);
+        }
+        prev = Some(current);
+    }
+}
+
+#[test]
+fn packed_sub_across_year_boundary() {
+    let jan = packed::MonthDate::new(2025, Month::January);
+    assert_eq!(
+        jan - Months::new(1),
+        packed::MonthDate::new(2024, Month::December)
+    );
+    assert_eq!(
+        jan - Months::new(12),
+        packed::MonthDate::new(2024, Month::January)
+    );
+    assert_eq!(
+        jan - Months::new(13),
+        packed::MonthDate::new(2023, Month::December)
+    );
+}
+
+fn month_strategy() -> impl Strategy<Value = (u16, Month)> {
+    (1..=MAX_YEAR, 1_u8..=12).prop_map(|(year, month)| (year, Month::from_u8(month).unwrap()))
+}
+
+proptest! {
+    #[test]
+    fn add_matches_naive((year, month) in month_strategy(), delta in 0_u32..(MAX_YEAR as u32 * 12)) {
+        prop_assume!(month_index(year, month) + delta <= month_index(MAX_YEAR, Month::December));
+        let naive = naive::MonthDate::new(year, month) + Months::new(delta);
+        let packed = packed::MonthDate::new(year, month) + Months::new(delta);
+        assert_same(naive, packed);
+    }
+
+    #[test]
+    fn sub_matches_naive((year, month) in month_strategy(), delta in 0_u32..(MAX_YEAR as u32 * 12)) {
+        prop_assume!(month_index(year, month) >= month_index(1, Month::January) + delta);
+        let naive = naive::MonthDate::new(year, month) - Months::new(delta);
+        let packed = packed::MonthDate::new(year, month) - Months::new(delta);
+        assert_same(naive, packed);
+    }
+
+    #[test]
+    fn add_then_sub_roundtrips((year, month) in month_strategy(), delta in 0_u32..120) {
+        prop_assume!(month_index(year, month) + delta <= month_index(MAX_YEAR, Month::December));
+        let packed = packed::MonthDate::new(year, month);
+        prop_assert_eq!(packed + Months::new(delta) - Months::new(delta), packed);
+    }
+
+    #[test]
+    fn from_date_matches_naive(
+        (year, month) in month_strategy(),
+        day in 1_u32..=28,
+    ) {
+        let date = NaiveDate::from_ymd_opt(year as i32, month.number_from_month(), day).unwrap();
+        let naive = naive::MonthDate::new_from_date(date);
+        let packed = packed::MonthDate::new_from_date(date);
+        assert_same(naive, packed);
+        prop_assert_eq!(packed.to_start_day_naive(), date.with_day(1).unwrap());
+    }
+}
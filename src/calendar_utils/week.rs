@@ -0,0 +1,106 @@
+use chrono::Weekday;
+
+/// Layout of the week: what day it starts on and what days are the weekend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WeekConfig {
+    first_day: Weekday,
+    /// Bit per weekday, starting from monday
+    weekend: u8,
+}
+
+impl WeekConfig {
+    /// Week starts on monday, saturday and sunday are the weekend
+    pub const ISO: Self = Self::new(Weekday::Mon, &[Weekday::Sat, Weekday::Sun]);
+
+    pub const fn new(first_day: Weekday, weekend: &[Weekday]) -> Self {
+        let mut mask = 0;
+        let mut i = 0;
+        while i < weekend.len() {
+            mask |= 1 << weekend[i].num_days_from_monday();
+            i += 1;
+        }
+        Self {
+            first_day,
+            weekend: mask,
+        }
+    }
+
+    pub const fn first_day(self) -> Weekday {
+        self.first_day
+    }
+
+    pub const fn is_weekend(self, day: Weekday) -> bool {
+        (self.weekend >> day.num_days_from_monday()) & 0b1 != 0
+    }
+
+    /// Get the column of the weekday in a calendar grid
+    pub const fn column(self, day: Weekday) -> u8 {
+        day.days_since(self.first_day) as u8
+    }
+
+    /// Get the weekdays in the order of calendar grid columns
+    pub const fn weekdays(self) -> [Weekday; 7] {
+        let mut res = [self.first_day; 7];
+        let mut i = 1;
+        while i < 7 {
+            res[i] = res[i - 1].succ();
+            i += 1;
+        }
+        res
+    }
+}
+
+impl Default for WeekConfig {
+    fn default() -> Self {
+        Self::ISO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use super::WeekConfig;
+
+    #[test]
+    fn iso_week() {
+        let week = WeekConfig::ISO;
+        assert_eq!(week.first_day(), Weekday::Mon);
+        assert_eq!(week.column(Weekday::Mon), 0);
+        assert_eq!(week.column(Weekday::Sun), 6);
+        assert!(week.is_weekend(Weekday::Sat));
+        assert!(week.is_weekend(Weekday::Sun));
+        assert!(!week.is_weekend(Weekday::Fri));
+    }
+
+    #[test]
+    fn sunday_first_week() {
+        let week = WeekConfig::new(Weekday::Sun, &[Weekday::Sat, Weekday::Sun]);
+        assert_eq!(
+            week.weekdays(),
+            [
+                Weekday::Sun,
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+            ]
+        );
+        assert_eq!(week.column(Weekday::Sun), 0);
+        assert_eq!(week.column(Weekday::Mon), 1);
+        assert_eq!(week.column(Weekday::Sat), 6);
+    }
+
+    #[test]
+    fn friday_saturday_weekend() {
+        let week = WeekConfig::new(Weekday::Sun, &[Weekday::Fri, Weekday::Sat]);
+        let weekend: Vec<Weekday> = week
+            .weekdays()
+            .into_iter()
+            .filter(|day| week.is_weekend(*day))
+            .collect();
+        assert_eq!(weekend, [Weekday::Fri, Weekday::Sat]);
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::Month;
+
+use super::{DaysOffMask, MonthDate};
+
+/// Days off of every month of a year
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YearDaysOff {
+    year: u16,
+    /// Starting from january
+    months: [DaysOffMask; 12],
+}
+
+impl YearDaysOff {
+    pub const fn new(year: u16, months: [DaysOffMask; 12]) -> Self {
+        Self { year, months }
+    }
+
+    /// Make the year from the days off of each of its months
+    pub fn from_fn(year: u16, mut f: impl FnMut(MonthDate) -> DaysOffMask) -> Self {
+        let months = core::array::from_fn(|idx| {
+            f(MonthDate::new(
+                year,
+                Month::try_from(idx as u8 + 1).unwrap(),
+            ))
+        });
+        Self { year, months }
+    }
+
+    pub const fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Get the days off of each month, starting from january
+    pub const fn months(&self) -> [DaysOffMask; 12] {
+        self.months
+    }
+
+    /// Get the days off of `month`, `None` when it's in another year
+    pub fn month(&self, month: MonthDate) -> Option<DaysOffMask> {
+        (month.year() == self.year)
+            .then(|| self.months[month.month().number_from_month() as usize - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Month;
+
+    use super::YearDaysOff;
+    use crate::calendar_utils::{DaysOffMask, MonthDate};
+
+    #[test]
+    fn months_are_in_order() {
+        let year = YearDaysOff::from_fn(2025, |month| {
+            DaysOffMask::from_days_off(month.month().number_from_month())
+        });
+        assert_eq!(
+            year.month(MonthDate::new(2025, Month::January)),
+            Some(DaysOffMask::from_days_off(1))
+        );
+        assert_eq!(
+            year.month(MonthDate::new(2025, Month::December)),
+            Some(DaysOffMask::from_days_off(12))
+        );
+    }
+
+    #[test]
+    fn other_years_have_no_data() {
+        let year = YearDaysOff::new(2025, [DaysOffMask::default(); 12]);
+        assert_eq!(year.month(MonthDate::new(2024, Month::December)), None);
+        assert_eq!(year.month(MonthDate::new(2026, Month::January)), None);
+    }
+}
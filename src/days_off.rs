@@ -0,0 +1,40 @@
+//! Sources of the days off of the months, see [`DaysOffProvider`]
+
+use chrono::Datelike;
+use embedded_nal_async::{Dns, TcpConnect};
+
+use crate::{
+    calendar_utils::{DaysOffMask, MonthDate, WeekConfig, YearDaysOff},
+    http::HttpClient,
+};
+
+/// A web service that knows the days off of the months
+// Only awaited by a single threaded executor, so the futures don't need to be `Send`
+#[allow(async_fn_in_trait)]
+pub trait DaysOffProvider {
+    /// Fetch the days off of the whole `year` in a single request, `None` when the service has no
+    /// data for it
+    ///
+    /// `week` sets the weekend days, for the services that only list the holidays.
+    async fn fetch_year_days_off<T: TcpConnect, D: Dns>(
+        &self,
+        client: &mut HttpClient<'_, T, D>,
+        year: u16,
+        week: WeekConfig,
+    ) -> Result<Option<YearDaysOff>, reqwless::Error>;
+
+    /// Get the ISO code of the country or subdivision the days off are for, like `ru` or `DE-BY`
+    ///
+    /// Tells apart the data stored for different configurations.
+    fn region(&self) -> &str;
+
+    /// Get the days off of `month` when the service can't be used, only the weekends by default
+    fn offline_days_off(&self, month: MonthDate, week: WeekConfig) -> DaysOffMask {
+        weekends_mask(month, week)
+    }
+}
+
+/// Mask with the weekend days of `week` off
+pub fn weekends_mask(month: MonthDate, week: WeekConfig) -> DaysOffMask {
+    DaysOffMask::default_days_off(month.to_start_day_naive().weekday(), week)
+}
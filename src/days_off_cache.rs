@@ -0,0 +1,288 @@
+//! Policy of the days off cache: what years are kept, when they are fetched again and how fresh
+//! the served data is
+
+use core::ops::RangeInclusive;
+
+use chrono::{Days, Months, NaiveDate};
+use heapless::{LinearMap, Vec};
+
+use crate::calendar_utils::{DaysOffMask, MonthDate, YearDaysOff};
+
+/// A year of days off, with the date it was fetched on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedYear {
+    pub days_off: YearDaysOff,
+    pub fetched_on: NaiveDate,
+}
+
+impl CachedYear {
+    /// Get the amount of days since the year was fetched, negative if the clock went back since
+    pub fn age(&self, today: NaiveDate) -> i64 {
+        (today - self.fetched_on).num_days()
+    }
+}
+
+/// Result of looking up the days off of a month
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// Fetched less than the revalidation age ago
+    Fresh {
+        mask: DaysOffMask,
+        age: i64,
+    },
+    /// Due for revalidation, but still the best data there is
+    Stale {
+        mask: DaysOffMask,
+        age: i64,
+    },
+    Miss,
+}
+
+/// Days off of the years shown by the previous, current and next months
+///
+/// The years are dropped when the month changes and they are no longer shown. They are fetched
+/// again after `revalidate_after`, and served stale until that succeeds.
+#[derive(Debug, Clone)]
+pub struct DaysOffCache {
+    /// The shown months span two years at most
+    years: LinearMap<u16, CachedYear, 2>,
+    /// Month the cache was rotated for last
+    month: Option<MonthDate>,
+    revalidate_after: Days,
+}
+
+impl DaysOffCache {
+    pub const fn new(revalidate_after: Days) -> Self {
+        Self {
+            years: LinearMap::new(),
+            month: None,
+            revalidate_after,
+        }
+    }
+
+    /// Cache a year, replacing the previous data of that year
+    ///
+    /// Returns the year back when the cache is full, it must be rotated first.
+    pub fn insert(&mut self, cached: CachedYear) -> Result<(), CachedYear> {
+        self.years
+            .insert(cached.days_off.year(), cached)
+            .map(|_| ())
+            .map_err(|(_, cached)| cached)
+    }
+
+    /// Cache years loaded from elsewhere, like flash, the years shown around `current_month` first
+    ///
+    /// The newest of the other years fill the rest of the room. Only the newest years are cached
+    /// when the current month is not known. Returns the years that didn't fit.
+    pub fn fill(
+        &mut self,
+        years: &[CachedYear],
+        current_month: Option<MonthDate>,
+    ) -> alloc::vec::Vec<u16> {
+        let is_shown = |cached: &&CachedYear| {
+            current_month.is_some_and(|month| shown_years(month).contains(&cached.days_off.year()))
+        };
+        let shown = years.iter().filter(is_shown);
+        let others = years.iter().rev().filter(|cached| !is_shown(cached));
+        shown
+            .chain(others)
+            .filter_map(|&cached| self.insert(cached).err())
+            .map(|cached| cached.days_off.year())
+            .collect()
+    }
+
+    /// Drop the years no longer shown when the current month changed since the last call
+    ///
+    /// Returns the dropped years.
+    pub fn rotate(&mut self, current_month: MonthDate) -> Vec<u16, 2> {
+        if self.month == Some(current_month) {
+            return Vec::new();
+        }
+        self.month = Some(current_month);
+        let shown = shown_years(current_month);
+        let old_years: Vec<u16, 2> = self
+            .years
+            .keys()
+            .filter(|year| !shown.contains(year))
+            .copied()
+            .collect();
+        for year in &old_years {
+            self.years.remove(year);
+        }
+        old_years
+    }
+
+    /// Get the shown years that are not cached or are due for revalidation
+    pub fn to_refresh(&self, current_month: MonthDate, today: NaiveDate) -> Vec<u16, 2> {
+        shown_years(current_month)
+            .filter(|year| {
+                self.years
+                    .get(year)
+                    .is_none_or(|cached| self.is_stale(cached, today))
+            })
+            .collect()
+    }
+
+    /// Get the days off of `month`
+    pub fn lookup(&self, month: MonthDate, today: NaiveDate) -> Lookup {
+        let Some(cached) = self.years.get(&month.year()) else {
+            return Lookup::Miss;
+        };
+        let Some(mask) = cached.days_off.month(month) else {
+            return Lookup::Miss;
+        };
+        let age = cached.age(today);
+        if self.is_stale(cached, today) {
+            Lookup::Stale { mask, age }
+        } else {
+            Lookup::Fresh { mask, age }
+        }
+    }
+
+    /// Iterate over the cached years
+    pub fn iter(&self) -> impl Iterator<Item = &CachedYear> {
+        self.years.values()
+    }
+
+    fn is_stale(&self, cached: &CachedYear, today: NaiveDate) -> bool {
+        today < cached.fetched_on
+            || cached
+                .fetched_on
+                .checked_add_days(self.revalidate_after)
+                .is_none_or(|due| today >= due)
+    }
+}
+
+/// Years of the previous, current and next months
+fn shown_years(current_month: MonthDate) -> RangeInclusive<u16> {
+    (current_month - Months::new(1)).year()..=(current_month + Months::new(1)).year()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, Month, NaiveDate};
+
+    use super::{CachedYear, DaysOffCache, Lookup};
+    use crate::calendar_utils::{DaysOffMask, MonthDate, YearDaysOff};
+
+    const MAX_AGE: Days = Days::new(30);
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn month(year: u16, month: Month) -> MonthDate {
+        MonthDate::new(year, month)
+    }
+
+    fn cached(year: u16, fetched_on: NaiveDate) -> CachedYear {
+        CachedYear {
+            days_off: YearDaysOff::from_fn(year, |month| {
+                DaysOffMask::from_days_off(month.month().number_from_month())
+            }),
+            fetched_on,
+        }
+    }
+
+    #[test]
+    fn empty_cache_misses() {
+        let cache = DaysOffCache::new(MAX_AGE);
+        let today = date(2025, 6, 15);
+        assert_eq!(cache.lookup(month(2025, Month::June), today), Lookup::Miss);
+        assert_eq!(cache.to_refresh(month(2025, Month::June), today), [2025]);
+        assert_eq!(
+            cache.to_refresh(month(2025, Month::January), today),
+            [2024, 2025]
+        );
+        assert_eq!(
+            cache.to_refresh(month(2025, Month::December), today),
+            [2025, 2026]
+        );
+    }
+
+    #[test]
+    fn fresh_until_revalidation_age() {
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        cache.insert(cached(2025, date(2025, 6, 1))).unwrap();
+        let june = month(2025, Month::June);
+        let mask = DaysOffMask::from_days_off(6);
+
+        let today = date(2025, 6, 30);
+        assert_eq!(cache.lookup(june, today), Lookup::Fresh { mask, age: 29 });
+        assert_eq!(cache.to_refresh(june, today), []);
+
+        let today = date(2025, 7, 1);
+        assert_eq!(cache.lookup(june, today), Lookup::Stale { mask, age: 30 });
+        assert_eq!(cache.to_refresh(june, today), [2025]);
+    }
+
+    #[test]
+    fn clock_going_back_revalidates() {
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        cache.insert(cached(2025, date(2025, 6, 1))).unwrap();
+        let june = month(2025, Month::June);
+        assert_eq!(cache.to_refresh(june, date(2025, 5, 31)), [2025]);
+    }
+
+    #[test]
+    fn refetched_year_replaces_stale_one() {
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        cache.insert(cached(2025, date(2025, 1, 1))).unwrap();
+        cache.insert(cached(2025, date(2025, 6, 1))).unwrap();
+        let june = month(2025, Month::June);
+        assert!(matches!(
+            cache.lookup(june, date(2025, 6, 2)),
+            Lookup::Fresh { age: 1, .. }
+        ));
+        assert_eq!(cache.iter().count(), 1);
+    }
+
+    #[test]
+    fn rotates_only_when_month_changes() {
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        cache.insert(cached(2024, date(2025, 1, 1))).unwrap();
+        cache.insert(cached(2025, date(2025, 1, 1))).unwrap();
+        // Full until the december of the previous year is no longer shown
+        assert!(cache.insert(cached(2026, date(2025, 1, 1))).is_err());
+
+        assert_eq!(cache.rotate(month(2025, Month::January)), []);
+        assert_eq!(cache.rotate(month(2025, Month::February)), [2024]);
+        assert_eq!(cache.rotate(month(2025, Month::February)), []);
+        assert_eq!(cache.iter().count(), 1);
+        assert!(cache.insert(cached(2026, date(2025, 1, 1))).is_ok());
+    }
+
+    #[test]
+    fn first_rotation_drops_loaded_years_not_shown() {
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        cache.insert(cached(2023, date(2023, 5, 1))).unwrap();
+        cache.insert(cached(2025, date(2025, 5, 1))).unwrap();
+        assert_eq!(cache.rotate(month(2025, Month::May)), [2023]);
+        assert!(matches!(
+            cache.lookup(month(2025, Month::May), date(2025, 5, 2)),
+            Lookup::Fresh { .. }
+        ));
+    }
+
+    #[test]
+    fn fills_with_the_shown_years_first() {
+        let stored = [2025, 2026, 2027, 2028].map(|year| cached(year, date(2026, 12, 1)));
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        // The December of the previous year is still shown in January
+        assert_eq!(
+            cache.fill(&stored, Some(month(2027, Month::January))),
+            [2028, 2025]
+        );
+        assert_eq!(cache.rotate(month(2027, Month::January)), []);
+
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        assert_eq!(
+            cache.fill(&stored, Some(month(2026, Month::June))),
+            [2027, 2025]
+        );
+        assert!(cache.iter().any(|cached| cached.days_off.year() == 2028));
+
+        let mut cache = DaysOffCache::new(MAX_AGE);
+        assert_eq!(cache.fill(&stored, None), [2026, 2025]);
+    }
+}
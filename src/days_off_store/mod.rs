@@ -0,0 +1,281 @@
+//! Days off cache kept in flash, so it survives reboots
+//!
+//! Entries are appended as fixed size records to a ring of flash sectors, and the newest record of
+//! each year wins. A sector is only erased when the ring comes back to it, so the erases are spread
+//! evenly over the whole range. The records that are still the newest for their year are moved
+//! forward before their sector is erased. That's meant for a few entries, much less than fit in a
+//! sector.
+//!
+//! A record is laid out as follows, in little endian:
+//!
+//! | Offset | Size | Field                                      |
+//! |--------|------|--------------------------------------------|
+//! | 0      | 2    | Magic, `0xDA70`                            |
+//! | 2      | 1    | Format version, [`FORMAT_VERSION`]         |
+//! | 3      | 1    | Length of the region code                  |
+//! | 4      | 4    | Sequence number, increases with each write |
+//! | 8      | 2    | Year                                       |
+//! | 10     | 8    | Region code, zero padded                   |
+//! | 18     | 2    | Zero                                       |
+//! | 20     | 4    | Fetch date, days from CE                   |
+//! | 24     | 96   | Days off masks of the 12 months            |
+//! | 120    | 4    | CRC-32 of the previous bytes               |
+//! | 124    | 4    | Zero                                       |
+//!
+//! Records of other format versions, torn writes and erased slots are all skipped.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use chrono::{Datelike, NaiveDate};
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{
+    calendar_utils::{DaysOffMask, YearDaysOff},
+    crc::crc32,
+    days_off_cache::CachedYear,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Increase when the record layout changes, older records are then ignored
+pub const FORMAT_VERSION: u8 = 1;
+/// Region codes, like `ru` or `DE-BY`, are stored in this many bytes
+pub const MAX_REGION_LEN: usize = 8;
+
+const MAGIC: u16 = 0xDA70;
+const SLOT_SIZE: usize = 128;
+const CRC_OFFSET: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreError<E> {
+    Flash(E),
+    /// The flash range is not made of at least two whole sectors
+    InvalidRange,
+    /// The region code is longer than [`MAX_REGION_LEN`]
+    RegionTooLong,
+}
+
+impl<E> From<E> for StoreError<E> {
+    fn from(e: E) -> Self {
+        Self::Flash(e)
+    }
+}
+
+/// A record read back from flash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Record {
+    seq: u32,
+    region: [u8; MAX_REGION_LEN],
+    region_len: u8,
+    entry: CachedYear,
+}
+
+impl Record {
+    fn region(&self) -> &[u8] {
+        &self.region[..self.region_len as usize]
+    }
+
+    fn same_key(&self, other: &Self) -> bool {
+        self.region() == other.region() && self.entry.days_off.year() == other.entry.days_off.year()
+    }
+
+    fn encode(&self) -> [u8; SLOT_SIZE] {
+        let mut buf = [0; SLOT_SIZE];
+        buf[0..2].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[2] = FORMAT_VERSION;
+        buf[3] = self.region_len;
+        buf[4..8].copy_from_slice(&self.seq.to_le_bytes());
+        buf[8..10].copy_from_slice(&self.entry.days_off.year().to_le_bytes());
+        buf[10..18].copy_from_slice(&self.region);
+        buf[20..24].copy_from_slice(&self.entry.fetched_on.num_days_from_ce().to_le_bytes());
+        for (idx, mask) in self.entry.days_off.months().iter().enumerate() {
+            let offset = 24 + idx * 8;
+            buf[offset..offset + 8].copy_from_slice(&mask.to_bits().to_le_bytes());
+        }
+        let crc = crc32(&buf[..CRC_OFFSET]);
+        buf[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8; SLOT_SIZE]) -> Option<Self> {
+        let u16_at = |offset: usize| u16::from_le_bytes([buf[offset], buf[offset + 1]]);
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
+        if u16_at(0) != MAGIC
+            || buf[2] != FORMAT_VERSION
+            || u32_at(CRC_OFFSET) != crc32(&buf[..CRC_OFFSET])
+        {
+            return None;
+        }
+        let region_len = buf[3];
+        if region_len as usize > MAX_REGION_LEN {
+            return None;
+        }
+        let fetched_on = NaiveDate::from_num_days_from_ce_opt(u32_at(20) as i32)?;
+        let months = core::array::from_fn(|idx| {
+            let offset = 24 + idx * 8;
+            DaysOffMask::from_bits(u64::from_le_bytes(
+                buf[offset..offset + 8].try_into().unwrap(),
+            ))
+        });
+        Some(Self {
+            seq: u32_at(4),
+            region: buf[10..18].try_into().unwrap(),
+            region_len,
+            entry: CachedYear {
+                days_off: YearDaysOff::new(u16_at(8), months),
+                fetched_on,
+            },
+        })
+    }
+}
+
+/// Days off cache in a range of flash, see the [module docs](self) for the layout
+pub struct DaysOffStore<F> {
+    flash: F,
+    start: u32,
+    slots: u32,
+    /// Slot the next record is written to
+    next: u32,
+    /// Sequence number of the next record
+    seq: u32,
+}
+
+impl<F: NorFlash> DaysOffStore<F> {
+    const SLOTS_PER_SECTOR: u32 = (F::ERASE_SIZE / SLOT_SIZE) as u32;
+
+    /// Open the store in the `range` of flash addresses, it must be made of at least two whole
+    /// sectors
+    ///
+    /// Flash that was never written to can be used as is, it reads as an empty store.
+    pub fn new(flash: F, range: Range<u32>) -> Result<Self, StoreError<F::Error>> {
+        let sector_size = F::ERASE_SIZE as u32;
+        if !range.start.is_multiple_of(sector_size)
+            || !range.end.is_multiple_of(sector_size)
+            || range.end < range.start + 2 * sector_size
+            || !F::ERASE_SIZE.is_multiple_of(SLOT_SIZE)
+            || !SLOT_SIZE.is_multiple_of(F::WRITE_SIZE)
+        {
+            return Err(StoreError::InvalidRange);
+        }
+        let mut store = Self {
+            flash,
+            start: range.start,
+            slots: (range.end - range.start) / SLOT_SIZE as u32,
+            next: 0,
+            seq: 0,
+        };
+        let mut newest: Option<(u32, u32)> = None;
+        for slot in 0..store.slots {
+            if let Some(record) = store.read_slot(slot)?
+                && newest.is_none_or(|(_, seq)| record.seq > seq)
+            {
+                newest = Some((slot, record.seq));
+            }
+        }
+        if let Some((slot, seq)) = newest {
+            store.next = (slot + 1) % store.slots;
+            store.seq = seq.wrapping_add(1);
+        }
+        Ok(store)
+    }
+
+    /// Get the newest entry of each year stored for `region`, ordered by year
+    pub fn load(&mut self, region: &str) -> Result<Vec<CachedYear>, StoreError<F::Error>> {
+        let mut res: Vec<CachedYear> = self
+            .newest_records()?
+            .into_iter()
+            .filter(|(_, record)| record.region() == region.as_bytes())
+            .map(|(_, record)| record.entry)
+            .collect();
+        res.sort_by_key(|entry| entry.days_off.year());
+        Ok(res)
+    }
+
+    /// Store `entry` for `region`, replacing the previous entry of the same year
+    pub fn save(&mut self, region: &str, entry: CachedYear) -> Result<(), StoreError<F::Error>> {
+        if region.len() > MAX_REGION_LEN {
+            return Err(StoreError::RegionTooLong);
+        }
+        let mut region_buf = [0; MAX_REGION_LEN];
+        region_buf[..region.len()].copy_from_slice(region.as_bytes());
+        let mut record = Record {
+            seq: 0,
+            region: region_buf,
+            region_len: region.len() as u8,
+            entry,
+        };
+        loop {
+            if self.next.is_multiple_of(Self::SLOTS_PER_SECTOR) {
+                self.erase_next_sector()?;
+            }
+            if self.is_slot_erased(self.next)? {
+                record.seq = self.seq;
+                return self.write_record(&record);
+            }
+            // Left over from an interrupted write
+            self.next = (self.next + 1) % self.slots;
+        }
+    }
+
+    /// Get the newest record of each region and year, with their slots
+    fn newest_records(&mut self) -> Result<Vec<(u32, Record)>, StoreError<F::Error>> {
+        let mut res: Vec<(u32, Record)> = Vec::new();
+        for slot in 0..self.slots {
+            let Some(record) = self.read_slot(slot)? else {
+                continue;
+            };
+            match res.iter_mut().find(|(_, other)| other.same_key(&record)) {
+                Some(other) if other.1.seq < record.seq => *other = (slot, record),
+                Some(_) => {}
+                None => res.push((slot, record)),
+            }
+        }
+        Ok(res)
+    }
+
+    /// Erase the sector of the next slot, moving the records still in use to its start
+    fn erase_next_sector(&mut self) -> Result<(), StoreError<F::Error>> {
+        let sector_slots = self.next..self.next + Self::SLOTS_PER_SECTOR;
+        let live: Vec<Record> = self
+            .newest_records()?
+            .into_iter()
+            .filter(|(slot, _)| sector_slots.contains(slot))
+            .map(|(_, record)| record)
+            .collect();
+        let from = self.slot_addr(self.next);
+        self.flash.erase(from, from + F::ERASE_SIZE as u32)?;
+        for mut record in live {
+            record.seq = self.seq;
+            self.write_record(&record)?;
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), StoreError<F::Error>> {
+        let addr = self.slot_addr(self.next);
+        // Moved past the slot first, a failed write leaves it unusable until the sector is erased
+        self.next = (self.next + 1) % self.slots;
+        self.seq = self.seq.wrapping_add(1);
+        self.flash.write(addr, &record.encode())?;
+        Ok(())
+    }
+
+    fn read_slot(&mut self, slot: u32) -> Result<Option<Record>, StoreError<F::Error>> {
+        let mut buf = [0; SLOT_SIZE];
+        self.flash.read(self.slot_addr(slot), &mut buf)?;
+        Ok(Record::decode(&buf))
+    }
+
+    fn is_slot_erased(&mut self, slot: u32) -> Result<bool, StoreError<F::Error>> {
+        let mut buf = [0; SLOT_SIZE];
+        self.flash.read(self.slot_addr(slot), &mut buf)?;
+        Ok(buf.iter().all(|byte| *byte == 0xFF))
+    }
+
+    const fn slot_addr(&self, slot: u32) -> u32 {
+        self.start + slot * SLOT_SIZE as u32
+    }
+}
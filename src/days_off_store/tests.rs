@@ -0,0 +1,243 @@
+use chrono::{Month, NaiveDate};
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+use super::{CRC_OFFSET, DaysOffStore, Record, SLOT_SIZE, StoreError};
+use crate::{
+    calendar_utils::{DaysOffMask, MonthDate, YearDaysOff},
+    crc::crc32,
+    days_off_cache::CachedYear,
+};
+
+const SECTOR_SIZE: usize = 4096;
+const SECTORS: usize = 4;
+const RANGE: core::ops::Range<u32> = 0..(SECTORS * SECTOR_SIZE) as u32;
+
+/// NOR flash in memory, writes can only clear bits
+struct SimFlash {
+    data: Vec<u8>,
+    erases: [u32; SECTORS],
+    /// Bytes that can still be written before writes fail, to simulate a power loss
+    write_budget: Option<usize>,
+}
+
+impl SimFlash {
+    fn new() -> Self {
+        Self {
+            data: vec![0xFF; SECTORS * SECTOR_SIZE],
+            erases: [0; SECTORS],
+            write_budget: None,
+        }
+    }
+}
+
+impl ErrorType for SimFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for SimFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for SimFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        assert_eq!(from as usize % SECTOR_SIZE, 0);
+        assert_eq!(to as usize % SECTOR_SIZE, 0);
+        self.data[from as usize..to as usize].fill(0xFF);
+        for sector in from as usize / SECTOR_SIZE..to as usize / SECTOR_SIZE {
+            self.erases[sector] += 1;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        assert_eq!(offset as usize % Self::WRITE_SIZE, 0);
+        assert_eq!(bytes.len() % Self::WRITE_SIZE, 0);
+        let offset = offset as usize;
+        let len = match self.write_budget {
+            Some(budget) => budget.min(bytes.len()),
+            None => bytes.len(),
+        };
+        for (target, byte) in self.data[offset..offset + len].iter_mut().zip(bytes) {
+            assert_eq!(*target, 0xFF, "writing over data that is not erased");
+            *target &= byte;
+        }
+        if let Some(budget) = &mut self.write_budget {
+            *budget -= len;
+            if len < bytes.len() {
+                return Err(NorFlashErrorKind::Other);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn entry(year: u16, marker: u32, fetched_on: (i32, u32, u32)) -> CachedYear {
+    CachedYear {
+        days_off: YearDaysOff::from_fn(year, |month| {
+            DaysOffMask::from_days_off(marker ^ month.month().number_from_month())
+        }),
+        fetched_on: NaiveDate::from_ymd_opt(fetched_on.0, fetched_on.1, fetched_on.2).unwrap(),
+    }
+}
+
+fn open(flash: SimFlash) -> DaysOffStore<SimFlash> {
+    DaysOffStore::new(flash, RANGE).unwrap()
+}
+
+#[test]
+fn empty_flash_has_no_entries() {
+    let mut store = open(SimFlash::new());
+    assert_eq!(store.load("ru").unwrap(), []);
+}
+
+#[test]
+fn saved_entries_are_loaded_after_reopening() {
+    let mut store = open(SimFlash::new());
+    let year_2025 = entry(2025, 0b101, (2025, 1, 10));
+    let year_2024 = entry(2024, 0b11, (2024, 12, 1));
+    store.save("ru", year_2025).unwrap();
+    store.save("ru", year_2024).unwrap();
+
+    let mut store = open(store.flash);
+    assert_eq!(store.load("ru").unwrap(), [year_2024, year_2025]);
+}
+
+#[test]
+fn newest_entry_of_a_year_wins() {
+    let mut store = open(SimFlash::new());
+    store.save("ru", entry(2025, 1, (2025, 1, 1))).unwrap();
+    store.save("ru", entry(2025, 2, (2025, 2, 1))).unwrap();
+    let mut store = open(store.flash);
+    store.save("ru", entry(2025, 3, (2025, 3, 1))).unwrap();
+
+    let mut store = open(store.flash);
+    assert_eq!(store.load("ru").unwrap(), [entry(2025, 3, (2025, 3, 1))]);
+}
+
+#[test]
+fn regions_are_kept_apart() {
+    let mut store = open(SimFlash::new());
+    store.save("ru", entry(2025, 1, (2025, 1, 1))).unwrap();
+    store.save("DE-BY", entry(2025, 2, (2025, 1, 1))).unwrap();
+    assert_eq!(store.load("ru").unwrap(), [entry(2025, 1, (2025, 1, 1))]);
+    assert_eq!(store.load("DE-BY").unwrap(), [entry(2025, 2, (2025, 1, 1))]);
+    assert_eq!(store.load("by").unwrap(), []);
+    assert_eq!(
+        store.save("too-long-region", entry(2025, 2, (2025, 1, 1))),
+        Err(StoreError::RegionTooLong)
+    );
+}
+
+#[test]
+fn erases_are_spread_over_sectors() {
+    let mut store = open(SimFlash::new());
+    // Saved once, has to survive its sector being erased
+    let old = entry(2024, 7, (2024, 6, 1));
+    store.save("ru", old).unwrap();
+    let slots = SECTORS * SECTOR_SIZE / SLOT_SIZE;
+    for idx in 0..slots as u32 * 5 {
+        store.save("ru", entry(2025, idx, (2025, 1, 1))).unwrap();
+        if idx % 97 == 0 {
+            store = open(store.flash);
+        }
+    }
+    let last = slots as u32 * 5 - 1;
+    assert_eq!(
+        store.load("ru").unwrap(),
+        [old, entry(2025, last, (2025, 1, 1))]
+    );
+    let erases = store.flash.erases;
+    let (min, max) = (erases.iter().min().unwrap(), erases.iter().max().unwrap());
+    assert!(max - min <= 1, "uneven wear {erases:?}");
+    assert!(*max <= 6, "too many erases {erases:?}");
+}
+
+#[test]
+fn interrupted_write_keeps_previous_entry() {
+    let mut store = open(SimFlash::new());
+    let saved = entry(2025, 1, (2025, 1, 1));
+    store.save("ru", saved).unwrap();
+    store.flash.write_budget = Some(60);
+    assert_eq!(
+        store.save("ru", entry(2025, 2, (2025, 2, 1))),
+        Err(StoreError::Flash(NorFlashErrorKind::Other))
+    );
+
+    let mut flash = store.flash;
+    flash.write_budget = None;
+    let mut store = open(flash);
+    assert_eq!(store.load("ru").unwrap(), [saved]);
+    // The torn slot is skipped
+    let next = entry(2025, 3, (2025, 3, 1));
+    store.save("ru", next).unwrap();
+    let mut store = open(store.flash);
+    assert_eq!(store.load("ru").unwrap(), [next]);
+}
+
+#[test]
+fn other_format_versions_are_ignored() {
+    let mut flash = SimFlash::new();
+    let record = Record {
+        seq: 0,
+        region: *b"ru\0\0\0\0\0\0",
+        region_len: 2,
+        entry: entry(2025, 1, (2025, 1, 1)),
+    };
+    let mut buf = record.encode();
+    buf[2] += 1;
+    let crc = crc32(&buf[..CRC_OFFSET]);
+    buf[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+    flash.write(0, &buf).unwrap();
+
+    let mut store = open(flash);
+    assert_eq!(store.load("ru").unwrap(), []);
+}
+
+#[test]
+fn corrupted_records_are_ignored() {
+    let mut store = open(SimFlash::new());
+    store.save("ru", entry(2025, 1, (2025, 1, 1))).unwrap();
+    // Clear a bit of the february mask, its first two days are days off
+    assert_eq!(store.flash.data[32], 0b0101);
+    store.flash.data[32] = 0b0100;
+    assert_eq!(store.load("ru").unwrap(), []);
+}
+
+#[test]
+fn record_round_trip() {
+    let record = Record {
+        seq: 0x1234_5678,
+        region: *b"DE-BY\0\0\0",
+        region_len: 5,
+        entry: entry(2028, 0xABCD, (2027, 12, 31)),
+    };
+    assert_eq!(Record::decode(&record.encode()), Some(record));
+    let may = record
+        .entry
+        .days_off
+        .month(MonthDate::new(2028, Month::May))
+        .unwrap();
+    assert_eq!(may, DaysOffMask::from_days_off(0xABCD ^ 5));
+}
+
+#[test]
+fn rejects_invalid_ranges() {
+    let range_error = |range| DaysOffStore::new(SimFlash::new(), range).err();
+    assert_eq!(range_error(0..4096), Some(StoreError::InvalidRange));
+    assert_eq!(range_error(100..8192), Some(StoreError::InvalidRange));
+    assert_eq!(range_error(0..8000), Some(StoreError::InvalidRange));
+    assert_eq!(range_error(4096..12288), None);
+}
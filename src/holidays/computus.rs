@@ -0,0 +1,78 @@
+//! Easter date calculation
+
+use chrono::{Days, NaiveDate};
+
+/// Get the date of the western Easter Sunday, per the Gregorian calendar
+pub fn gregorian_easter(year: i32) -> NaiveDate {
+    // Anonymous Gregorian algorithm, also known as Meeus/Jones/Butcher
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+/// Get the date of the orthodox Easter Sunday, converted to the Gregorian calendar
+pub fn orthodox_easter(year: i32) -> NaiveDate {
+    // Meeus Julian algorithm
+    let a = year % 4;
+    let b = year % 7;
+    let c = year % 19;
+    let d = (19 * c + 15) % 30;
+    let e = (2 * a + 4 * b - d + 34) % 7;
+    let month = (d + e + 114) / 31;
+    let day = (d + e + 114) % 31 + 1;
+    // Easter is after the leap day, so the difference between the calendars is that of the year
+    let julian_shift = year / 100 - year / 400 - 2;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+        + Days::new(julian_shift as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, NaiveDate, Weekday};
+
+    use super::{gregorian_easter, orthodox_easter};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn gregorian_easter_dates() {
+        assert_eq!(gregorian_easter(2000), date(2000, 4, 23));
+        assert_eq!(gregorian_easter(2019), date(2019, 4, 21));
+        assert_eq!(gregorian_easter(2024), date(2024, 3, 31));
+        assert_eq!(gregorian_easter(2025), date(2025, 4, 20));
+        assert_eq!(gregorian_easter(2026), date(2026, 4, 5));
+        assert_eq!(gregorian_easter(2038), date(2038, 4, 25));
+    }
+
+    #[test]
+    fn orthodox_easter_dates() {
+        assert_eq!(orthodox_easter(2000), date(2000, 4, 30));
+        assert_eq!(orthodox_easter(2019), date(2019, 4, 28));
+        assert_eq!(orthodox_easter(2021), date(2021, 5, 2));
+        assert_eq!(orthodox_easter(2024), date(2024, 5, 5));
+        assert_eq!(orthodox_easter(2025), date(2025, 4, 20));
+        assert_eq!(orthodox_easter(2026), date(2026, 4, 12));
+    }
+
+    #[test]
+    fn easter_is_sunday() {
+        for year in 1900..2200 {
+            assert_eq!(gregorian_easter(year).weekday(), Weekday::Sun, "{year}");
+            assert_eq!(orthodox_easter(year).weekday(), Weekday::Sun, "{year}");
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Offline rules for the public holidays of the supported countries
+//!
+//! Used when no days off data can be fetched. Covers the fixed date and Easter based holidays and
+//! moving the holidays that fall on a weekend to the next workday.
+
+use alloc::vec::Vec;
+
+use chrono::{Datelike, Days, Months, NaiveDate, TimeDelta};
+
+use crate::calendar_utils::{DayKind, DaysOffMask, MonthDate, WeekConfig};
+
+mod computus;
+mod rules;
+#[cfg(test)]
+mod tests;
+
+pub use computus::{gregorian_easter, orthodox_easter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetCountry {
+    Belarus,
+    Kazakhstan,
+    Russia,
+    Ukraine,
+}
+
+impl TargetCountry {
+    pub const fn to_countrycode(self) -> &'static str {
+        match self {
+            Self::Belarus => "by",
+            Self::Kazakhstan => "kz",
+            Self::Russia => "ru",
+            Self::Ukraine => "ua",
+        }
+    }
+}
+
+/// Date of a holiday in any year
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HolidayDate {
+    Fixed {
+        month: u8,
+        day: u8,
+    },
+    /// Days after the western Easter
+    GregorianEaster(i16),
+    /// Days after the orthodox Easter
+    OrthodoxEaster(i16),
+}
+
+impl HolidayDate {
+    pub fn in_year(self, year: i32) -> NaiveDate {
+        let (easter, offset) = match self {
+            Self::Fixed { month, day } => {
+                return NaiveDate::from_ymd_opt(year, month.into(), day.into()).unwrap();
+            }
+            Self::GregorianEaster(offset) => (gregorian_easter(year), offset),
+            Self::OrthodoxEaster(offset) => (orthodox_easter(year), offset),
+        };
+        easter + TimeDelta::days(offset.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Holiday {
+    pub date: HolidayDate,
+    /// Falling on a weekend moves the day off to the next workday
+    pub transfer: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountryRules {
+    pub holidays: &'static [Holiday],
+    /// The workday before a holiday is shortened
+    pub shortened_eves: bool,
+}
+
+/// Compute the days off of `month` in `country`, with the weekend days of `week`
+///
+/// Days off moved from holidays are marked as weekends, like isdayoff does.
+pub fn holidays_mask(country: TargetCountry, month: MonthDate, week: WeekConfig) -> DaysOffMask {
+    let rules = country.rules();
+    let year = i32::from(month.year());
+    // Holidays at the ends of the neighbour years can move days off or shorten days of this month
+    let mut holidays: Vec<(NaiveDate, bool)> = (year - 1..=year + 1)
+        .flat_map(|year| {
+            rules
+                .holidays
+                .iter()
+                .map(move |holiday| (holiday.date.in_year(year), holiday.transfer))
+        })
+        .collect();
+    holidays.sort_unstable();
+
+    let is_weekend = |date: NaiveDate| week.is_weekend(date.weekday());
+    let is_holiday = |date: NaiveDate| holidays.iter().any(|(holiday, _)| *holiday == date);
+    let mut transfers = Vec::new();
+    for &(holiday, transfer) in &holidays {
+        if !transfer || !is_weekend(holiday) {
+            continue;
+        }
+        let mut date = holiday + Days::new(1);
+        while is_weekend(date) || is_holiday(date) || transfers.contains(&date) {
+            date = date + Days::new(1);
+        }
+        transfers.push(date);
+    }
+
+    let start = month.to_start_day_naive();
+    let end = (month + Months::new(1)).to_start_day_naive();
+    let mut mask = DaysOffMask::default();
+    for (day, date) in start.iter_days().take_while(|date| *date < end).enumerate() {
+        let kind = if is_holiday(date) {
+            DayKind::PublicHoliday
+        } else if is_weekend(date) || transfers.contains(&date) {
+            DayKind::Weekend
+        } else if rules.shortened_eves && is_holiday(date + Days::new(1)) {
+            DayKind::Shortened
+        } else {
+            DayKind::Workday
+        };
+        mask = mask.with_day0(day as u8, kind);
+    }
+    mask
+}
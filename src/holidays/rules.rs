@@ -0,0 +1,119 @@
+//! Public holidays of the supported countries
+//!
+//! Only the rules set by the labour codes are described. Days off moved by yearly government
+//! decrees, like the new year holidays in Russia, can't be known in advance.
+
+use super::{CountryRules, Holiday, HolidayDate, TargetCountry};
+
+const fn fixed(month: u8, day: u8) -> HolidayDate {
+    HolidayDate::Fixed { month, day }
+}
+
+const fn transferred(date: HolidayDate) -> Holiday {
+    Holiday {
+        date,
+        transfer: true,
+    }
+}
+
+const fn kept(date: HolidayDate) -> Holiday {
+    Holiday {
+        date,
+        transfer: false,
+    }
+}
+
+const BELARUS: CountryRules = CountryRules {
+    holidays: &[
+        kept(fixed(1, 1)),
+        kept(fixed(1, 2)),
+        // Orthodox Christmas
+        kept(fixed(1, 7)),
+        kept(fixed(3, 8)),
+        kept(HolidayDate::GregorianEaster(0)),
+        kept(HolidayDate::OrthodoxEaster(0)),
+        // Radunitsa
+        kept(HolidayDate::OrthodoxEaster(9)),
+        kept(fixed(5, 1)),
+        kept(fixed(5, 9)),
+        kept(fixed(7, 3)),
+        kept(fixed(11, 7)),
+        // Catholic Christmas
+        kept(fixed(12, 25)),
+    ],
+    shortened_eves: true,
+};
+
+const KAZAKHSTAN: CountryRules = CountryRules {
+    holidays: &[
+        transferred(fixed(1, 1)),
+        transferred(fixed(1, 2)),
+        // Religious holidays are not moved
+        kept(fixed(1, 7)),
+        transferred(fixed(3, 8)),
+        // Nauryz
+        transferred(fixed(3, 21)),
+        transferred(fixed(3, 22)),
+        transferred(fixed(3, 23)),
+        transferred(fixed(5, 1)),
+        transferred(fixed(5, 7)),
+        transferred(fixed(5, 9)),
+        transferred(fixed(7, 6)),
+        transferred(fixed(8, 30)),
+        transferred(fixed(10, 25)),
+        transferred(fixed(12, 16)),
+    ],
+    shortened_eves: false,
+};
+
+const RUSSIA: CountryRules = CountryRules {
+    holidays: &[
+        // Weekends that fall on the new year holidays are moved by decree
+        kept(fixed(1, 1)),
+        kept(fixed(1, 2)),
+        kept(fixed(1, 3)),
+        kept(fixed(1, 4)),
+        kept(fixed(1, 5)),
+        kept(fixed(1, 6)),
+        kept(fixed(1, 7)),
+        kept(fixed(1, 8)),
+        transferred(fixed(2, 23)),
+        transferred(fixed(3, 8)),
+        transferred(fixed(5, 1)),
+        transferred(fixed(5, 9)),
+        transferred(fixed(6, 12)),
+        transferred(fixed(11, 4)),
+    ],
+    shortened_eves: true,
+};
+
+/// Days off for the holidays are suspended while martial law is in effect, that is not accounted
+/// for
+const UKRAINE: CountryRules = CountryRules {
+    holidays: &[
+        transferred(fixed(1, 1)),
+        transferred(fixed(3, 8)),
+        transferred(HolidayDate::OrthodoxEaster(0)),
+        // Trinity
+        transferred(HolidayDate::OrthodoxEaster(49)),
+        transferred(fixed(5, 1)),
+        transferred(fixed(5, 8)),
+        transferred(fixed(6, 28)),
+        transferred(fixed(7, 15)),
+        transferred(fixed(8, 24)),
+        transferred(fixed(10, 1)),
+        transferred(fixed(12, 25)),
+    ],
+    shortened_eves: true,
+};
+
+impl TargetCountry {
+    pub(super) const fn rules(self) -> &'static CountryRules {
+        match self {
+            Self::Belarus => &BELARUS,
+            Self::Kazakhstan => &KAZAKHSTAN,
+            Self::Russia => &RUSSIA,
+            Self::Ukraine => &UKRAINE,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{Month, NaiveDate};
+
+use super::{HolidayDate, TargetCountry, holidays_mask};
+use crate::calendar_utils::{DayKind, DaysOffMask, MonthDate, WeekConfig};
+
+fn mask(country: TargetCountry, year: u16, month: u8) -> DaysOffMask {
+    let month = MonthDate::new(year, Month::try_from(month).unwrap());
+    holidays_mask(country, month, WeekConfig::ISO)
+}
+
+/// Get the 1-based days of the kind
+fn days(mask: DaysOffMask, kind: DayKind) -> Vec<u8> {
+    (1..=31)
+        .filter(|day| mask.day1_kind(*day) == kind)
+        .collect()
+}
+
+#[test]
+fn russia_new_year_holidays_are_not_moved() {
+    let mask = mask(TargetCountry::Russia, 2025, 1);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(days(mask, DayKind::Weekend), [11, 12, 18, 19, 25, 26]);
+    assert_eq!(days(mask, DayKind::Shortened), []);
+}
+
+#[test]
+fn russia_holidays_on_weekend_move_to_next_workday() {
+    // May 1st is a saturday and May 9th is a sunday
+    let mask = mask(TargetCountry::Russia, 2021, 5);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [1, 9]);
+    assert_eq!(
+        days(mask, DayKind::Weekend),
+        [2, 3, 8, 10, 15, 16, 22, 23, 29, 30]
+    );
+}
+
+#[test]
+fn russia_eves_are_shortened() {
+    // November 4th is a saturday, the eve is a friday
+    let mask = mask(TargetCountry::Russia, 2023, 11);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [4]);
+    assert_eq!(days(mask, DayKind::Shortened), [3]);
+    assert_eq!(mask.day1_kind(6), DayKind::Weekend);
+}
+
+#[test]
+fn eve_of_next_year_is_shortened() {
+    let mask = mask(TargetCountry::Russia, 2025, 12);
+    assert_eq!(days(mask, DayKind::Shortened), [31]);
+}
+
+#[test]
+fn kazakhstan_nauryz_moves_past_weekend() {
+    // March 8th is a saturday, Nauryz is from friday to sunday
+    let mask = mask(TargetCountry::Kazakhstan, 2025, 3);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [8, 21, 22, 23]);
+    assert_eq!(
+        days(mask, DayKind::Weekend),
+        [1, 2, 9, 10, 15, 16, 24, 25, 29, 30]
+    );
+    assert_eq!(days(mask, DayKind::Shortened), []);
+}
+
+#[test]
+fn kazakhstan_christmas_is_not_moved() {
+    let mask = mask(TargetCountry::Kazakhstan, 2024, 1);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [1, 2, 7]);
+    assert_eq!(mask.day1_kind(8), DayKind::Workday);
+}
+
+#[test]
+fn holiday_moves_into_next_month() {
+    // August 30th is a saturday
+    let mask = mask(TargetCountry::Kazakhstan, 2025, 9);
+    assert_eq!(mask.day1_kind(1), DayKind::Weekend);
+    assert_eq!(mask.day1_kind(2), DayKind::Workday);
+}
+
+#[test]
+fn belarus_easter_holidays() {
+    let mask = mask(TargetCountry::Belarus, 2025, 4);
+    // Both Easters are on the 20th, Radunitsa is 9 days after the orthodox one
+    assert_eq!(days(mask, DayKind::PublicHoliday), [20, 29]);
+    // The 30th is the eve of May 1st
+    assert_eq!(days(mask, DayKind::Shortened), [28, 30]);
+    assert_eq!(mask.day1_kind(21), DayKind::Workday);
+}
+
+#[test]
+fn ukraine_easter_moves_to_monday() {
+    let mask = mask(TargetCountry::Ukraine, 2025, 4);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [20]);
+    assert_eq!(mask.day1_kind(21), DayKind::Weekend);
+    assert_eq!(mask.day1_kind(18), DayKind::Workday);
+}
+
+#[test]
+fn days_past_month_end_are_empty() {
+    let mask = mask(TargetCountry::Russia, 2025, 2);
+    assert_eq!(days(mask, DayKind::PublicHoliday), [23]);
+    assert_eq!(mask.day1_kind(29), DayKind::Workday);
+    assert_eq!(mask.day1_kind(30), DayKind::Workday);
+    assert_eq!(mask, mask.truncate(28));
+}
+
+#[test]
+fn other_weekend_days() {
+    let week = WeekConfig::new(chrono::Weekday::Sun, &[chrono::Weekday::Sun]);
+    let month = MonthDate::new(2021, Month::May);
+    let mask = holidays_mask(TargetCountry::Russia, month, week);
+    // Saturday is a workday, only the sunday holiday moves
+    assert_eq!(mask.day1_kind(1), DayKind::PublicHoliday);
+    assert_eq!(mask.day1_kind(8), DayKind::Shortened);
+    assert_eq!(mask.day1_kind(10), DayKind::Weekend);
+    assert_eq!(mask.day1_kind(3), DayKind::Workday);
+}
+
+#[test]
+fn easter_offsets() {
+    assert_eq!(
+        HolidayDate::OrthodoxEaster(49).in_year(2025),
+        NaiveDate::from_ymd_opt(2025, 6, 8).unwrap()
+    );
+    assert_eq!(
+        HolidayDate::GregorianEaster(-2).in_year(2024),
+        NaiveDate::from_ymd_opt(2024, 3, 29).unwrap()
+    );
+}
@@ -0,0 +1,196 @@
+//! HTTP client of the web services, with `https` URLs verified against a CA certificate
+//!
+//! reqwless can only open TLS connections without verifying the server, so the connections are
+//! opened here and reqwless only speaks HTTP over them. The requests and responses are the ones of
+//! reqwless.
+
+use core::net::SocketAddr;
+#[cfg(not(feature = "tls"))]
+use core::{convert::Infallible, marker::PhantomData};
+
+use embedded_io::{Error as _, ErrorKind, ErrorType};
+use embedded_io_async::{Read, Write};
+use embedded_nal_async::{AddrType, Dns, TcpConnect};
+use nourl::{Url, UrlScheme};
+use reqwless::{
+    Error,
+    client::{HttpConnection, HttpResource},
+    request::{Method, Request, RequestBuilder},
+    response::Response,
+};
+
+#[cfg(all(test, feature = "tls"))]
+mod tests;
+#[cfg(feature = "tls")]
+mod tls;
+
+#[cfg(feature = "tls")]
+pub use tls::{TLS_READ_BUFFER_SIZE, TlsConfig};
+
+/// Opens a connection for each request, `http` or `https` when TLS is set up
+pub struct HttpClient<'a, T: TcpConnect + 'a, D: Dns + 'a> {
+    tcp: &'a T,
+    dns: &'a D,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig<'a>>,
+}
+
+impl<'a, T: TcpConnect + 'a, D: Dns + 'a> HttpClient<'a, T, D> {
+    /// Create a client of `http` URLs only
+    pub fn new(tcp: &'a T, dns: &'a D) -> Self {
+        Self {
+            tcp,
+            dns,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Create a client of both `http` and `https` URLs
+    #[cfg(feature = "tls")]
+    pub fn new_with_tls(tcp: &'a T, dns: &'a D, tls: TlsConfig<'a>) -> Self {
+        Self {
+            tcp,
+            dns,
+            tls: Some(tls),
+        }
+    }
+
+    /// Connect to the server of `url` for a single request
+    pub async fn request<'conn>(
+        &'conn mut self,
+        method: Method,
+        url: &'conn str,
+    ) -> Result<HttpRequest<'conn, Connection<'conn, T::Connection<'conn>>>, Error> {
+        let url = Url::parse(url)?;
+        let conn = self.connect(&url).await?;
+        Ok(HttpRequest {
+            resource: HttpResource {
+                conn: HttpConnection::Plain(conn),
+                host: url.host(),
+                // The whole path is in the request
+                base_path: "",
+            },
+            method,
+            path: url.path(),
+        })
+    }
+
+    /// Connect to the server of `url`, the path of which is the base of the requests
+    pub async fn resource<'res>(
+        &'res mut self,
+        url: &'res str,
+    ) -> Result<HttpResource<'res, Connection<'res, T::Connection<'res>>>, Error> {
+        let url = Url::parse(url)?;
+        let conn = self.connect(&url).await?;
+        Ok(HttpResource {
+            conn: HttpConnection::Plain(conn),
+            host: url.host(),
+            base_path: url.path(),
+        })
+    }
+
+    async fn connect<'conn>(
+        &'conn mut self,
+        url: &Url<'_>,
+    ) -> Result<Connection<'conn, T::Connection<'conn>>, Error> {
+        #[cfg(feature = "tls")]
+        let has_tls = self.tls.is_some();
+        #[cfg(not(feature = "tls"))]
+        let has_tls = false;
+        if url.scheme() == UrlScheme::HTTPS && !has_tls {
+            return Err(Error::InvalidUrl(nourl::Error::UnsupportedScheme));
+        }
+
+        let remote = self
+            .dns
+            .get_host_by_name(url.host(), AddrType::Either)
+            .await
+            .map_err(|_| Error::Dns)?;
+        let conn = self
+            .tcp
+            .connect(SocketAddr::new(remote, url.port_or_default()))
+            .await
+            .map_err(|e| e.kind())?;
+
+        #[cfg(feature = "tls")]
+        if url.scheme() == UrlScheme::HTTPS
+            && let Some(tls) = self.tls.as_mut()
+        {
+            return tls::open(tls, url.host(), conn)
+                .await
+                .map(Connection::Tls)
+                .map_err(|e| Error::Network(tls::error_kind(e)));
+        }
+        Ok(Connection::Plain(conn))
+    }
+}
+
+/// A request to be sent on its own connection, which is closed when it's dropped
+pub struct HttpRequest<'conn, C: Read + Write> {
+    resource: HttpResource<'conn, C>,
+    method: Method,
+    path: &'conn str,
+}
+
+impl<'conn, C: Read + Write> HttpRequest<'conn, C> {
+    /// Send the request, the headers of the response are read into `rx_buf`
+    pub async fn send<'req, 'buf>(
+        &'req mut self,
+        rx_buf: &'buf mut [u8],
+    ) -> Result<Response<'req, 'buf, HttpConnection<'conn, C>>, Error> {
+        let request = Request::new(self.method, self.path)
+            .host(self.resource.host)
+            .build();
+        self.resource.send(request, rx_buf).await
+    }
+}
+
+/// Connection to a server, encrypted for `https` URLs
+#[allow(clippy::large_enum_variant)]
+pub enum Connection<'conn, C: Read + Write> {
+    Plain(C),
+    #[cfg(feature = "tls")]
+    Tls(tls::TlsStream<'conn, C>),
+    /// Can't be created, only uses the lifetime
+    #[cfg(not(feature = "tls"))]
+    Tls(Infallible, PhantomData<&'conn mut ()>),
+}
+
+impl<C: Read + Write> ErrorType for Connection<'_, C> {
+    type Error = ErrorKind;
+}
+
+impl<C: Read + Write> Read for Connection<'_, C> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        match self {
+            Self::Plain(conn) => conn.read(buf).await.map_err(|e| e.kind()),
+            #[cfg(feature = "tls")]
+            Self::Tls(conn) => conn.read(buf).await.map_err(tls::error_kind),
+            #[cfg(not(feature = "tls"))]
+            Self::Tls(never, _) => match *never {},
+        }
+    }
+}
+
+impl<C: Read + Write> Write for Connection<'_, C> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        match self {
+            Self::Plain(conn) => conn.write(buf).await.map_err(|e| e.kind()),
+            #[cfg(feature = "tls")]
+            Self::Tls(conn) => conn.write(buf).await.map_err(tls::error_kind),
+            #[cfg(not(feature = "tls"))]
+            Self::Tls(never, _) => match *never {},
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), ErrorKind> {
+        match self {
+            Self::Plain(conn) => conn.flush().await.map_err(|e| e.kind()),
+            #[cfg(feature = "tls")]
+            Self::Tls(conn) => conn.flush().await.map_err(tls::error_kind),
+            #[cfg(not(feature = "tls"))]
+            Self::Tls(never, _) => match *never {},
+        }
+    }
+}
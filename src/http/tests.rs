@@ -0,0 +1,93 @@
+use embassy_futures::block_on;
+use embedded_io_async::Read;
+use nourl::Error as UrlError;
+use reqwless::{Error, request::Method};
+
+use super::{HttpClient, TLS_READ_BUFFER_SIZE, TlsConfig};
+use crate::stub_server::{OTHER_CA, StdDns, StdTcp, StubServer, TEST_CA};
+
+/// Get the status and body of `url`
+fn get(client: &mut HttpClient<'_, StdTcp, StdDns>, url: &str) -> Result<(u16, Vec<u8>), Error> {
+    block_on(async {
+        let mut rx_buf = [0; 1024];
+        let mut request = client.request(Method::GET, url).await?;
+        let response = request.send(&mut rx_buf).await?;
+        let status = response.status.0;
+        let mut body = Vec::new();
+        let mut reader = response.body().reader();
+        let mut chunk = [0; 64];
+        loop {
+            let len = reader.read(&mut chunk).await?;
+            if len == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..len]);
+        }
+        Ok((status, body))
+    })
+}
+
+fn server() -> StubServer {
+    StubServer::start_tls(vec![("/feed?key=1".into(), 200, b"BEGIN".to_vec())])
+}
+
+#[test]
+fn fetches_over_tls_from_server_issued_by_ca() {
+    let server = server();
+    let (mut read_buffer, mut write_buffer) = (vec![0; TLS_READ_BUFFER_SIZE], vec![0; 1024]);
+    let tls = TlsConfig::new([1; 32], &mut read_buffer, &mut write_buffer, TEST_CA);
+    let mut client = HttpClient::new_with_tls(&StdTcp, &StdDns, tls);
+
+    let url = format!("{}/feed?key=1", server.url());
+    assert_eq!(get(&mut client, &url).unwrap(), (200, b"BEGIN".to_vec()));
+    // The buffers are reused by the next connection
+    assert_eq!(get(&mut client, &url).unwrap(), (200, b"BEGIN".to_vec()));
+    assert_eq!(server.requests(), ["/feed?key=1", "/feed?key=1"]);
+}
+
+#[test]
+fn rejects_server_not_issued_by_ca() {
+    let server = server();
+    let (mut read_buffer, mut write_buffer) = (vec![0; TLS_READ_BUFFER_SIZE], vec![0; 1024]);
+    let tls = TlsConfig::new([1; 32], &mut read_buffer, &mut write_buffer, OTHER_CA);
+    let mut client = HttpClient::new_with_tls(&StdTcp, &StdDns, tls);
+
+    let url = format!("{}/feed?key=1", server.url());
+    assert!(get(&mut client, &url).is_err());
+    assert_eq!(server.requests(), Vec::<String>::new());
+}
+
+#[test]
+fn pinned_certificate_replaces_ca_for_its_host() {
+    let server = server();
+    let (mut read_buffer, mut write_buffer) = (vec![0; TLS_READ_BUFFER_SIZE], vec![0; 1024]);
+    let pinned = [("localhost", TEST_CA)];
+    let tls =
+        TlsConfig::new([1; 32], &mut read_buffer, &mut write_buffer, OTHER_CA).with_pinned(&pinned);
+    let mut client = HttpClient::new_with_tls(&StdTcp, &StdDns, tls);
+
+    let url = format!("{}/feed?key=1", server.url());
+    assert_eq!(get(&mut client, &url).unwrap().0, 200);
+}
+
+#[test]
+fn https_needs_tls_config() {
+    let server = server();
+    let mut client = HttpClient::new(&StdTcp, &StdDns);
+    let url = format!("{}/feed?key=1", server.url());
+    assert!(matches!(
+        get(&mut client, &url),
+        Err(Error::InvalidUrl(UrlError::UnsupportedScheme))
+    ));
+}
+
+#[test]
+fn plain_http_with_tls_config() {
+    let server = StubServer::start(vec![("/feed".into(), 200, b"BEGIN".to_vec())]);
+    let (mut read_buffer, mut write_buffer) = (vec![0; TLS_READ_BUFFER_SIZE], vec![0; 1024]);
+    let tls = TlsConfig::new([1; 32], &mut read_buffer, &mut write_buffer, TEST_CA);
+    let mut client = HttpClient::new_with_tls(&StdTcp, &StdDns, tls);
+
+    let url = format!("{}/feed", server.url());
+    assert_eq!(get(&mut client, &url).unwrap(), (200, b"BEGIN".to_vec()));
+}
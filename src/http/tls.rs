@@ -0,0 +1,172 @@
+//! TLS 1.3 connections by embedded-tls, with the server certificate verified against a CA
+//!
+//! The validity dates of the certificates are not checked. The clock is wrong after the RTC lost
+//! power, and that shouldn't cut the calendar off from its data.
+
+use alloc::boxed::Box;
+
+use embedded_io::{Error as _, ErrorKind, ErrorType};
+use embedded_io_async::{Read, Write};
+use embedded_tls::{
+    Aes128GcmSha256, Certificate, CryptoProvider, CryptoRngCore, NoClock, TlsConnection,
+    TlsContext, TlsError, TlsVerifier, pki::CertVerifier,
+};
+use log::error;
+use rand_chacha::{ChaCha20Rng, rand_core::SeedableRng};
+
+/// The read buffer must fit a whole TLS record, servers can't be asked for smaller ones
+pub const TLS_READ_BUFFER_SIZE: usize = 16 * 1024 + 256;
+/// The certificates sent by the server are kept in this many bytes during the handshake
+const MAX_CHAIN_SIZE: usize = 6 * 1024;
+
+pub type TlsStream<'a, C> = TlsConnection<'a, Io07<C>, Aes128GcmSha256>;
+
+/// Buffers of the connections and the certificates the servers are verified with
+pub struct TlsConfig<'a> {
+    rng: ChaCha20Rng,
+    read_buffer: &'a mut [u8],
+    write_buffer: &'a mut [u8],
+    ca: &'a [u8],
+    pinned: &'a [(&'a str, &'a [u8])],
+}
+
+impl<'a> TlsConfig<'a> {
+    /// Verify the servers against the DER encoded `ca` certificate
+    ///
+    /// `seed` must be random, like from the hardware RNG, the keys of all the connections are
+    /// derived from it. The `read_buffer` should have [`TLS_READ_BUFFER_SIZE`] bytes, the
+    /// `write_buffer` must fit the requests.
+    pub fn new(
+        seed: [u8; 32],
+        read_buffer: &'a mut [u8],
+        write_buffer: &'a mut [u8],
+        ca: &'a [u8],
+    ) -> Self {
+        Self {
+            rng: ChaCha20Rng::from_seed(seed),
+            read_buffer,
+            write_buffer,
+            ca,
+            pinned: &[],
+        }
+    }
+
+    /// Verify the servers of some hosts against their own DER encoded certificates instead of the
+    /// CA, like a self-signed certificate of a home server or a CA only trusted for that host
+    pub const fn with_pinned(self, pinned: &'a [(&'a str, &'a [u8])]) -> Self {
+        Self { pinned, ..self }
+    }
+
+    fn trust_anchor(&self, host: &str) -> &'a [u8] {
+        self.pinned
+            .iter()
+            .find(|(pinned_host, _)| pinned_host.eq_ignore_ascii_case(host))
+            .map_or(self.ca, |(_, cert)| cert)
+    }
+}
+
+/// Make a TLS connection to `host` over `conn`
+pub async fn open<'conn, C: Read + Write + 'conn>(
+    config: &'conn mut TlsConfig<'_>,
+    host: &str,
+    conn: C,
+) -> Result<TlsStream<'conn, C>, TlsError> {
+    let trust_anchor = config.trust_anchor(host);
+    // Boxed, it's too big for the stack of the task
+    let mut provider = Box::new(Provider {
+        rng: &mut config.rng,
+        verifier: CertVerifier::new(Certificate::X509(trust_anchor)),
+    });
+    let tls_config = embedded_tls::TlsConfig::new().with_server_name(host);
+    let mut stream = TlsConnection::new(Io07(conn), config.read_buffer, config.write_buffer);
+    stream
+        .open(TlsContext::new(&tls_config, &mut *provider))
+        .await?;
+    Ok(stream)
+}
+
+/// Map a TLS error to the error of the connection, the TLS details are only logged
+pub fn error_kind(e: TlsError) -> ErrorKind {
+    match e {
+        TlsError::Io(kind) => kind_from_07(kind),
+        e => {
+            error!("TLS error: {e:?}");
+            ErrorKind::Other
+        }
+    }
+}
+
+struct Provider<'a> {
+    rng: &'a mut ChaCha20Rng,
+    verifier: CertVerifier<'a, Aes128GcmSha256, NoClock, MAX_CHAIN_SIZE>,
+}
+
+impl CryptoProvider for Provider<'_> {
+    type CipherSuite = Aes128GcmSha256;
+    // Client certificates are not used
+    type Signature = &'static [u8];
+
+    fn rng(&mut self) -> impl CryptoRngCore {
+        &mut *self.rng
+    }
+
+    fn verifier(&mut self) -> Result<&mut impl TlsVerifier<Aes128GcmSha256>, TlsError> {
+        Ok(&mut self.verifier)
+    }
+}
+
+/// Connection with the IO traits of embedded-tls
+pub struct Io07<C>(C);
+
+impl<C: ErrorType> embedded_io_07::ErrorType for Io07<C> {
+    type Error = embedded_io_07::ErrorKind;
+}
+
+impl<C: Read> embedded_io_async_07::Read for Io07<C> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, embedded_io_07::ErrorKind> {
+        self.0.read(buf).await.map_err(|e| kind_to_07(e.kind()))
+    }
+}
+
+impl<C: Write> embedded_io_async_07::Write for Io07<C> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, embedded_io_07::ErrorKind> {
+        self.0.write(buf).await.map_err(|e| kind_to_07(e.kind()))
+    }
+
+    async fn flush(&mut self) -> Result<(), embedded_io_07::ErrorKind> {
+        self.0.flush().await.map_err(|e| kind_to_07(e.kind()))
+    }
+}
+
+/// Map the error kinds between the versions of the IO traits, they have the same variants
+macro_rules! map_error_kind {
+    ($name:ident, $from:path => $to:path) => {
+        fn $name(kind: $from) -> $to {
+            use $from as Src;
+            use $to as Dst;
+            match kind {
+                Src::NotFound => Dst::NotFound,
+                Src::PermissionDenied => Dst::PermissionDenied,
+                Src::ConnectionRefused => Dst::ConnectionRefused,
+                Src::ConnectionReset => Dst::ConnectionReset,
+                Src::ConnectionAborted => Dst::ConnectionAborted,
+                Src::NotConnected => Dst::NotConnected,
+                Src::AddrInUse => Dst::AddrInUse,
+                Src::AddrNotAvailable => Dst::AddrNotAvailable,
+                Src::BrokenPipe => Dst::BrokenPipe,
+                Src::AlreadyExists => Dst::AlreadyExists,
+                Src::InvalidInput => Dst::InvalidInput,
+                Src::InvalidData => Dst::InvalidData,
+                Src::TimedOut => Dst::TimedOut,
+                Src::Interrupted => Dst::Interrupted,
+                Src::Unsupported => Dst::Unsupported,
+                Src::OutOfMemory => Dst::OutOfMemory,
+                Src::WriteZero => Dst::WriteZero,
+                _ => Dst::Other,
+            }
+        }
+    };
+}
+
+map_error_kind!(kind_to_07, embedded_io::ErrorKind => embedded_io_07::ErrorKind);
+map_error_kind!(kind_from_07, embedded_io_07::ErrorKind => embedded_io::ErrorKind);
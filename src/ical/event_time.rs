@@ -0,0 +1,185 @@
+use core::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Time zone a date-time value is given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeRef {
+    Utc,
+    Zone(Tz),
+    /// The same wall time in every time zone
+    Floating,
+}
+
+/// Start or end of an event, in the time zone it was given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTime {
+    /// All-day value, `VALUE=DATE`
+    Date(NaiveDate),
+    DateTime(NaiveDateTime, TimeRef),
+}
+
+impl EventTime {
+    /// Parse a `DATE` or `DATE-TIME` value, like `20250101`, `20250101T090000` or
+    /// `20250101T090000Z`
+    ///
+    /// `zone` is the time zone of the `TZID` parameter, it's ignored for UTC values.
+    pub fn parse(value: &str, zone: Option<Tz>) -> Option<Self> {
+        let value = value.as_bytes();
+        let date = parse_date(value.get(..8)?)?;
+        match value.get(8..) {
+            Some([]) => Some(Self::Date(date)),
+            Some([b'T', time @ ..]) => {
+                let (time, time_ref) = match time {
+                    [time @ .., b'Z'] => (time, TimeRef::Utc),
+                    time => (time, zone.map_or(TimeRef::Floating, TimeRef::Zone)),
+                };
+                let time = parse_time(time)?;
+                Some(Self::DateTime(date.and_time(time), time_ref))
+            }
+            _ => None,
+        }
+    }
+
+    pub const fn is_date(self) -> bool {
+        matches!(self, Self::Date(_))
+    }
+
+    /// Get the wall time in the time zone of the value, midnight for all-day values
+    pub fn wall(self) -> NaiveDateTime {
+        match self {
+            Self::Date(date) => date.and_time(NaiveTime::MIN),
+            Self::DateTime(wall, _) => wall,
+        }
+    }
+
+    /// Get the same kind of value in the same time zone at another wall time
+    pub fn with_wall(self, wall: NaiveDateTime) -> Self {
+        match self {
+            Self::Date(_) => Self::Date(wall.date()),
+            Self::DateTime(_, time_ref) => Self::DateTime(wall, time_ref),
+        }
+    }
+
+    /// Convert to the wall time in `tz`
+    ///
+    /// All-day and floating values mean the same wall time everywhere, so they are returned as is.
+    pub fn to_local(self, tz: impl TimeZone + Copy) -> NaiveDateTime {
+        match self {
+            Self::Date(_) | Self::DateTime(_, TimeRef::Floating) => self.wall(),
+            Self::DateTime(wall, TimeRef::Utc) => tz.from_utc_datetime(&wall).naive_local(),
+            Self::DateTime(wall, TimeRef::Zone(zone)) => {
+                let instant = zone
+                    .from_local_datetime(&wall)
+                    .earliest()
+                    // Wall time skipped by a DST change, RFC 5545 says to use the time after the
+                    // gap. No time zone has a gap longer than an hour.
+                    .or_else(|| {
+                        zone.from_local_datetime(&(wall + TimeDelta::hours(1)))
+                            .earliest()
+                    });
+                match instant {
+                    Some(instant) => instant.with_timezone(&tz).naive_local(),
+                    None => wall,
+                }
+            }
+        }
+    }
+
+    /// Get the point in time of the value, as UTC wall time
+    ///
+    /// Floating and all-day values are treated as if they were UTC.
+    pub fn instant(self) -> NaiveDateTime {
+        self.to_local(Utc)
+    }
+
+    /// Convert to the wall time in the time zone of `frame`
+    pub(crate) fn to_frame_of(self, frame: Self) -> NaiveDateTime {
+        match frame {
+            Self::Date(_) | Self::DateTime(_, TimeRef::Floating) => self.wall(),
+            Self::DateTime(_, TimeRef::Utc) => self.instant(),
+            Self::DateTime(_, TimeRef::Zone(zone)) => self.to_local(zone),
+        }
+    }
+}
+
+/// Resolve the value of a `TZID` parameter
+///
+/// Besides plain IANA names, names with a prefix like `/mozilla.org/20050126_1/Europe/Berlin` are
+/// understood. Custom names defined only by a `VTIMEZONE` component are not.
+pub fn resolve_tzid(tzid: &str) -> Option<Tz> {
+    let mut name = tzid.trim_start_matches('/');
+    loop {
+        if let Ok(tz) = Tz::from_str(name) {
+            return Some(tz);
+        }
+        name = name.split_once('/')?.1;
+    }
+}
+
+/// Parse a `DURATION` value, like `PT1H30M`, `P1D` or `-P2W`
+pub fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let (sign, value) = match value.as_bytes() {
+        [b'-', ..] => (-1, &value[1..]),
+        [b'+', ..] => (1, &value[1..]),
+        _ => (1, value),
+    };
+    let value = value.strip_prefix('P')?;
+    let mut res = TimeDelta::zero();
+    let mut in_time = false;
+    let mut number: Option<i64> = None;
+    for c in value.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            number = Some(number.unwrap_or(0).checked_mul(10)? + i64::from(digit));
+            continue;
+        }
+        let part = match (c, in_time) {
+            ('T', false) if number.is_none() => {
+                in_time = true;
+                continue;
+            }
+            ('W', false) => TimeDelta::try_weeks(number?)?,
+            ('D', false) => TimeDelta::try_days(number?)?,
+            ('H', true) => TimeDelta::try_hours(number?)?,
+            ('M', true) => TimeDelta::try_minutes(number?)?,
+            ('S', true) => TimeDelta::try_seconds(number?)?,
+            _ => return None,
+        };
+        res += part;
+        number = None;
+    }
+    if number.is_some() {
+        return None;
+    }
+    Some(res * sign)
+}
+
+fn parse_number(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0, |acc, digit| {
+        digit
+            .is_ascii_digit()
+            .then(|| acc * 10 + u32::from(digit - b'0'))
+    })
+}
+
+fn parse_date(value: &[u8]) -> Option<NaiveDate> {
+    let year = parse_number(value.get(0..4)?)?;
+    let month = parse_number(value.get(4..6)?)?;
+    let day = parse_number(value.get(6..8)?)?;
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+fn parse_time(value: &[u8]) -> Option<NaiveTime> {
+    if value.len() != 6 {
+        return None;
+    }
+    let hour = parse_number(&value[0..2])?;
+    let min = parse_number(&value[2..4])?;
+    // Leap seconds are allowed by the RFC, chrono doesn't represent them this way
+    let sec = parse_number(&value[4..6])?.min(59);
+    NaiveTime::from_hms_opt(hour, min, sec)
+}
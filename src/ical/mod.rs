@@ -0,0 +1,167 @@
+//! Events from iCalendar (RFC 5545) feeds
+//!
+//! Only what's needed to show events on the calendar is parsed: the `VEVENT` components with their
+//! summary, start, end and recurrence.
+
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+
+use crate::calendar_utils::{EventDaysMask, MonthDate};
+
+mod event_time;
+mod parser;
+mod rrule;
+#[cfg(test)]
+mod tests;
+
+pub use event_time::{EventTime, TimeRef, parse_duration, resolve_tzid};
+pub use parser::{IcalParser, MAX_SUMMARY_LEN};
+pub use rrule::{ByDay, Frequency, RecurrenceRule, RuleError};
+
+/// Time zones shift wall times by less than a day, used as a margin when comparing dates in
+/// different time zones
+const ZONE_MARGIN: Days = Days::new(1);
+
+/// An event of a calendar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub uid: String,
+    pub summary: String,
+    pub start: EventTime,
+    pub duration: TimeDelta,
+    pub rule: Option<RecurrenceRule>,
+    /// Starts of the occurrences excluded from the recurrence
+    pub exdates: Vec<EventTime>,
+    /// Start of the occurrence of another event with the same UID this event replaces
+    pub recurrence_id: Option<EventTime>,
+}
+
+/// A single occurrence of an event, in local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Occurrence<'a> {
+    pub event: &'a Event,
+    pub start: NaiveDateTime,
+    /// Exclusive
+    pub end: NaiveDateTime,
+}
+
+impl Occurrence<'_> {
+    pub const fn is_all_day(&self) -> bool {
+        self.event.start.is_date()
+    }
+
+    /// Get the local dates this occurrence takes place on
+    pub fn dates(&self) -> Range<NaiveDate> {
+        let last = if self.end > self.start {
+            (self.end - TimeDelta::nanoseconds(1)).date()
+        } else {
+            self.start.date()
+        };
+        self.start.date()..last.succ_opt().unwrap_or(last)
+    }
+}
+
+impl Event {
+    /// Call `f` with every occurrence that overlaps the `from..to` local time range in `tz`, in
+    /// order
+    pub fn for_each_occurrence<'a>(
+        &'a self,
+        tz: impl TimeZone + Copy,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        mut f: impl FnMut(Occurrence<'a>),
+    ) {
+        let mut emit = |start: EventTime| {
+            if self.is_excluded(start) {
+                return;
+            }
+            let start = start.to_local(tz);
+            let end = start + self.duration;
+            if start < to && (end > from || start >= from) {
+                f(Occurrence {
+                    event: self,
+                    start,
+                    end,
+                });
+            }
+        };
+        match &self.rule {
+            None => emit(self.start),
+            Some(rule) => {
+                let skip_to = (from - self.duration).date() - ZONE_MARGIN;
+                let horizon = to + ZONE_MARGIN;
+                rule.for_each_start(self.start, skip_to, horizon, |wall| {
+                    emit(self.start.with_wall(wall))
+                });
+            }
+        }
+    }
+
+    fn is_excluded(&self, start: EventTime) -> bool {
+        self.exdates.iter().any(|exdate| match exdate {
+            EventTime::Date(date) => start.wall().date() == *date,
+            exdate => exdate.to_frame_of(self.start) == start.wall(),
+        })
+    }
+
+    /// Check if the event can have occurrences on the `window` dates, in any time zone
+    pub(crate) fn may_overlap(&self, window: &Range<NaiveDate>) -> bool {
+        let start = self.start.wall();
+        if start.date() > window.end + ZONE_MARGIN {
+            return false;
+        }
+        match &self.rule {
+            None => (start + self.duration).date() + ZONE_MARGIN >= window.start,
+            Some(rule) => rule.until.is_none_or(|until| {
+                (until.wall() + self.duration).date() + ZONE_MARGIN >= window.start
+            }),
+        }
+    }
+}
+
+/// Get the days of `month` that have events in the local time of `tz`
+pub fn event_days(events: &[Event], month: MonthDate, tz: impl TimeZone + Copy) -> EventDaysMask {
+    let month_start = month.to_start_day_naive();
+    let month_end = (month + chrono::Months::new(1)).to_start_day_naive();
+    let mut mask = EventDaysMask::default();
+    for event in events {
+        event.for_each_occurrence(
+            tz,
+            month_start.and_time(NaiveTime::MIN),
+            month_end.and_time(NaiveTime::MIN),
+            |occurrence| {
+                let dates = occurrence.dates();
+                let mut date = dates.start.max(month_start);
+                while date < dates.end.min(month_end) {
+                    mask = mask.with_day0((date - month_start).num_days() as u8);
+                    date = date.succ_opt().unwrap();
+                }
+            },
+        );
+    }
+    mask
+}
+
+/// Get up to `amount` occurrences that are not over at `now` and start within `horizon`, ordered
+/// by their start
+pub fn upcoming(
+    events: &[Event],
+    tz: impl TimeZone + Copy,
+    now: NaiveDateTime,
+    horizon: Days,
+    amount: usize,
+) -> Vec<Occurrence<'_>> {
+    let mut res = Vec::new();
+    for event in events {
+        event.for_each_occurrence(tz, now, now + horizon, |occurrence| res.push(occurrence));
+    }
+    res.sort_by(|a, b| {
+        a.start
+            .cmp(&b.start)
+            .then_with(|| a.event.summary.cmp(&b.event.summary))
+    });
+    res.truncate(amount);
+    res
+}
@@ -0,0 +1,296 @@
+use alloc::{string::String, vec::Vec};
+use core::{ops::Range, str::from_utf8};
+
+use chrono::{NaiveDate, TimeDelta};
+use log::warn;
+
+use super::{
+    Event, EventTime, RecurrenceRule,
+    event_time::{parse_duration, resolve_tzid},
+};
+
+/// Longer content lines are cut, this only loses the end of long descriptions
+const MAX_LINE_LEN: usize = 1024;
+/// Summaries are cut to this amount of characters
+pub const MAX_SUMMARY_LEN: usize = 64;
+
+/// Streaming parser of iCalendar data that collects the events
+///
+/// The data can be pushed in chunks of any size as it's received, so the whole feed never has to
+/// be kept in memory. Only the events that can have occurrences in the window are kept.
+pub struct IcalParser {
+    line: Vec<u8>,
+    /// Set after a line break, the next byte tells if the line continues
+    line_start: bool,
+    event: Option<EventBuilder>,
+    /// Components nested in the current event, like `VALARM`
+    nesting: u8,
+    window: Range<NaiveDate>,
+    max_events: usize,
+    events: Vec<Event>,
+    /// Occurrences replaced by other events, by UID
+    overrides: Vec<(String, EventTime)>,
+    dropped: usize,
+}
+
+impl IcalParser {
+    /// Keep up to `max_events` events that can have occurrences in the `window` dates
+    pub fn new(window: Range<NaiveDate>, max_events: usize) -> Self {
+        Self {
+            line: Vec::new(),
+            line_start: false,
+            event: None,
+            nesting: 0,
+            window,
+            max_events,
+            events: Vec::new(),
+            overrides: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Parse the next chunk of data
+    pub fn push(&mut self, data: &[u8]) {
+        for &byte in data {
+            if self.line_start {
+                self.line_start = false;
+                // Long lines are folded by a line break followed by a space or a tab
+                if byte == b' ' || byte == b'\t' {
+                    continue;
+                }
+                self.process_line();
+            }
+            match byte {
+                b'\r' => {}
+                b'\n' => self.line_start = true,
+                byte => {
+                    if self.line.len() < MAX_LINE_LEN {
+                        self.line.push(byte);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finish parsing and get the events
+    pub fn finish(mut self) -> Vec<Event> {
+        self.process_line();
+        if self.dropped > 0 {
+            warn!(
+                "Dropped {} events over the limit of {}",
+                self.dropped, self.max_events
+            );
+        }
+        for (uid, recurrence_id) in &self.overrides {
+            for event in &mut self.events {
+                if event.rule.is_some() && event.recurrence_id.is_none() && event.uid == *uid {
+                    event.exdates.push(*recurrence_id);
+                }
+            }
+        }
+        self.events
+    }
+
+    fn process_line(&mut self) {
+        let mut line = core::mem::take(&mut self.line);
+        // A line cut at the length limit can end in the middle of a character
+        let text = match from_utf8(&line) {
+            Ok(text) => text,
+            Err(e) => from_utf8(&line[..e.valid_up_to()]).unwrap(),
+        };
+        if let Some(content) = ContentLine::parse(text) {
+            self.process_content(&content);
+        }
+        line.clear();
+        self.line = line;
+    }
+
+    fn process_content(&mut self, content: &ContentLine) {
+        let is_event = content.value.eq_ignore_ascii_case("VEVENT");
+        if content.is_named("BEGIN") {
+            if self.event.is_some() {
+                self.nesting += 1;
+            } else if is_event {
+                self.event = Some(EventBuilder::default());
+            }
+        } else if content.is_named("END") {
+            if self.nesting > 0 {
+                self.nesting -= 1;
+            } else if is_event && let Some(event) = self.event.take() {
+                self.finish_event(event);
+            }
+        } else if self.nesting == 0
+            && let Some(event) = &mut self.event
+        {
+            event.property(content);
+        }
+    }
+
+    fn finish_event(&mut self, event: EventBuilder) {
+        let cancelled = event.cancelled;
+        let Some(event) = event.build() else {
+            warn!("Skipping an event without a valid start");
+            return;
+        };
+        if let Some(recurrence_id) = event.recurrence_id {
+            self.overrides.push((event.uid.clone(), recurrence_id));
+        }
+        if cancelled || !event.may_overlap(&self.window) {
+            return;
+        }
+        if self.events.len() >= self.max_events {
+            self.dropped += 1;
+            return;
+        }
+        self.events.push(event);
+    }
+}
+
+/// Properties of the event being parsed
+#[derive(Default)]
+struct EventBuilder {
+    uid: String,
+    summary: String,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+    duration: Option<TimeDelta>,
+    rule: Option<RecurrenceRule>,
+    exdates: Vec<EventTime>,
+    recurrence_id: Option<EventTime>,
+    cancelled: bool,
+}
+
+impl EventBuilder {
+    fn property(&mut self, content: &ContentLine) {
+        let value = content.value;
+        match content.name.to_ascii_uppercase().as_str() {
+            "UID" => self.uid = value.into(),
+            "SUMMARY" => self.summary = unescape_text(value, MAX_SUMMARY_LEN),
+            "DTSTART" => self.start = content.time_value(value),
+            "DTEND" => self.end = content.time_value(value),
+            "DURATION" => self.duration = parse_duration(value),
+            "RRULE" => {
+                self.rule = RecurrenceRule::parse(value)
+                    .inspect_err(|e| {
+                        warn!("Recurrence rule {value} is not supported ({e:?}), only the first occurrence is used")
+                    })
+                    .ok()
+            }
+            "EXDATE" => self.exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|value| content.time_value(value)),
+            ),
+            "RECURRENCE-ID" => self.recurrence_id = content.time_value(value),
+            "STATUS" => self.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+
+    fn build(self) -> Option<Event> {
+        let start = self.start?;
+        let duration = match (self.end, self.duration) {
+            (Some(end), _) => end.instant() - start.instant(),
+            (None, Some(duration)) => duration,
+            // An all-day event without an end takes the whole day
+            (None, None) if start.is_date() => TimeDelta::days(1),
+            (None, None) => TimeDelta::zero(),
+        };
+        Some(Event {
+            uid: self.uid,
+            summary: self.summary,
+            start,
+            duration: duration.max(TimeDelta::zero()),
+            rule: self.rule,
+            exdates: self.exdates,
+            recurrence_id: self.recurrence_id,
+        })
+    }
+}
+
+/// A line of iCalendar data, like `DTSTART;TZID=Europe/Moscow:20250101T090000`
+struct ContentLine<'a> {
+    name: &'a str,
+    /// Parameters, separated by `;`
+    params: &'a str,
+    value: &'a str,
+}
+
+impl<'a> ContentLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let name_end = line.find([';', ':'])?;
+        let name = &line[..name_end];
+        // Parameter values can contain colons when quoted
+        let mut quoted = false;
+        let value_start = line[name_end..].char_indices().find_map(|(idx, c)| {
+            match c {
+                '"' => quoted = !quoted,
+                ':' if !quoted => return Some(name_end + idx),
+                _ => {}
+            }
+            None
+        })?;
+        Some(Self {
+            name,
+            params: line[name_end..value_start].trim_start_matches(';'),
+            value: &line[value_start + 1..],
+        })
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    fn param(&self, name: &str) -> Option<&'a str> {
+        let mut quoted = false;
+        self.params
+            .split(move |c| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == ';' && !quoted
+            })
+            .filter_map(|param| param.split_once('='))
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim_matches('"'))
+    }
+
+    /// Parse a date or date-time value of this line, using its time zone
+    fn time_value(&self, value: &str) -> Option<EventTime> {
+        let zone = self.param("TZID").and_then(|tzid| {
+            let zone = resolve_tzid(tzid);
+            if zone.is_none() {
+                warn!("Unknown time zone {tzid}, using floating time");
+            }
+            zone
+        });
+        let res = EventTime::parse(value, zone);
+        if res.is_none() {
+            warn!("Invalid {} value {value}", self.name);
+        }
+        res
+    }
+}
+
+/// Unescape a `TEXT` value and cut it to `max_len` characters, line breaks become spaces
+fn unescape_text(value: &str, max_len: usize) -> String {
+    let mut res = String::new();
+    let mut chars = value.chars();
+    let mut len = 0;
+    while let Some(c) = chars.next() {
+        if len >= max_len {
+            break;
+        }
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => ' ',
+                Some(c) => c,
+                None => break,
+            },
+            c => c,
+        };
+        res.push(c);
+        len += 1;
+    }
+    res
+}
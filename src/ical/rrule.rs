@@ -0,0 +1,368 @@
+use alloc::vec::Vec;
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, Weekday};
+
+use super::EventTime;
+
+/// Recurrence periods checked at most, for rules without an end that start long before the
+/// requested range this is counted from the first period near the range
+const MAX_PERIODS: u32 = 10_000;
+
+/// How often a recurring event repeats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A `BYDAY` rule part, a weekday with an optional occurrence within the month or year
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub weekday: Weekday,
+    /// 1 for the first such weekday, -1 for the last one, 0 for every one
+    pub nth: i8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleError {
+    /// Missing `FREQ` or a frequency that is not supported, like `HOURLY`
+    UnsupportedFrequency,
+    /// A rule part that is not supported, like `BYSETPOS`
+    UnsupportedPart,
+    InvalidValue,
+}
+
+/// A recurrence rule, `RRULE`
+///
+/// Supports the `FREQ`, `INTERVAL`, `COUNT`, `UNTIL`, `BYDAY`, `BYMONTHDAY`, `BYMONTH` and `WKST`
+/// rule parts, which covers what calendar apps create.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    /// Last possible start of an occurrence, inclusive
+    pub until: Option<EventTime>,
+    pub by_day: Vec<ByDay>,
+    /// Days of the month, negative ones count from the end of the month
+    pub by_month_day: Vec<i8>,
+    pub by_month: Vec<u8>,
+    pub week_start: Weekday,
+}
+
+impl RecurrenceRule {
+    /// Parse the value of a `RRULE` property, like `FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10`
+    pub fn parse(value: &str) -> Result<Self, RuleError> {
+        let mut frequency = None;
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            week_start: Weekday::Mon,
+        };
+        for part in value.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part.split_once('=').ok_or(RuleError::InvalidValue)?;
+            match name {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(RuleError::UnsupportedFrequency),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or(RuleError::InvalidValue)?
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| RuleError::InvalidValue)?),
+                "UNTIL" => {
+                    rule.until = Some(EventTime::parse(value, None).ok_or(RuleError::InvalidValue)?)
+                }
+                "BYDAY" => rule.by_day = parse_list(value, parse_by_day)?,
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_list(value, |value| {
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|day: &i8| *day != 0 && (-31..=31).contains(day))
+                    })?
+                }
+                "BYMONTH" => {
+                    rule.by_month = parse_list(value, |value| {
+                        value.parse().ok().filter(|month| (1..=12).contains(month))
+                    })?
+                }
+                "WKST" => rule.week_start = parse_weekday(value).ok_or(RuleError::InvalidValue)?,
+                _ => return Err(RuleError::UnsupportedPart),
+            }
+        }
+        rule.frequency = frequency.ok_or(RuleError::UnsupportedFrequency)?;
+        Ok(rule)
+    }
+
+    /// Call `f` with the wall time of the start of every occurrence, in order
+    ///
+    /// Occurrences that start after `horizon` are not generated. When the rule has no `COUNT`,
+    /// the occurrences before `skip_to` may be skipped too.
+    pub(crate) fn for_each_start(
+        &self,
+        start: EventTime,
+        skip_to: NaiveDate,
+        horizon: NaiveDateTime,
+        mut f: impl FnMut(NaiveDateTime),
+    ) {
+        let start_wall = start.wall();
+        let start_date = start_wall.date();
+        let until = self.until.map(|until| match until {
+            EventTime::Date(date) => date.and_hms_opt(23, 59, 59).unwrap(),
+            until => until.to_frame_of(start),
+        });
+        let first_period = match self.count {
+            // Occurrences have to be counted from the start
+            Some(_) => 0,
+            None => self.periods_between(start_date, skip_to).saturating_sub(1),
+        };
+
+        let mut dates = Vec::new();
+        let mut emitted = 0;
+        for period in first_period..first_period.saturating_add(MAX_PERIODS) {
+            let Some(period_start) = self.period_start(start_date, period) else {
+                return;
+            };
+            if period_start > horizon.date() {
+                return;
+            }
+            dates.clear();
+            self.period_dates(start_date, period_start, &mut dates);
+            for date in &dates {
+                let occurrence = date.and_time(start_wall.time());
+                if occurrence < start_wall {
+                    continue;
+                }
+                if occurrence > horizon || until.is_some_and(|until| occurrence > until) {
+                    return;
+                }
+                f(occurrence);
+                emitted += 1;
+                if self.count.is_some_and(|count| emitted >= count) {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Whole periods of the rule from `start` to `date`
+    fn periods_between(&self, start: NaiveDate, date: NaiveDate) -> u32 {
+        if date <= start {
+            return 0;
+        }
+        let months = |start: NaiveDate, date: NaiveDate| {
+            (date.year() - start.year()) * 12 + date.month() as i32 - start.month() as i32
+        };
+        let units = match self.frequency {
+            Frequency::Daily => (date - start).num_days(),
+            Frequency::Weekly => (date - start).num_days() / 7,
+            Frequency::Monthly => months(start, date).into(),
+            Frequency::Yearly => (date.year() - start.year()).into(),
+        };
+        u32::try_from(units / i64::from(self.interval)).unwrap_or(u32::MAX)
+    }
+
+    /// First day of a recurrence period, for weekly rules the first day of the week
+    fn period_start(&self, start: NaiveDate, period: u32) -> Option<NaiveDate> {
+        let steps = period.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Daily => start.checked_add_days(Days::new(steps.into())),
+            Frequency::Weekly => start
+                .checked_sub_days(Days::new(
+                    start.weekday().days_since(self.week_start).into(),
+                ))?
+                .checked_add_days(Days::new(u64::from(steps) * 7)),
+            Frequency::Monthly => start.with_day(1)?.checked_add_months(Months::new(steps)),
+            Frequency::Yearly => {
+                NaiveDate::from_ymd_opt(start.year().checked_add(steps.try_into().ok()?)?, 1, 1)
+            }
+        }
+    }
+
+    /// Push the dates of the occurrences in a period to `out`, in order
+    fn period_dates(&self, start: NaiveDate, period_start: NaiveDate, out: &mut Vec<NaiveDate>) {
+        match self.frequency {
+            Frequency::Daily => {
+                let matches = (self.by_day.is_empty()
+                    || self
+                        .by_day
+                        .iter()
+                        .any(|by_day| by_day.weekday == period_start.weekday()))
+                    && (self.by_month_day.is_empty()
+                        || self
+                            .by_month_day
+                            .iter()
+                            .any(|day| month_day(period_start, *day) == Some(period_start)));
+                if matches {
+                    out.push(period_start);
+                }
+            }
+            Frequency::Weekly => {
+                if self.by_day.is_empty() {
+                    out.push(period_start + day_offset(start.weekday(), self.week_start));
+                } else {
+                    out.extend(
+                        self.by_day.iter().map(|by_day| {
+                            period_start + day_offset(by_day.weekday, self.week_start)
+                        }),
+                    );
+                }
+            }
+            Frequency::Monthly => self.month_dates(period_start, start.day(), out),
+            Frequency::Yearly => {
+                if !self.by_month.is_empty() {
+                    for month in &self.by_month {
+                        let month_start = period_start.with_month(u32::from(*month)).unwrap();
+                        self.month_dates(month_start, start.day(), out);
+                    }
+                } else if !self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    let year_end = period_start.with_year(period_start.year() + 1).unwrap();
+                    expand_weekdays(period_start, year_end, &self.by_day, out);
+                } else if !self.by_month_day.is_empty() {
+                    for month in 1..=12 {
+                        let month_start = period_start.with_month(month).unwrap();
+                        self.month_dates(month_start, start.day(), out);
+                    }
+                } else if let Some(date) = period_start
+                    .with_month(start.month())
+                    .and_then(|month_start| month_start.with_day(start.day()))
+                {
+                    out.push(date);
+                }
+            }
+        }
+        // Monthly and daily rules only use `BYMONTH` to limit the occurrences
+        if !self.by_month.is_empty() && self.frequency != Frequency::Yearly {
+            out.retain(|date| self.by_month.contains(&(date.month() as u8)));
+        }
+        out.sort_unstable();
+        out.dedup();
+    }
+
+    /// Push the dates of the occurrences in a month to `out`
+    fn month_dates(&self, month_start: NaiveDate, start_day: u32, out: &mut Vec<NaiveDate>) {
+        if !self.by_day.is_empty() {
+            let month_end = month_start + Months::new(1);
+            let from = out.len();
+            expand_weekdays(month_start, month_end, &self.by_day, out);
+            if !self.by_month_day.is_empty() {
+                let mut idx = from;
+                while idx < out.len() {
+                    let date = out[idx];
+                    if self
+                        .by_month_day
+                        .iter()
+                        .any(|day| month_day(month_start, *day) == Some(date))
+                    {
+                        idx += 1;
+                    } else {
+                        out.remove(idx);
+                    }
+                }
+            }
+        } else if !self.by_month_day.is_empty() {
+            out.extend(
+                self.by_month_day
+                    .iter()
+                    .filter_map(|day| month_day(month_start, *day)),
+            );
+        } else if let Some(date) = month_start.with_day(start_day) {
+            // Months without the day are skipped
+            out.push(date);
+        }
+    }
+}
+
+/// Push the dates matching `by_day` in the `start..end` range to `out`
+fn expand_weekdays(start: NaiveDate, end: NaiveDate, by_day: &[ByDay], out: &mut Vec<NaiveDate>) {
+    for by_day in by_day {
+        let first = start + day_offset(by_day.weekday, start.weekday());
+        let last = end - Days::new(1);
+        let last = last - day_offset(last.weekday(), by_day.weekday);
+        match by_day.nth {
+            0 => out.extend(first.iter_weeks().take_while(|date| *date < end)),
+            nth @ 1.. => {
+                let date = first + Days::new(u64::from(nth as u8 - 1) * 7);
+                if date < end {
+                    out.push(date);
+                }
+            }
+            nth => {
+                let date = last - Days::new(u64::from(nth.unsigned_abs() - 1) * 7);
+                if date >= start {
+                    out.push(date);
+                }
+            }
+        }
+    }
+}
+
+/// Days from `from` to the next `weekday`
+fn day_offset(weekday: Weekday, from: Weekday) -> Days {
+    Days::new(weekday.days_since(from).into())
+}
+
+/// Get a day of the month `date` is in, negative days count from the end of the month
+fn month_day(date: NaiveDate, day: i8) -> Option<NaiveDate> {
+    if day > 0 {
+        date.with_day(day as u32)
+    } else {
+        let month_start = date.with_day(1)?;
+        let date = month_start + Months::new(1) - Days::new(day.unsigned_abs().into());
+        (date >= month_start).then_some(date)
+    }
+}
+
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, RuleError> {
+    value
+        .split(',')
+        .map(parse)
+        .collect::<Option<_>>()
+        .ok_or(RuleError::InvalidValue)
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    Some(match value {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// Parse a `BYDAY` value, like `MO`, `1MO` or `-1FR`
+fn parse_by_day(value: &str) -> Option<ByDay> {
+    let (nth, weekday) = value.split_at_checked(value.len().checked_sub(2)?)?;
+    let nth = match nth {
+        "" => 0,
+        nth => nth
+            .parse()
+            .ok()
+            .filter(|nth: &i8| *nth != 0 && (-53..=53).contains(nth))?,
+    };
+    Some(ByDay {
+        weekday: parse_weekday(weekday)?,
+        nth,
+    })
+}
@@ -0,0 +1,525 @@
+use chrono::{Datelike, Days, Month, NaiveDate, NaiveDateTime, TimeDelta, Weekday};
+use chrono_tz::{Europe, Tz};
+
+use super::{
+    ByDay, Event, EventTime, Frequency, IcalParser, RecurrenceRule, RuleError, TimeRef, event_days,
+    parse_duration, resolve_tzid, upcoming,
+};
+use crate::calendar_utils::MonthDate;
+
+const GOOGLE_FEED: &str = include_str!("testdata/google.ics");
+const NEXTCLOUD_FEED: &str = include_str!("testdata/nextcloud.ics");
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn datetime(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+    date(year, month, day).and_hms_opt(hour, min, 0).unwrap()
+}
+
+fn parse(feed: &str, from: NaiveDate, to: NaiveDate) -> Vec<Event> {
+    let mut parser = IcalParser::new(from..to, 100);
+    parser.push(feed.as_bytes());
+    parser.finish()
+}
+
+fn find<'a>(events: &'a [Event], summary: &str) -> &'a Event {
+    events
+        .iter()
+        .find(|event| event.summary == summary)
+        .unwrap()
+}
+
+/// Local starts of the occurrences of an event within the dates
+fn starts(event: &Event, tz: Tz, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDateTime> {
+    let mut res = Vec::new();
+    event.for_each_occurrence(
+        tz,
+        from.and_hms_opt(0, 0, 0).unwrap(),
+        to.and_hms_opt(0, 0, 0).unwrap(),
+        |occurrence| res.push(occurrence.start),
+    );
+    res
+}
+
+fn rule_dates(rule: &str, start: NaiveDateTime, to: NaiveDate) -> Vec<NaiveDate> {
+    let event = Event {
+        uid: "test".into(),
+        summary: "test".into(),
+        start: EventTime::DateTime(start, TimeRef::Floating),
+        duration: TimeDelta::zero(),
+        rule: Some(RecurrenceRule::parse(rule).unwrap()),
+        exdates: Vec::new(),
+        recurrence_id: None,
+    };
+    starts(&event, Tz::UTC, start.date(), to)
+        .into_iter()
+        .map(|start| start.date())
+        .collect()
+}
+
+#[test]
+fn google_feed_keeps_events_in_window() {
+    let events = parse(GOOGLE_FEED, date(2025, 1, 1), date(2025, 2, 1));
+    let mut summaries: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+    summaries.sort();
+    assert_eq!(
+        summaries,
+        [
+            "Deploy window",
+            "Release review",
+            "Выездная сессия, Казань",
+            "Стендап",
+            "Стендап (перенесён)",
+        ]
+    );
+
+    let standup = find(&events, "Стендап");
+    assert_eq!(
+        standup.start,
+        EventTime::DateTime(datetime(2025, 1, 6, 10, 0), TimeRef::Zone(Europe::Moscow))
+    );
+    assert_eq!(standup.duration, TimeDelta::minutes(15));
+    let rule = standup.rule.as_ref().unwrap();
+    assert_eq!(rule.frequency, Frequency::Weekly);
+    assert_eq!(
+        rule.by_day,
+        [
+            ByDay {
+                weekday: Weekday::Mon,
+                nth: 0
+            },
+            ByDay {
+                weekday: Weekday::Wed,
+                nth: 0
+            }
+        ]
+    );
+
+    let deploy = find(&events, "Deploy window");
+    assert_eq!(deploy.duration, TimeDelta::hours(1));
+    assert_eq!(
+        deploy.start,
+        EventTime::DateTime(datetime(2025, 1, 10, 22, 0), TimeRef::Utc)
+    );
+}
+
+#[test]
+fn weekly_rule_with_exdate_and_overrides() {
+    let events = parse(GOOGLE_FEED, date(2025, 1, 1), date(2025, 2, 1));
+    let standup = find(&events, "Стендап");
+    let days: Vec<u32> = starts(standup, Europe::Moscow, date(2025, 1, 1), date(2025, 2, 1))
+        .into_iter()
+        .map(|start| start.day())
+        .collect();
+    // 13th is excluded, 22nd is moved and 29th is cancelled
+    assert_eq!(days, [6, 8, 15, 20, 27]);
+
+    let moved = find(&events, "Стендап (перенесён)");
+    assert_eq!(
+        starts(moved, Europe::Moscow, date(2025, 1, 1), date(2025, 2, 1)),
+        [datetime(2025, 1, 22, 12, 0)]
+    );
+}
+
+#[test]
+fn event_days_in_local_time() {
+    let events = parse(GOOGLE_FEED, date(2025, 1, 1), date(2025, 2, 1));
+    let month = MonthDate::new(2025, Month::January);
+    let marked = |tz| {
+        let mask = event_days(&events, month, tz);
+        (0..31)
+            .filter(|day| mask.has_events_day0(*day))
+            .map(|day| day + 1)
+            .collect::<Vec<u8>>()
+    };
+    // The deploy window starts late on the 10th in UTC, which is the 11th in Moscow
+    assert_eq!(marked(Europe::Moscow), [6, 8, 11, 15, 20, 22, 27, 28, 29]);
+    assert_eq!(marked(Tz::UTC), [6, 8, 10, 15, 20, 22, 27, 28, 29]);
+}
+
+#[test]
+fn upcoming_events_are_ordered() {
+    let events = parse(GOOGLE_FEED, date(2025, 1, 1), date(2025, 3, 1));
+    let now = datetime(2025, 1, 15, 10, 5);
+    let next = upcoming(&events, Europe::Moscow, now, Days::new(30), 5);
+    let next: Vec<(NaiveDateTime, &str, bool)> = next
+        .iter()
+        .map(|occurrence| {
+            (
+                occurrence.start,
+                occurrence.event.summary.as_str(),
+                occurrence.is_all_day(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        next,
+        [
+            // Started, but not over yet
+            (datetime(2025, 1, 15, 10, 0), "Стендап", false),
+            (datetime(2025, 1, 15, 18, 0), "Release review", false),
+            (datetime(2025, 1, 20, 10, 0), "Стендап", false),
+            (datetime(2025, 1, 22, 12, 0), "Стендап (перенесён)", false),
+            (datetime(2025, 1, 27, 0, 0), "Выездная сессия, Казань", true),
+        ]
+    );
+}
+
+#[test]
+fn chunked_input_gives_same_events() {
+    let expected = parse(GOOGLE_FEED, date(2025, 1, 1), date(2025, 2, 1));
+    for chunk_size in [1, 2, 3, 7, 64] {
+        let mut parser = IcalParser::new(date(2025, 1, 1)..date(2025, 2, 1), 100);
+        for chunk in GOOGLE_FEED.as_bytes().chunks(chunk_size) {
+            parser.push(chunk);
+        }
+        assert_eq!(parser.finish(), expected, "chunk size {chunk_size}");
+    }
+}
+
+#[test]
+fn folded_lines_split_characters() {
+    let feed = "BEGIN:VEVENT\r\nDTSTART:20250101T100000Z\r\nSUMMARY:П\u{440}\r\n ивет\\, мир\r\nEND:VEVENT\r\n";
+    // Fold in the middle of a two byte character
+    let bytes = feed.as_bytes();
+    let split = feed.find("\u{440}").unwrap() + 1;
+    let mut folded = bytes[..split].to_vec();
+    folded.extend_from_slice(b"\r\n ");
+    folded.extend_from_slice(&bytes[split..]);
+
+    let mut parser = IcalParser::new(date(2025, 1, 1)..date(2025, 2, 1), 10);
+    parser.push(&folded);
+    let events = parser.finish();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].summary, "Привет, мир");
+}
+
+#[test]
+fn events_over_limit_are_dropped() {
+    let mut parser = IcalParser::new(date(2025, 1, 1)..date(2025, 2, 1), 2);
+    parser.push(GOOGLE_FEED.as_bytes());
+    assert_eq!(parser.finish().len(), 2);
+}
+
+#[test]
+fn events_without_start_are_skipped() {
+    let feed = "BEGIN:VEVENT\nSUMMARY:No start\nEND:VEVENT\nBEGIN:VEVENT\nSUMMARY:Bad start\nDTSTART:2025011\nEND:VEVENT\n";
+    assert!(parse(feed, date(2025, 1, 1), date(2025, 2, 1)).is_empty());
+}
+
+#[test]
+fn monthly_last_friday_with_prefixed_tzid() {
+    let events = parse(NEXTCLOUD_FEED, date(2025, 1, 1), date(2025, 5, 1));
+    let retro = find(&events, "Retrospective");
+    assert_eq!(
+        retro.start,
+        EventTime::DateTime(datetime(2025, 1, 31, 16, 0), TimeRef::Zone(Europe::Berlin))
+    );
+    assert_eq!(
+        starts(retro, Europe::Berlin, date(2025, 2, 1), date(2025, 5, 1)),
+        [
+            datetime(2025, 2, 28, 16, 0),
+            datetime(2025, 3, 28, 16, 0),
+            datetime(2025, 4, 25, 16, 0),
+        ]
+    );
+    // Moscow is two hours ahead of Berlin in winter and one in summer
+    assert_eq!(
+        starts(retro, Europe::Moscow, date(2025, 3, 1), date(2025, 5, 1)),
+        [datetime(2025, 3, 28, 18, 0), datetime(2025, 4, 25, 17, 0)]
+    );
+}
+
+#[test]
+fn yearly_all_day_event_from_long_ago() {
+    let events = parse(NEXTCLOUD_FEED, date(2025, 3, 1), date(2025, 4, 1));
+    let birthday = find(&events, "Anna's birthday");
+    assert_eq!(birthday.duration, TimeDelta::days(1));
+    assert_eq!(
+        starts(birthday, Europe::Moscow, date(2025, 1, 1), date(2026, 1, 1)),
+        [datetime(2025, 3, 15, 0, 0)]
+    );
+    // All-day events are on the same date in every time zone
+    let mask = event_days(
+        &events,
+        MonthDate::new(2025, Month::March),
+        Tz::America__Los_Angeles,
+    );
+    assert!(mask.has_events_day0(14));
+    assert!(!mask.has_events_day0(13));
+    assert!(!mask.has_events_day0(15));
+}
+
+#[test]
+fn biweekly_rule_with_count_across_dst() {
+    let events = parse(NEXTCLOUD_FEED, date(2025, 3, 1), date(2025, 5, 1));
+    let sprint = find(&events, "Sprint planning");
+    assert_eq!(sprint.duration, TimeDelta::hours(2));
+    assert_eq!(
+        starts(sprint, Europe::Berlin, date(2025, 3, 1), date(2025, 6, 1)),
+        [
+            datetime(2025, 3, 3, 9, 30),
+            datetime(2025, 3, 17, 9, 30),
+            datetime(2025, 3, 31, 9, 30),
+            datetime(2025, 4, 14, 9, 30),
+        ]
+    );
+    // Central European summer time starts on March 30th
+    assert_eq!(
+        starts(sprint, Tz::UTC, date(2025, 3, 10), date(2025, 4, 10)),
+        [datetime(2025, 3, 17, 8, 30), datetime(2025, 3, 31, 7, 30)]
+    );
+}
+
+#[test]
+fn daily_rule_until_is_inclusive() {
+    let events = parse(NEXTCLOUD_FEED, date(2025, 3, 1), date(2025, 4, 1));
+    let course = find(&events, "Course");
+    let days: Vec<u32> = starts(course, Europe::Berlin, date(2025, 3, 1), date(2025, 4, 1))
+        .into_iter()
+        .map(|start| start.day())
+        .collect();
+    assert_eq!(days, [24, 25, 26, 27, 28]);
+}
+
+#[test]
+fn unsupported_rule_keeps_first_occurrence() {
+    let events = parse(NEXTCLOUD_FEED, date(2025, 3, 1), date(2025, 4, 1));
+    let event = find(&events, "Unsupported rule");
+    assert!(event.rule.is_none());
+    assert_eq!(
+        starts(event, Europe::Berlin, date(2025, 3, 1), date(2025, 4, 1)),
+        [datetime(2025, 3, 5, 10, 0)]
+    );
+}
+
+#[test]
+fn yearly_rule_skips_missing_leap_day() {
+    assert_eq!(
+        rule_dates(
+            "FREQ=YEARLY;COUNT=3",
+            datetime(2024, 2, 29, 12, 0),
+            date(2040, 1, 1)
+        ),
+        [date(2024, 2, 29), date(2028, 2, 29), date(2032, 2, 29)]
+    );
+}
+
+#[test]
+fn monthly_rule_skips_short_months() {
+    assert_eq!(
+        rule_dates(
+            "FREQ=MONTHLY;COUNT=4",
+            datetime(2025, 1, 31, 9, 0),
+            date(2026, 1, 1)
+        ),
+        [
+            date(2025, 1, 31),
+            date(2025, 3, 31),
+            date(2025, 5, 31),
+            date(2025, 7, 31)
+        ]
+    );
+    assert_eq!(
+        rule_dates(
+            "FREQ=MONTHLY;BYMONTHDAY=-1;COUNT=3",
+            datetime(2025, 1, 31, 9, 0),
+            date(2026, 1, 1)
+        ),
+        [date(2025, 1, 31), date(2025, 2, 28), date(2025, 3, 31)]
+    );
+}
+
+#[test]
+fn monthly_and_yearly_nth_weekday() {
+    assert_eq!(
+        rule_dates(
+            "FREQ=MONTHLY;BYDAY=2TU;UNTIL=20250501",
+            datetime(2025, 1, 1, 9, 0),
+            date(2026, 1, 1)
+        ),
+        [
+            date(2025, 1, 14),
+            date(2025, 2, 11),
+            date(2025, 3, 11),
+            date(2025, 4, 8)
+        ]
+    );
+    // Last sunday of march and october, like daylight saving time in Europe
+    assert_eq!(
+        rule_dates(
+            "FREQ=YEARLY;BYMONTH=3,10;BYDAY=-1SU",
+            datetime(2025, 1, 1, 2, 0),
+            date(2027, 1, 1)
+        ),
+        [
+            date(2025, 3, 30),
+            date(2025, 10, 26),
+            date(2026, 3, 29),
+            date(2026, 10, 25)
+        ]
+    );
+}
+
+#[test]
+fn daily_rule_with_interval_and_weekdays() {
+    assert_eq!(
+        rule_dates(
+            "FREQ=DAILY;INTERVAL=3;COUNT=4",
+            datetime(2025, 12, 29, 9, 0),
+            date(2027, 1, 1)
+        ),
+        [
+            date(2025, 12, 29),
+            date(2026, 1, 1),
+            date(2026, 1, 4),
+            date(2026, 1, 7)
+        ]
+    );
+    assert_eq!(
+        rule_dates(
+            "FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR;UNTIL=20250113T000000Z",
+            datetime(2025, 1, 3, 9, 0),
+            date(2026, 1, 1)
+        ),
+        [
+            date(2025, 1, 3),
+            date(2025, 1, 6),
+            date(2025, 1, 7),
+            date(2025, 1, 8),
+            date(2025, 1, 9),
+            date(2025, 1, 10)
+        ]
+    );
+}
+
+#[test]
+fn rule_far_from_start_is_skipped_to() {
+    let event = Event {
+        uid: "daily".into(),
+        summary: "Daily".into(),
+        start: EventTime::DateTime(datetime(1990, 1, 1, 9, 0), TimeRef::Floating),
+        duration: TimeDelta::hours(1),
+        rule: Some(RecurrenceRule::parse("FREQ=DAILY").unwrap()),
+        exdates: Vec::new(),
+        recurrence_id: None,
+    };
+    assert_eq!(
+        starts(&event, Tz::UTC, date(2025, 6, 1), date(2025, 6, 4)),
+        [
+            datetime(2025, 6, 1, 9, 0),
+            datetime(2025, 6, 2, 9, 0),
+            datetime(2025, 6, 3, 9, 0)
+        ]
+    );
+}
+
+#[test]
+fn parse_rules() {
+    let rule = RecurrenceRule::parse("FREQ=MONTHLY;INTERVAL=2;BYDAY=1MO,-2FR,SU;WKST=SU").unwrap();
+    assert_eq!(rule.frequency, Frequency::Monthly);
+    assert_eq!(rule.interval, 2);
+    assert_eq!(rule.week_start, Weekday::Sun);
+    assert_eq!(
+        rule.by_day,
+        [
+            ByDay {
+                weekday: Weekday::Mon,
+                nth: 1
+            },
+            ByDay {
+                weekday: Weekday::Fri,
+                nth: -2
+            },
+            ByDay {
+                weekday: Weekday::Sun,
+                nth: 0
+            }
+        ]
+    );
+    let rule = RecurrenceRule::parse("FREQ=YEARLY;UNTIL=20301231T235959Z;BYMONTH=1,7").unwrap();
+    assert_eq!(
+        rule.until,
+        Some(EventTime::DateTime(
+            datetime(2030, 12, 31, 23, 59) + TimeDelta::seconds(59),
+            TimeRef::Utc
+        ))
+    );
+    assert_eq!(rule.by_month, [1, 7]);
+
+    assert_eq!(
+        RecurrenceRule::parse("FREQ=HOURLY"),
+        Err(RuleError::UnsupportedFrequency)
+    );
+    assert_eq!(
+        RecurrenceRule::parse("COUNT=3"),
+        Err(RuleError::UnsupportedFrequency)
+    );
+    assert_eq!(
+        RecurrenceRule::parse("FREQ=MONTHLY;BYSETPOS=-1"),
+        Err(RuleError::UnsupportedPart)
+    );
+    assert_eq!(
+        RecurrenceRule::parse("FREQ=WEEKLY;BYDAY=XX"),
+        Err(RuleError::InvalidValue)
+    );
+    assert_eq!(
+        RecurrenceRule::parse("FREQ=WEEKLY;INTERVAL=0"),
+        Err(RuleError::InvalidValue)
+    );
+    assert_eq!(
+        RecurrenceRule::parse("FREQ=MONTHLY;BYMONTHDAY=32"),
+        Err(RuleError::InvalidValue)
+    );
+}
+
+#[test]
+fn parse_values() {
+    assert_eq!(
+        EventTime::parse("20250101", None),
+        Some(EventTime::Date(date(2025, 1, 1)))
+    );
+    assert_eq!(
+        EventTime::parse("20250101T093000", Some(Europe::Moscow)),
+        Some(EventTime::DateTime(
+            datetime(2025, 1, 1, 9, 30),
+            TimeRef::Zone(Europe::Moscow)
+        ))
+    );
+    assert_eq!(
+        EventTime::parse("20250101T093000Z", Some(Europe::Moscow)),
+        Some(EventTime::DateTime(
+            datetime(2025, 1, 1, 9, 30),
+            TimeRef::Utc
+        ))
+    );
+    assert_eq!(EventTime::parse("20250132", None), None);
+    assert_eq!(EventTime::parse("20250101T0930", None), None);
+    assert_eq!(EventTime::parse("2025-01-01", None), None);
+
+    assert_eq!(parse_duration("PT1H30M"), Some(TimeDelta::minutes(90)));
+    assert_eq!(parse_duration("P1D"), Some(TimeDelta::days(1)));
+    assert_eq!(parse_duration("P2W"), Some(TimeDelta::weeks(2)));
+    assert_eq!(
+        parse_duration("-P0DT0H10M0S"),
+        Some(TimeDelta::minutes(-10))
+    );
+    assert_eq!(parse_duration("P1H"), None);
+    assert_eq!(parse_duration("PT1"), None);
+
+    assert_eq!(resolve_tzid("Europe/Moscow"), Some(Europe::Moscow));
+    assert_eq!(
+        resolve_tzid("/mozilla.org/20050126_1/Europe/Berlin"),
+        Some(Europe::Berlin)
+    );
+    assert_eq!(resolve_tzid("W. Europe Standard Time"), None);
+}
+
+#[test]
+fn wall_time_in_dst_gap_is_moved_forward() {
+    // Clocks in Berlin skip from 02:00 to 03:00 on March 30th 2025
+    let time = EventTime::DateTime(datetime(2025, 3, 30, 2, 30), TimeRef::Zone(Europe::Berlin));
+    assert_eq!(time.to_local(Europe::Berlin), datetime(2025, 3, 30, 3, 30));
+}
@@ -0,0 +1,391 @@
+//! Client of <https://www.isdayoff.ru>, the days off provider for Belarus, Kazakhstan, Russia and
+//! Ukraine
+
+use alloc::format;
+use core::str::from_utf8;
+
+use chrono::{Month, Months};
+use embedded_nal_async::{Dns, TcpConnect};
+use log::{error, info, warn};
+use reqwless::{request::Method, response::StatusCode};
+
+pub use crate::holidays::TargetCountry;
+use crate::{
+    calendar_utils::{DayKind, DaysOffMask, MonthDate, WeekConfig, YearDaysOff},
+    days_off::DaysOffProvider,
+    holidays::holidays_mask,
+    http::HttpClient,
+};
+
+/// Address of the service, over `https` when TLS is available
+#[cfg(feature = "tls")]
+pub const ISDAYOFF_URL: &str = "https://isdayoff.ru";
+#[cfg(not(feature = "tls"))]
+pub const ISDAYOFF_URL: &str = "http://isdayoff.ru";
+
+/// Days off from isdayoff, the offline holiday rules of the country are used when it can't be
+/// reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsdayoffProvider {
+    country: TargetCountry,
+    base_url: &'static str,
+}
+
+impl IsdayoffProvider {
+    pub const fn new(country: TargetCountry) -> Self {
+        Self {
+            country,
+            base_url: ISDAYOFF_URL,
+        }
+    }
+
+    /// Use a mirror or a test server of the service at `base_url`, without a trailing slash
+    pub const fn with_base_url(self, base_url: &'static str) -> Self {
+        Self { base_url, ..self }
+    }
+}
+
+impl DaysOffProvider for IsdayoffProvider {
+    async fn fetch_year_days_off<T: TcpConnect, D: Dns>(
+        &self,
+        client: &mut HttpClient<'_, T, D>,
+        year: u16,
+        _week: WeekConfig,
+    ) -> Result<Option<YearDaysOff>, reqwless::Error> {
+        info!("Fetching isdayoff data for year {year}");
+        let cc = self.country.to_countrycode();
+        let url = format!(
+            "{}/api/getdata?year={year}&cc={cc}&pre=1&holiday=1",
+            self.base_url
+        );
+        let mut rx_buf = [0; 4096];
+        let mut request = client.request(Method::GET, &url).await?;
+        let response = request.send(&mut rx_buf).await?;
+
+        match response.status {
+            StatusCode(200) => {
+                let body = response.body().read_to_end().await?;
+                let res = parse_isdayoff_year_response(year, body)
+                    .inspect_err(|e| error!("Failed to parse isdayoff response: {e:?}"))
+                    .ok();
+                Ok(res)
+            }
+            StatusCode(400) => {
+                let body = response.body().read_to_end().await?;
+                // Why is this service using 400 as status code for "service error"? It should be
+                // 5XX. It even uses 400 for "not found" like favicon.ico!
+                if body == b"100" {
+                    error!("isdayoff request failed, invalid date");
+                } else if body == b"199" {
+                    error!("isdayoff request failed, backend error");
+                } else {
+                    let body = from_utf8(body).unwrap_or("<binary>");
+                    warn!("Unexpected error response: {body}")
+                }
+                Ok(None)
+            }
+            StatusCode(404) => {
+                error!("isdayoff found no data");
+                Ok(None)
+            }
+            _ => {
+                warn!("Unexpected status code: {}", response.status.0);
+                Ok(None)
+            }
+        }
+    }
+
+    fn region(&self) -> &str {
+        self.country.to_countrycode()
+    }
+
+    fn offline_days_off(&self, month: MonthDate, week: WeekConfig) -> DaysOffMask {
+        holidays_mask(self.country, month, week)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsdayoffParseError {
+    /// Response has a character that is not a known day code
+    UnknownDayCode(u8),
+    /// Response has more days than a month can have
+    TooManyDays(usize),
+    /// Response for a year doesn't have a code for each of its days
+    WrongYearLength { expected: usize, got: usize },
+}
+
+/// Parse the body of a successful `getdata` response for a single month.
+///
+/// The body is a string of day codes, one character per day, starting from the first day of the
+/// month. Shortened days and holidays are only reported when requested with `pre=1` and
+/// `holiday=1`.
+pub fn parse_isdayoff_response(body: &[u8]) -> Result<DaysOffMask, IsdayoffParseError> {
+    if body.len() > 31 {
+        return Err(IsdayoffParseError::TooManyDays(body.len()));
+    }
+    let mut mask = DaysOffMask::default();
+    for (day, code) in body.iter().enumerate() {
+        mask = mask.with_day0(day as u8, parse_day_code(*code)?);
+    }
+    Ok(mask)
+}
+
+/// Parse the body of a successful `getdata` response for a whole year, 365 or 366 day codes
+pub fn parse_isdayoff_year_response(
+    year: u16,
+    body: &[u8],
+) -> Result<YearDaysOff, IsdayoffParseError> {
+    let start = MonthDate::new(year, Month::January).to_start_day_naive();
+    let end = MonthDate::new(year + 1, Month::January).to_start_day_naive();
+    let expected = (end - start).num_days() as usize;
+    if body.len() != expected {
+        return Err(IsdayoffParseError::WrongYearLength {
+            expected,
+            got: body.len(),
+        });
+    }
+    let mut rest = body;
+    let mut res = Ok(());
+    let year_days_off = YearDaysOff::from_fn(year, |month| {
+        let start = month.to_start_day_naive();
+        let days = ((month + Months::new(1)).to_start_day_naive() - start).num_days() as usize;
+        let (days_codes, next) = rest.split_at(days);
+        rest = next;
+        parse_isdayoff_response(days_codes).unwrap_or_else(|e| {
+            res = Err(e);
+            DaysOffMask::default()
+        })
+    });
+    res.map(|()| year_days_off)
+}
+
+const fn parse_day_code(code: u8) -> Result<DayKind, IsdayoffParseError> {
+    match code {
+        b'0' => Ok(DayKind::Workday),
+        b'1' => Ok(DayKind::Weekend),
+        b'2' => Ok(DayKind::Shortened),
+        b'8' => Ok(DayKind::PublicHoliday),
+        _ => Err(IsdayoffParseError::UnknownDayCode(code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Month;
+    use embassy_futures::block_on;
+
+    use super::{
+        IsdayoffParseError, IsdayoffProvider, TargetCountry, parse_isdayoff_response,
+        parse_isdayoff_year_response,
+    };
+    use crate::{
+        calendar_utils::{DayKind, DaysOffMask, MonthDate, WeekConfig},
+        days_off::DaysOffProvider,
+        holidays::holidays_mask,
+        http::HttpClient,
+        stub_server::{StdDns, StdTcp, StubServer, closed_port_url},
+    };
+
+    const MAY_2025: MonthDate = MonthDate::new(2025, Month::May);
+
+    fn provider(url: String) -> IsdayoffProvider {
+        IsdayoffProvider::new(TargetCountry::Russia).with_base_url(Box::leak(url.into_boxed_str()))
+    }
+
+    #[test]
+    fn parses_first_day_as_lowest_bit() {
+        assert_eq!(
+            parse_isdayoff_response(b"1"),
+            Ok(DaysOffMask::from_days_off(0b1))
+        );
+        assert_eq!(
+            parse_isdayoff_response(b"0001"),
+            Ok(DaysOffMask::from_days_off(0b1000))
+        );
+    }
+
+    #[test]
+    fn parses_january_2025_russia() {
+        // New year holidays from 1st to 8th, then regular weekends
+        let mask = parse_isdayoff_response(b"1111111100110000011000001100000").unwrap();
+        let off: Vec<u8> = (0..31).filter(|d| mask.is_day0_off(*d)).collect();
+        assert_eq!(off, [0, 1, 2, 3, 4, 5, 6, 7, 10, 11, 17, 18, 24, 25]);
+    }
+
+    #[test]
+    fn parses_holidays_and_shortened_days() {
+        // May 2025 in Russia with `pre=1&holiday=1`
+        let mask = parse_isdayoff_response(b"8111002181100000110000011000001").unwrap();
+        assert_eq!(mask.day0_kind(0), DayKind::PublicHoliday);
+        assert_eq!(mask.day0_kind(1), DayKind::Weekend);
+        assert_eq!(mask.day0_kind(4), DayKind::Workday);
+        assert_eq!(mask.day0_kind(6), DayKind::Shortened);
+        assert_eq!(mask.day0_kind(7), DayKind::Weekend);
+        assert_eq!(mask.day0_kind(8), DayKind::PublicHoliday);
+        assert!(mask.is_day0_off(8));
+        assert!(!mask.is_day0_off(6));
+    }
+
+    #[test]
+    fn day1_is_the_day_of_the_month() {
+        // May 2025 in Russia: 1st and 9th are holidays, 2nd a day off, 5th a workday and 7th
+        // shortened
+        let mask = parse_isdayoff_response(b"8111002181100000110000011000001").unwrap();
+        assert_eq!(mask.day1_kind(1), DayKind::PublicHoliday);
+        assert_eq!(mask.day1_kind(2), DayKind::Weekend);
+        assert_eq!(mask.day1_kind(5), DayKind::Workday);
+        assert_eq!(mask.day1_kind(7), DayKind::Shortened);
+        assert_eq!(mask.day1_kind(9), DayKind::PublicHoliday);
+        assert_eq!(mask.day1_kind(31), DayKind::Weekend);
+        assert!(mask.is_day1_off(2));
+        assert!(!mask.is_day1_off(7));
+    }
+
+    #[test]
+    fn parses_full_month() {
+        let body = [b'1'; 31];
+        assert_eq!(
+            parse_isdayoff_response(&body),
+            Ok(DaysOffMask::from_days_off(u32::MAX >> 1))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_codes() {
+        assert_eq!(
+            parse_isdayoff_response(b"0104"),
+            Err(IsdayoffParseError::UnknownDayCode(b'4'))
+        );
+        assert_eq!(
+            parse_isdayoff_response(b"01 0"),
+            Err(IsdayoffParseError::UnknownDayCode(b' '))
+        );
+    }
+
+    #[test]
+    fn rejects_too_long_response() {
+        assert_eq!(
+            parse_isdayoff_response(&[b'0'; 32]),
+            Err(IsdayoffParseError::TooManyDays(32))
+        );
+    }
+
+    /// Year 2025 in Russia, with only the days of January and May filled in
+    fn year_2025_body() -> Vec<u8> {
+        let mut body = b"1111111100110000011000001100000".to_vec();
+        body.resize(120, b'0');
+        body.extend_from_slice(b"8111002181100000110000011000001");
+        body.resize(365, b'0');
+        body
+    }
+
+    #[test]
+    fn parses_year_into_months() {
+        let year = parse_isdayoff_year_response(2025, &year_2025_body()).unwrap();
+        assert_eq!(year.year(), 2025);
+        let january = year.month(MonthDate::new(2025, Month::January)).unwrap();
+        assert_eq!(
+            Ok(january),
+            parse_isdayoff_response(b"1111111100110000011000001100000")
+        );
+        let may = year.month(MAY_2025).unwrap();
+        assert_eq!(may.day1_kind(1), DayKind::PublicHoliday);
+        assert_eq!(may.day1_kind(9), DayKind::PublicHoliday);
+        assert_eq!(may.day1_kind(31), DayKind::Weekend);
+        let april = year.month(MonthDate::new(2025, Month::April)).unwrap();
+        assert_eq!(april, DaysOffMask::default());
+    }
+
+    #[test]
+    fn parses_leap_year() {
+        let mut body = [b'0'; 366];
+        // February 29th and December 31st
+        body[59] = b'8';
+        body[365] = b'1';
+        let year = parse_isdayoff_year_response(2024, &body).unwrap();
+        let february = year.month(MonthDate::new(2024, Month::February)).unwrap();
+        assert_eq!(february.day1_kind(29), DayKind::PublicHoliday);
+        assert_eq!(february.day1_kind(28), DayKind::Workday);
+        let march = year.month(MonthDate::new(2024, Month::March)).unwrap();
+        assert_eq!(march.day1_kind(1), DayKind::Workday);
+        let december = year.month(MonthDate::new(2024, Month::December)).unwrap();
+        assert_eq!(december.day1_kind(31), DayKind::Weekend);
+    }
+
+    #[test]
+    fn rejects_wrong_year_length() {
+        assert_eq!(
+            parse_isdayoff_year_response(2025, &[b'0'; 366]),
+            Err(IsdayoffParseError::WrongYearLength {
+                expected: 365,
+                got: 366
+            })
+        );
+        assert_eq!(
+            parse_isdayoff_year_response(2024, &[b'0'; 365]),
+            Err(IsdayoffParseError::WrongYearLength {
+                expected: 366,
+                got: 365
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_codes_in_year() {
+        let mut body = year_2025_body();
+        body[300] = b'x';
+        assert_eq!(
+            parse_isdayoff_year_response(2025, &body),
+            Err(IsdayoffParseError::UnknownDayCode(b'x'))
+        );
+    }
+
+    #[test]
+    fn fetches_year_from_server() {
+        let server = StubServer::start(vec![(
+            "/api/getdata?year=2025&cc=ru&pre=1&holiday=1".into(),
+            200,
+            year_2025_body(),
+        )]);
+        let mut client = HttpClient::new(&StdTcp, &StdDns);
+        let year = block_on(provider(server.url()).fetch_year_days_off(
+            &mut client,
+            2025,
+            WeekConfig::ISO,
+        ))
+        .unwrap();
+        assert_eq!(
+            year,
+            parse_isdayoff_year_response(2025, &year_2025_body()).ok()
+        );
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn service_error_has_no_data() {
+        let server = StubServer::start(vec![(
+            "/api/getdata?year=2025&cc=ru&pre=1&holiday=1".into(),
+            400,
+            b"199".to_vec(),
+        )]);
+        let mut client = HttpClient::new(&StdTcp, &StdDns);
+        let year = block_on(provider(server.url()).fetch_year_days_off(
+            &mut client,
+            2025,
+            WeekConfig::ISO,
+        ));
+        assert_eq!(year.unwrap(), None);
+    }
+
+    #[test]
+    fn unreachable_service_falls_back_to_holiday_rules() {
+        let provider = provider(closed_port_url());
+        let mut client = HttpClient::new(&StdTcp, &StdDns);
+        let year = block_on(provider.fetch_year_days_off(&mut client, 2025, WeekConfig::ISO));
+        assert!(year.is_err());
+        assert_eq!(
+            provider.offline_days_off(MAY_2025, WeekConfig::ISO),
+            holidays_mask(TargetCountry::Russia, MAY_2025, WeekConfig::ISO)
+        );
+    }
+}
@@ -1 +1,35 @@
-#![no_std]
+//! Platform-independent core of the calendar, kept separate from the firmware so it can be built
+//! and tested on the host.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "caldav")]
+pub mod caldav;
+pub mod calendar_utils;
+mod crc;
+#[cfg(feature = "http")]
+pub mod days_off;
+pub mod days_off_cache;
+pub mod days_off_store;
+pub mod holidays;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "ical")]
+pub mod ical;
+#[cfg(feature = "isdayoff")]
+pub mod isdayoff;
+pub mod locale;
+#[cfg(feature = "nager")]
+pub mod nager;
+pub mod partition_table;
+pub mod posix_tz;
+pub mod power_budget;
+pub mod refresh_schedule;
+pub mod rtc_drift;
+pub mod sleep_state;
+pub mod sntp;
+#[cfg(all(test, feature = "http"))]
+mod stub_server;
+pub mod sync_schedule;
+pub mod temperature_log;
@@ -0,0 +1,223 @@
+//! Localized strings shown on the calendar
+
+use chrono::{Month, Weekday};
+
+use crate::calendar_utils::all_weekdays_short_en;
+
+/// Language of the calendar text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Locale {
+    Belarusian,
+    English,
+    Kazakh,
+    Russian,
+    Ukrainian,
+}
+
+impl Locale {
+    pub const ALL: [Self; 5] = [
+        Self::Belarusian,
+        Self::English,
+        Self::Kazakh,
+        Self::Russian,
+        Self::Ukrainian,
+    ];
+
+    /// Get the name of the month, as used in a calendar title
+    pub const fn month_name(self, month: Month) -> &'static str {
+        self.month_names()[month.number_from_month() as usize - 1]
+    }
+
+    /// Get the abbreviated name of the weekday, as used in a calendar header
+    pub const fn weekday_short_name(self, day: Weekday) -> &'static str {
+        self.weekdays_short()[day.num_days_from_monday() as usize]
+    }
+
+    /// Get the label of the week numbers column
+    pub const fn week_label(self) -> &'static str {
+        match self {
+            Self::Belarusian => "тыд",
+            Self::English => "wk",
+            Self::Kazakh => "апт",
+            Self::Russian => "нед",
+            Self::Ukrainian => "тиж",
+        }
+    }
+
+    /// Get the message shown instead of the calendar when the date is not known
+    pub const fn time_unknown_label(self) -> &'static str {
+        match self {
+            Self::Belarusian => "Час невядомы",
+            Self::English => "Time unknown",
+            Self::Kazakh => "Уақыт белгісіз",
+            Self::Russian => "Время неизвестно",
+            Self::Ukrainian => "Час невідомий",
+        }
+    }
+
+    /// Get the message shown instead of the calendar when the RTC lost its time and it wasn't
+    /// synchronized since
+    pub const fn time_not_set_label(self) -> &'static str {
+        match self {
+            Self::Belarusian => "Час не ўсталяваны",
+            Self::English => "Time not set",
+            Self::Kazakh => "Уақыт орнатылмаған",
+            Self::Russian => "Время не установлено",
+            Self::Ukrainian => "Час не встановлено",
+        }
+    }
+
+    const fn month_names(self) -> [&'static str; 12] {
+        match self {
+            Self::Belarusian => [
+                "Студзень",
+                "Люты",
+                "Сакавік",
+                "Красавік",
+                "Травень",
+                "Чэрвень",
+                "Ліпень",
+                "Жнівень",
+                "Верасень",
+                "Кастрычнік",
+                "Лістапад",
+                "Снежань",
+            ],
+            Self::English => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Self::Kazakh => [
+                "Қаңтар",
+                "Ақпан",
+                "Наурыз",
+                "Сәуір",
+                "Мамыр",
+                "Маусым",
+                "Шілде",
+                "Тамыз",
+                "Қыркүйек",
+                "Қазан",
+                "Қараша",
+                "Желтоқсан",
+            ],
+            Self::Russian => [
+                "Январь",
+                "Февраль",
+                "Март",
+                "Апрель",
+                "Май",
+                "Июнь",
+                "Июль",
+                "Август",
+                "Сентябрь",
+                "Октябрь",
+                "Ноябрь",
+                "Декабрь",
+            ],
+            Self::Ukrainian => [
+                "Січень",
+                "Лютий",
+                "Березень",
+                "Квітень",
+                "Травень",
+                "Червень",
+                "Липень",
+                "Серпень",
+                "Вересень",
+                "Жовтень",
+                "Листопад",
+                "Грудень",
+            ],
+        }
+    }
+
+    /// Starting from monday
+    const fn weekdays_short(self) -> [&'static str; 7] {
+        match self {
+            Self::Belarusian => ["пн", "аў", "ср", "чц", "пт", "сб", "нд"],
+            Self::English => all_weekdays_short_en(),
+            Self::Kazakh => ["дс", "сс", "ср", "бс", "жм", "сн", "жс"],
+            Self::Russian => ["пн", "вт", "ср", "чт", "пт", "сб", "вс"],
+            Self::Ukrainian => ["пн", "вт", "ср", "чт", "пт", "сб", "нд"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Month, Weekday};
+    use num_traits::FromPrimitive;
+
+    use super::Locale;
+    use crate::calendar_utils::all_weekdays;
+
+    #[test]
+    fn english_matches_chrono() {
+        for month in 1..=12 {
+            let month = Month::from_u8(month).unwrap();
+            assert_eq!(Locale::English.month_name(month), month.name());
+        }
+        assert_eq!(Locale::English.weekday_short_name(Weekday::Mon), "mon");
+    }
+
+    #[test]
+    fn localized_names() {
+        assert_eq!(Locale::Russian.month_name(Month::January), "Январь");
+        assert_eq!(Locale::Ukrainian.month_name(Month::November), "Листопад");
+        assert_eq!(Locale::Belarusian.month_name(Month::October), "Кастрычнік");
+        assert_eq!(Locale::Kazakh.month_name(Month::December), "Желтоқсан");
+        assert_eq!(Locale::Russian.weekday_short_name(Weekday::Sun), "вс");
+        assert_eq!(Locale::Ukrainian.weekday_short_name(Weekday::Sun), "нд");
+        assert_eq!(Locale::Belarusian.weekday_short_name(Weekday::Tue), "аў");
+        assert_eq!(Locale::Kazakh.weekday_short_name(Weekday::Fri), "жм");
+        assert_eq!(Locale::Kazakh.weekday_short_name(Weekday::Sat), "сн");
+    }
+
+    #[test]
+    fn names_are_distinct() {
+        for locale in Locale::ALL {
+            let mut months: Vec<&str> = (1..=12)
+                .map(|month| locale.month_name(Month::from_u8(month).unwrap()))
+                .collect();
+            months.sort();
+            months.dedup();
+            assert_eq!(months.len(), 12, "{locale:?}");
+
+            let mut weekdays: Vec<&str> = all_weekdays()
+                .into_iter()
+                .map(|day| locale.weekday_short_name(day))
+                .collect();
+            weekdays.sort();
+            weekdays.dedup();
+            assert_eq!(weekdays.len(), 7, "{locale:?}");
+        }
+    }
+
+    #[test]
+    fn names_fit_the_header() {
+        for locale in Locale::ALL {
+            for month in 1..=12 {
+                let name = locale.month_name(Month::from_u8(month).unwrap());
+                assert!(name.chars().count() <= 10, "{locale:?} {name}");
+            }
+            for day in all_weekdays() {
+                assert!(locale.weekday_short_name(day).chars().count() <= 3);
+            }
+            assert!(locale.week_label().chars().count() <= 3);
+            // Drawn across the whole screen in the biggest font
+            assert!(locale.time_unknown_label().chars().count() <= 29);
+            assert!(locale.time_not_set_label().chars().count() <= 29);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Client of the [Nager.Date](https://date.nager.at) public holiday API, covers over a hundred
+//! countries
+
+use alloc::{format, vec::Vec};
+
+use chrono::{Datelike, NaiveDate};
+use embedded_io_async::Read;
+use embedded_nal_async::{Dns, TcpConnect};
+use log::{error, info, warn};
+use reqwless::{request::Method, response::StatusCode};
+
+use crate::{
+    calendar_utils::{DayKind, DaysOffMask, MonthDate, WeekConfig, YearDaysOff},
+    days_off::{DaysOffProvider, weekends_mask},
+    http::HttpClient,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Address of the public instance, over `https` when TLS is available
+#[cfg(feature = "tls")]
+pub const NAGER_URL: &str = "https://date.nager.at";
+#[cfg(not(feature = "tls"))]
+pub const NAGER_URL: &str = "http://date.nager.at";
+/// Strings are cut to this amount of bytes, only the short ones are needed
+const MAX_TOKEN_LEN: usize = 32;
+
+/// Public holidays from Nager.Date, on top of the weekends
+///
+/// The API lists the holidays without the days off they are moved to, and the weekends come from
+/// the week config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NagerProvider {
+    country: &'static str,
+    subdivision: Option<&'static str>,
+    base_url: &'static str,
+}
+
+impl NagerProvider {
+    /// Get the holidays of the whole country, given by its ISO 3166-1 code like `DE`
+    pub const fn new(country: &'static str) -> Self {
+        Self {
+            country,
+            subdivision: None,
+            base_url: NAGER_URL,
+        }
+    }
+
+    /// Also include the regional holidays of a subdivision, given by its ISO 3166-2 code like
+    /// `DE-BY`
+    pub const fn with_subdivision(self, subdivision: &'static str) -> Self {
+        Self {
+            subdivision: Some(subdivision),
+            ..self
+        }
+    }
+
+    /// Use a self-hosted instance or a test server at `base_url`, without a trailing slash
+    pub const fn with_base_url(self, base_url: &'static str) -> Self {
+        Self { base_url, ..self }
+    }
+}
+
+impl DaysOffProvider for NagerProvider {
+    async fn fetch_year_days_off<T: TcpConnect, D: Dns>(
+        &self,
+        client: &mut HttpClient<'_, T, D>,
+        year: u16,
+        week: WeekConfig,
+    ) -> Result<Option<YearDaysOff>, reqwless::Error> {
+        info!("Fetching Nager.Date holidays for year {year}");
+        let url = format!(
+            "{}/api/v3/PublicHolidays/{year}/{}",
+            self.base_url, self.country
+        );
+        let mut rx_buf = [0; 4096];
+        let mut request = client.request(Method::GET, &url).await?;
+        let response = request.send(&mut rx_buf).await?;
+
+        match response.status {
+            StatusCode(200) => {}
+            StatusCode(204) => {
+                warn!("Nager.Date has no holidays for year {year}");
+                return Ok(None);
+            }
+            StatusCode(404) => {
+                error!("Nager.Date doesn't know country {}", self.country);
+                return Ok(None);
+            }
+            status => {
+                warn!("Unexpected status code: {}", status.0);
+                return Ok(None);
+            }
+        }
+        let mut parser = NagerParser::new(self.subdivision);
+        let mut reader = response.body().reader();
+        let mut chunk = [0; 256];
+        loop {
+            let len = reader.read(&mut chunk).await?;
+            if len == 0 {
+                break;
+            }
+            parser.push(&chunk[..len]);
+        }
+        let holidays = parser.finish();
+        Ok(Some(YearDaysOff::from_fn(year, |month| {
+            month_mask(&holidays, month, week)
+        })))
+    }
+
+    fn region(&self) -> &str {
+        self.subdivision.unwrap_or(self.country)
+    }
+}
+
+/// Mask of the weekends of `month` with the `holidays` that fall on it
+pub fn month_mask(holidays: &[NaiveDate], month: MonthDate, week: WeekConfig) -> DaysOffMask {
+    let start = month.to_start_day_naive();
+    holidays
+        .iter()
+        .filter(|date| date.year() == start.year() && date.month() == start.month())
+        .fold(weekends_mask(month, week), |mask, date| {
+            mask.with_day0(date.day0() as u8, DayKind::PublicHoliday)
+        })
+}
+
+/// Streaming parser of the `PublicHolidays` response that collects the dates of the holidays
+///
+/// Only the public holidays of the whole country or of the subdivision are kept, bank holidays and
+/// observances are not days off.
+pub struct NagerParser {
+    subdivision: Option<&'static str>,
+    /// Nesting of arrays and objects, the holidays are objects at depth 2
+    depth: u8,
+    in_string: bool,
+    escape: bool,
+    /// Set after the key of a holiday field, until its value ends
+    in_value: bool,
+    key: Vec<u8>,
+    /// String or literal being read
+    token: Vec<u8>,
+    holiday: HolidayFields,
+    holidays: Vec<NaiveDate>,
+}
+
+/// Fields of the holiday object being parsed
+#[derive(Default)]
+struct HolidayFields {
+    date: Option<NaiveDate>,
+    global: bool,
+    in_subdivision: bool,
+    /// If the holiday is public, `None` when it has no types
+    public: Option<bool>,
+}
+
+impl NagerParser {
+    pub fn new(subdivision: Option<&'static str>) -> Self {
+        Self {
+            subdivision,
+            depth: 0,
+            in_string: false,
+            escape: false,
+            in_value: false,
+            key: Vec::new(),
+            token: Vec::new(),
+            holiday: HolidayFields::default(),
+            holidays: Vec::new(),
+        }
+    }
+
+    /// Parse the next chunk of data
+    pub fn push(&mut self, data: &[u8]) {
+        for &byte in data {
+            if self.in_string {
+                match byte {
+                    _ if self.escape => {
+                        self.escape = false;
+                        self.push_token(byte);
+                    }
+                    b'\\' => self.escape = true,
+                    b'"' => {
+                        self.in_string = false;
+                        self.string_end();
+                    }
+                    byte => self.push_token(byte),
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => {
+                    self.depth = self.depth.saturating_add(1);
+                    if byte == b'{' && self.depth == 2 {
+                        self.holiday = HolidayFields::default();
+                    } else if self.depth == 3 && self.key == b"types" {
+                        self.holiday.public = Some(false);
+                    }
+                }
+                b'}' | b']' => {
+                    self.value_end();
+                    if byte == b'}' && self.depth == 2 {
+                        self.holiday_end();
+                    }
+                    self.depth = self.depth.saturating_sub(1);
+                }
+                b':' if self.depth == 2 => self.in_value = true,
+                b',' => self.value_end(),
+                b' ' | b'\t' | b'\r' | b'\n' => {}
+                // Literals like `true` and numbers
+                byte if self.depth == 2 && self.in_value => self.push_token(byte),
+                _ => {}
+            }
+        }
+    }
+
+    /// Finish parsing and get the dates of the holidays, in the order of the response
+    pub fn finish(self) -> Vec<NaiveDate> {
+        self.holidays
+    }
+
+    fn push_token(&mut self, byte: u8) {
+        if self.token.len() < MAX_TOKEN_LEN {
+            self.token.push(byte);
+        }
+    }
+
+    fn string_end(&mut self) {
+        let token = core::mem::take(&mut self.token);
+        match self.depth {
+            2 if !self.in_value => self.key = token,
+            2 => {
+                if self.key == b"date" {
+                    self.holiday.date = parse_date(&token);
+                    if self.holiday.date.is_none() {
+                        warn!("Invalid holiday date");
+                    }
+                }
+                self.in_value = false;
+            }
+            3 if self.key == b"types" && token == b"Public" => self.holiday.public = Some(true),
+            3 if self.key == b"counties"
+                && self.subdivision.is_some_and(|sub| sub.as_bytes() == token) =>
+            {
+                self.holiday.in_subdivision = true
+            }
+            _ => {}
+        }
+    }
+
+    /// End the value of a holiday field, literal values are only known at this point
+    fn value_end(&mut self) {
+        if self.depth != 2 {
+            return;
+        }
+        if self.in_value && self.key == b"global" {
+            self.holiday.global = self.token == b"true";
+        }
+        self.in_value = false;
+        self.token.clear();
+    }
+
+    fn holiday_end(&mut self) {
+        let holiday = core::mem::take(&mut self.holiday);
+        if let Some(date) = holiday.date
+            && holiday.public.unwrap_or(true)
+            && (holiday.global || holiday.in_subdivision)
+        {
+            self.holidays.push(date);
+        }
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date
+fn parse_date(value: &[u8]) -> Option<NaiveDate> {
+    let value = core::str::from_utf8(value).ok()?;
+    let mut parts = value.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    NaiveDate::from_ymd_opt(year, month, day)
+}
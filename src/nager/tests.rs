@@ -0,0 +1,160 @@
+use chrono::{Month, NaiveDate};
+use embassy_futures::block_on;
+
+use super::{NagerParser, NagerProvider, month_mask};
+use crate::{
+    calendar_utils::{DayKind, MonthDate, WeekConfig},
+    days_off::{DaysOffProvider, weekends_mask},
+    http::HttpClient,
+    stub_server::{StdDns, StdTcp, StubServer, closed_port_url},
+};
+
+const DE_2025: &[u8] = include_bytes!("testdata/de_2025.json");
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn parse(data: &[u8], subdivision: Option<&'static str>) -> Vec<NaiveDate> {
+    let mut parser = NagerParser::new(subdivision);
+    parser.push(data);
+    parser.finish()
+}
+
+/// Leak the URL of a stub server, providers are configured with static strings
+fn leak(url: String) -> &'static str {
+    Box::leak(url.into_boxed_str())
+}
+
+#[test]
+fn parses_nationwide_public_holidays() {
+    assert_eq!(
+        parse(DE_2025, None),
+        [
+            date(2025, 1, 1),
+            date(2025, 4, 18),
+            date(2025, 4, 21),
+            date(2025, 5, 1),
+            date(2025, 5, 29),
+            date(2025, 6, 9),
+            date(2025, 10, 3),
+            date(2025, 12, 25),
+            date(2025, 12, 26),
+        ]
+    );
+}
+
+#[test]
+fn includes_subdivision_holidays() {
+    let holidays = parse(DE_2025, Some("DE-BY"));
+    assert_eq!(holidays.len(), 12);
+    assert!(holidays.contains(&date(2025, 1, 6)));
+    assert!(holidays.contains(&date(2025, 6, 19)));
+    assert!(holidays.contains(&date(2025, 11, 1)));
+    assert!(!holidays.contains(&date(2025, 10, 31)));
+}
+
+#[test]
+fn skips_observances() {
+    assert!(!parse(DE_2025, None).contains(&date(2025, 12, 24)));
+}
+
+#[test]
+fn parses_in_chunks() {
+    let expected = parse(DE_2025, Some("DE-SN"));
+    for size in 1..=7 {
+        let mut parser = NagerParser::new(Some("DE-SN"));
+        for chunk in DE_2025.chunks(size) {
+            parser.push(chunk);
+        }
+        assert_eq!(parser.finish(), expected, "chunk size {size}");
+    }
+}
+
+#[test]
+fn holidays_without_types_are_public() {
+    let data = br#"[ {"date": "2024-07-04", "name": "Independence Day", "global": true} ]"#;
+    assert_eq!(parse(data, None), [date(2024, 7, 4)]);
+}
+
+#[test]
+fn empty_response() {
+    assert_eq!(parse(b"[]", None), []);
+}
+
+#[test]
+fn month_mask_has_weekends_and_holidays() {
+    let month = MonthDate::new(2025, Month::May);
+    let mask = month_mask(&parse(DE_2025, None), month, WeekConfig::ISO);
+    assert_eq!(mask.day1_kind(1), DayKind::PublicHoliday);
+    assert_eq!(mask.day1_kind(29), DayKind::PublicHoliday);
+    assert_eq!(mask.day1_kind(3), DayKind::Weekend);
+    assert_eq!(mask.day1_kind(2), DayKind::Workday);
+    assert_eq!(mask.day1_kind(30), DayKind::Workday);
+}
+
+#[test]
+fn fetches_from_server() {
+    let server = StubServer::start(vec![(
+        "/api/v3/PublicHolidays/2025/DE".into(),
+        200,
+        DE_2025.to_vec(),
+    )]);
+    let provider = NagerProvider::new("DE").with_base_url(leak(server.url()));
+    let mut client = HttpClient::new(&StdTcp, &StdDns);
+    let year = block_on(provider.fetch_year_days_off(&mut client, 2025, WeekConfig::ISO)).unwrap();
+
+    let year = year.unwrap();
+    let october = year.month(MonthDate::new(2025, Month::October)).unwrap();
+    assert_eq!(october.day1_kind(3), DayKind::PublicHoliday);
+    assert_eq!(october.day1_kind(31), DayKind::Workday);
+    let december = year.month(MonthDate::new(2025, Month::December)).unwrap();
+    assert_eq!(december.day1_kind(25), DayKind::PublicHoliday);
+    assert_eq!(december.day1_kind(24), DayKind::Workday);
+    assert_eq!(server.requests(), ["/api/v3/PublicHolidays/2025/DE"]);
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn fetches_over_https() {
+    use crate::{
+        http::{TLS_READ_BUFFER_SIZE, TlsConfig},
+        stub_server::TEST_CA,
+    };
+
+    let server = StubServer::start_tls(vec![(
+        "/api/v3/PublicHolidays/2025/DE".into(),
+        200,
+        DE_2025.to_vec(),
+    )]);
+    let provider = NagerProvider::new("DE").with_base_url(leak(server.url()));
+    let (mut read_buffer, mut write_buffer) = (vec![0; TLS_READ_BUFFER_SIZE], vec![0; 1024]);
+    let tls = TlsConfig::new([7; 32], &mut read_buffer, &mut write_buffer, TEST_CA);
+    let mut client = HttpClient::new_with_tls(&StdTcp, &StdDns, tls);
+    let year = block_on(provider.fetch_year_days_off(&mut client, 2025, WeekConfig::ISO)).unwrap();
+
+    let october = year.unwrap().month(MonthDate::new(2025, Month::October));
+    assert_eq!(october.unwrap().day1_kind(3), DayKind::PublicHoliday);
+}
+
+#[test]
+fn unknown_country_has_no_data() {
+    let server = StubServer::start(vec![]);
+    let provider = NagerProvider::new("XX").with_base_url(leak(server.url()));
+    let mut client = HttpClient::new(&StdTcp, &StdDns);
+    let year = block_on(provider.fetch_year_days_off(&mut client, 2025, WeekConfig::ISO));
+    assert_eq!(year.unwrap(), None);
+}
+
+#[test]
+fn unreachable_server_fails() {
+    let provider = NagerProvider::new("DE").with_base_url(leak(closed_port_url()));
+    let mut client = HttpClient::new(&StdTcp, &StdDns);
+    let month = MonthDate::new(2025, Month::May);
+    let year = block_on(provider.fetch_year_days_off(&mut client, 2025, WeekConfig::ISO));
+    assert!(year.is_err());
+    assert_eq!(
+        provider.offline_days_off(month, WeekConfig::ISO),
+        weekends_mask(month, WeekConfig::ISO)
+    );
+}
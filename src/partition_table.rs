@@ -0,0 +1,145 @@
+//! Partitions of the flash, from the ESP-IDF partition table written by `espflash`
+//!
+//! The table is at [`TABLE_OFFSET`] and is made of 32-byte entries, in little endian:
+//!
+//! | Offset | Size | Field                   |
+//! |--------|------|-------------------------|
+//! | 0      | 2    | Magic, `0xAA 0x50`      |
+//! | 2      | 1    | Type, like [`DATA`]     |
+//! | 3      | 1    | Subtype                 |
+//! | 4      | 4    | Offset of the partition |
+//! | 8      | 4    | Size of the partition   |
+//! | 12     | 16   | Label, zero padded      |
+//! | 28     | 4    | Flags                   |
+//!
+//! The entries end at an MD5 entry or at erased flash.
+
+use core::ops::Range;
+
+use embedded_storage::nor_flash::ReadNorFlash;
+
+/// Flash address of the partition table
+pub const TABLE_OFFSET: u32 = 0x8000;
+/// Most bytes the partition table takes
+pub const TABLE_LEN: u32 = 0xC00;
+/// Type of the data partitions
+pub const DATA: u8 = 0x01;
+
+const ENTRY_LEN: usize = 32;
+const MAGIC: [u8; 2] = [0xAA, 0x50];
+
+/// Find the flash addresses of the partition of `kind` and `subtype` labelled `label`
+///
+/// `None` when the table has no such partition, or there's no valid table.
+pub fn find<F: ReadNorFlash>(
+    flash: &mut F,
+    kind: u8,
+    subtype: u8,
+    label: &str,
+) -> Result<Option<Range<u32>>, F::Error> {
+    let mut entry = [0; ENTRY_LEN];
+    for offset in (TABLE_OFFSET..TABLE_OFFSET + TABLE_LEN).step_by(ENTRY_LEN) {
+        flash.read(offset, &mut entry)?;
+        // Anything else is the MD5 entry or the end of the table
+        if entry[0..2] != MAGIC {
+            return Ok(None);
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(entry[offset..offset + 4].try_into().unwrap());
+        let name = &entry[12..28];
+        let name_len = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+        if entry[2] == kind && entry[3] == subtype && &name[..name_len] == label.as_bytes() {
+            let start = u32_at(4);
+            return Ok(Some(start..start + u32_at(8)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    use super::{DATA, TABLE_OFFSET, find};
+
+    /// Flash with only a partition table, at [`TABLE_OFFSET`]
+    struct TableFlash(Vec<u8>);
+
+    impl TableFlash {
+        fn new(entries: &[(u8, u8, u32, u32, &str)]) -> Self {
+            let mut data = Vec::new();
+            for &(kind, subtype, offset, size, label) in entries {
+                data.extend([0xAA, 0x50, kind, subtype]);
+                data.extend(offset.to_le_bytes());
+                data.extend(size.to_le_bytes());
+                let mut name = [0; 16];
+                name[..label.len()].copy_from_slice(label.as_bytes());
+                data.extend(name);
+                data.extend([0; 4]);
+            }
+            // The MD5 entry
+            data.extend([0xEB, 0xEB]);
+            data.resize(0xC00, 0xFF);
+            Self(data)
+        }
+    }
+
+    impl ErrorType for TableFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for TableFlash {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = (offset - TABLE_OFFSET) as usize;
+            let data = self.0.get(offset..offset + bytes.len());
+            bytes.copy_from_slice(data.ok_or(NorFlashErrorKind::OutOfBounds)?);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            TABLE_OFFSET as usize + self.0.len()
+        }
+    }
+
+    fn partitions_csv() -> TableFlash {
+        TableFlash::new(&[
+            (DATA, 0x02, 0x9000, 0x6000, "nvs"),
+            (DATA, 0x01, 0xf000, 0x1000, "phy_init"),
+            (0x00, 0x00, 0x1_0000, 0x3e_0000, "factory"),
+            (DATA, 0x40, 0x3f_0000, 0x1_0000, "calendar"),
+        ])
+    }
+
+    #[test]
+    fn finds_the_calendar_partition() {
+        let mut flash = partitions_csv();
+        assert_eq!(
+            find(&mut flash, DATA, 0x40, "calendar"),
+            Ok(Some(0x3f_0000..0x40_0000))
+        );
+        assert_eq!(
+            find(&mut flash, DATA, 0x02, "nvs"),
+            Ok(Some(0x9000..0xf000))
+        );
+    }
+
+    #[test]
+    fn needs_the_type_subtype_and_label_to_match() {
+        let mut flash = partitions_csv();
+        assert_eq!(find(&mut flash, DATA, 0x41, "calendar"), Ok(None));
+        assert_eq!(find(&mut flash, 0x00, 0x40, "calendar"), Ok(None));
+        assert_eq!(find(&mut flash, DATA, 0x40, "calenda"), Ok(None));
+        assert_eq!(find(&mut flash, DATA, 0x40, "calendars"), Ok(None));
+    }
+
+    #[test]
+    fn finds_nothing_without_a_table() {
+        let mut flash = TableFlash(vec![0xFF; 0xC00]);
+        assert_eq!(find(&mut flash, DATA, 0x40, "calendar"), Ok(None));
+    }
+}
@@ -0,0 +1,488 @@
+//! Time zones given by POSIX TZ strings, like `CET-1CEST,M3.5.0,M10.5.0/3`
+//!
+//! A TZ string has the standard time of the zone and, optionally, the daylight saving time with
+//! the rules of the yearly changes between them. That's a few bytes instead of the whole tz
+//! database, and it's enough for the current and future times of almost all zones. The TZ strings
+//! of the zones are the last lines of their files in `/usr/share/zoneinfo`.
+//!
+//! The format is `std offset [dst [offset] [,start[/time],end[/time]]]`:
+//!
+//! - `std` and `dst` are the abbreviations, of at least three letters or quoted like `<+03>`.
+//! - The offsets are `[+|-]hh[:mm[:ss]]` **west** of UTC, so `CET-1` is an hour ahead of UTC. The
+//!   DST offset is an hour ahead of the standard one by default.
+//! - The dates are `Mm.w.d` for the day `d` (0 is Sunday) of the week `w` (5 is the last one) of
+//!   the month `m`, `Jn` for the day `n` of the year from 1 without February 29, or `n` for the day
+//!   of the year from 0.
+//! - The times are the local wall times of the changes, `[+|-]hh[:mm[:ss]]` up to 167 hours, 2:00
+//!   by default.
+//!
+//! Without the rules the US ones, `M3.2.0,M11.1.0`, are used like glibc does.
+
+use core::{fmt, str::FromStr};
+
+use chrono::{
+    Datelike, Days, FixedOffset, MappedLocalTime, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+    TimeDelta, TimeZone, Weekday,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Abbreviations are cut to this many bytes, the longest in use have 6
+pub const MAX_ABBR_LEN: usize = 8;
+
+const DEFAULT_TRANSITION_TIME: i32 = 2 * 3600;
+const MAX_TRANSITION_TIME: i32 = 167 * 3600;
+/// Offsets are parsed up to a day, but must be less than that, like a chrono `FixedOffset`
+const MAX_OFFSET: i32 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PosixTzError {
+    /// An abbreviation is missing, too short or has invalid characters
+    InvalidAbbreviation,
+    InvalidOffset,
+    InvalidRule,
+    /// There is something after the end rule
+    TrailingData,
+}
+
+/// Time zone of a POSIX TZ string, see the [module docs](self) for the format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PosixTz {
+    std: ZoneType,
+    dst: Option<Dst>,
+}
+
+/// Standard or daylight saving time of a zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ZoneType {
+    abbr: Abbreviation,
+    /// Seconds east of UTC, like chrono
+    utc_offset: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Dst {
+    zone: ZoneType,
+    start: Transition,
+    end: Transition,
+}
+
+/// Yearly change between the standard and daylight saving times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    date: RuleDate,
+    /// Seconds since the local midnight of the date, in the time before the change
+    time: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleDate {
+    /// `Jn`, 1 to 365, February 29 is never counted
+    Julian1(u16),
+    /// `n`, 0 to 365, February 29 is counted
+    Julian0(u16),
+    /// `Mm.w.d`, the week 5 is the last one
+    MonthWeekDay {
+        month: u8,
+        week: u8,
+        weekday: Weekday,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Abbreviation {
+    bytes: [u8; MAX_ABBR_LEN],
+    len: u8,
+}
+
+impl PosixTz {
+    pub const UTC: Self = Self {
+        std: ZoneType {
+            abbr: Abbreviation {
+                bytes: *b"UTC\0\0\0\0\0",
+                len: 3,
+            },
+            utc_offset: 0,
+        },
+        dst: None,
+    };
+
+    /// Check if the zone has daylight saving time
+    pub const fn has_dst(&self) -> bool {
+        self.dst.is_some()
+    }
+
+    /// Check if daylight saving time is in effect at the `utc` time
+    pub fn is_dst_at(&self, utc: &NaiveDateTime) -> bool {
+        let Some(dst) = self.dst else {
+            return false;
+        };
+        // The changes of the neighbouring years are also needed, the rules can place them past the
+        // end of the year
+        let mut last: Option<(NaiveDateTime, bool)> = None;
+        for year in utc.year() - 1..=utc.year() + 1 {
+            let changes = [
+                (dst.start.utc(year, self.std.utc_offset), true),
+                (dst.end.utc(year, dst.zone.utc_offset), false),
+            ];
+            for (at, is_dst) in changes {
+                if let Some(at) = at
+                    && at <= *utc
+                    // DST all year ends at the same time as it starts again
+                    && last.is_none_or(|(last_at, _)| at > last_at || (at == last_at && is_dst))
+                {
+                    last = Some((at, is_dst));
+                }
+            }
+        }
+        last.is_some_and(|(_, is_dst)| is_dst)
+    }
+
+    const fn offset(&self, is_dst: bool) -> PosixOffset {
+        PosixOffset { tz: *self, is_dst }
+    }
+
+    const fn zone_type(&self, is_dst: bool) -> &ZoneType {
+        match &self.dst {
+            Some(dst) if is_dst => &dst.zone,
+            _ => &self.std,
+        }
+    }
+}
+
+impl FromStr for PosixTz {
+    type Err = PosixTzError;
+
+    fn from_str(s: &str) -> Result<Self, PosixTzError> {
+        let mut parser = Parser {
+            input: s.as_bytes(),
+            pos: 0,
+        };
+        let std = ZoneType {
+            abbr: parser.abbreviation()?,
+            utc_offset: checked_offset(-parser.offset()?)?,
+        };
+        if parser.is_done() {
+            return Ok(Self { std, dst: None });
+        }
+        let abbr = parser.abbreviation()?;
+        let utc_offset = match parser.peek() {
+            Some(b'+' | b'-' | b'0'..=b'9') => -parser.offset()?,
+            _ => std.utc_offset + 3600,
+        };
+        let utc_offset = checked_offset(utc_offset)?;
+        let (start, end) = if parser.is_done() {
+            let rule = |month| Transition {
+                date: RuleDate::MonthWeekDay {
+                    month,
+                    week: if month == 3 { 2 } else { 1 },
+                    weekday: Weekday::Sun,
+                },
+                time: DEFAULT_TRANSITION_TIME,
+            };
+            (rule(3), rule(11))
+        } else {
+            parser.expect(b',')?;
+            let start = parser.transition()?;
+            parser.expect(b',')?;
+            (start, parser.transition()?)
+        };
+        if !parser.is_done() {
+            return Err(PosixTzError::TrailingData);
+        }
+        Ok(Self {
+            std,
+            dst: Some(Dst {
+                zone: ZoneType { abbr, utc_offset },
+                start,
+                end,
+            }),
+        })
+    }
+}
+
+/// Check that an offset is less than a day, the default DST offset of a standard offset of 23 hours
+/// east of UTC is a day already
+fn checked_offset(offset: i32) -> Result<i32, PosixTzError> {
+    (offset.abs() < MAX_OFFSET)
+        .then_some(offset)
+        .ok_or(PosixTzError::InvalidOffset)
+}
+
+impl TimeZone for PosixTz {
+    type Offset = PosixOffset;
+
+    fn from_offset(offset: &PosixOffset) -> Self {
+        offset.tz
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<PosixOffset> {
+        // Any offset in effect on the date will do, the same one as chrono-tz picks
+        let last_second = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+        let earliest = self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN));
+        let latest = self.offset_from_local_datetime(&local.and_time(last_second));
+        match (earliest, latest) {
+            (res @ MappedLocalTime::Single(_), _) | (_, res @ MappedLocalTime::Single(_)) => res,
+            (MappedLocalTime::Ambiguous(offset, _), _)
+            | (_, MappedLocalTime::Ambiguous(offset, _)) => MappedLocalTime::Single(offset),
+            (MappedLocalTime::None, MappedLocalTime::None) => MappedLocalTime::None,
+        }
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> MappedLocalTime<PosixOffset> {
+        if self.dst.is_none() {
+            return MappedLocalTime::Single(self.offset(false));
+        }
+        // The local time exists in a zone type when it's in effect at the matching UTC time
+        let valid = [false, true].map(|is_dst| {
+            let utc_offset = self.zone_type(is_dst).utc_offset;
+            local
+                .checked_sub_signed(TimeDelta::seconds(utc_offset.into()))
+                .is_some_and(|utc| self.is_dst_at(&utc) == is_dst)
+        });
+        let std = self.offset(false);
+        let dst = self.offset(true);
+        match valid {
+            [false, false] => MappedLocalTime::None,
+            [true, false] => MappedLocalTime::Single(std),
+            [false, true] => MappedLocalTime::Single(dst),
+            // The earliest time is the one further ahead of UTC
+            [true, true] if dst.fix() == std.fix() => MappedLocalTime::Single(std),
+            [true, true] if dst.fix().local_minus_utc() > std.fix().local_minus_utc() => {
+                MappedLocalTime::Ambiguous(dst, std)
+            }
+            [true, true] => MappedLocalTime::Ambiguous(std, dst),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> PosixOffset {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> PosixOffset {
+        self.offset(self.is_dst_at(utc))
+    }
+}
+
+/// Offset of a [`PosixTz`], shown as its abbreviation
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PosixOffset {
+    tz: PosixTz,
+    is_dst: bool,
+}
+
+impl PosixOffset {
+    pub fn abbreviation(&self) -> &str {
+        self.tz.zone_type(self.is_dst).abbr.as_str()
+    }
+
+    pub const fn is_dst(&self) -> bool {
+        self.is_dst
+    }
+}
+
+impl Offset for PosixOffset {
+    fn fix(&self) -> FixedOffset {
+        // The offsets are checked when parsed
+        FixedOffset::east_opt(self.tz.zone_type(self.is_dst).utc_offset).unwrap()
+    }
+}
+
+impl fmt::Debug for PosixOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.abbreviation())
+    }
+}
+
+impl fmt::Display for PosixOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.abbreviation())
+    }
+}
+
+impl Transition {
+    /// Get the UTC time of the change in `year`, the local time is at `utc_offset` before it
+    fn utc(&self, year: i32, utc_offset: i32) -> Option<NaiveDateTime> {
+        let local = self.date.date(year)?.and_time(NaiveTime::MIN);
+        local.checked_add_signed(TimeDelta::seconds(i64::from(self.time - utc_offset)))
+    }
+}
+
+impl RuleDate {
+    fn date(&self, year: i32) -> Option<NaiveDate> {
+        let jan_1 = NaiveDate::from_yo_opt(year, 1)?;
+        match *self {
+            Self::Julian1(day) => {
+                let leap_day = jan_1.leap_year() && day >= 60;
+                jan_1.checked_add_days(Days::new(u64::from(day) - 1 + u64::from(leap_day)))
+            }
+            Self::Julian0(day) => jan_1.checked_add_days(Days::new(day.into())),
+            Self::MonthWeekDay {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = NaiveDate::from_weekday_of_month_opt(year, month.into(), weekday, 1)?;
+                // Not every month has a fifth one, the week 5 is the last one
+                (1..week)
+                    .rev()
+                    .map(|n| first + Days::new(7 * u64::from(n)))
+                    .find(|date| date.month() == first.month())
+                    .or(Some(first))
+            }
+        }
+    }
+}
+
+impl Abbreviation {
+    fn as_str(&self) -> &str {
+        // Only ASCII is parsed into it
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Abbreviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), PosixTzError> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(PosixTzError::InvalidRule)
+        }
+    }
+
+    /// Parse letters, or letters, digits and signs in angle brackets
+    fn abbreviation(&mut self) -> Result<Abbreviation, PosixTzError> {
+        let quoted = self.eat(b'<');
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            let valid = byte.is_ascii_alphabetic()
+                || (quoted && (byte.is_ascii_digit() || byte == b'+' || byte == b'-'));
+            if !valid {
+                break;
+            }
+            self.pos += 1;
+        }
+        let abbr = &self.input[start..self.pos];
+        if (quoted && !self.eat(b'>')) || abbr.len() < 3 {
+            return Err(PosixTzError::InvalidAbbreviation);
+        }
+        let len = abbr.len().min(MAX_ABBR_LEN);
+        let mut bytes = [0; MAX_ABBR_LEN];
+        bytes[..len].copy_from_slice(&abbr[..len]);
+        Ok(Abbreviation {
+            bytes,
+            len: len as u8,
+        })
+    }
+
+    /// Parse an offset west of UTC, in seconds
+    fn offset(&mut self) -> Result<i32, PosixTzError> {
+        self.signed_time(MAX_OFFSET)
+            .ok_or(PosixTzError::InvalidOffset)
+    }
+
+    fn transition(&mut self) -> Result<Transition, PosixTzError> {
+        let date = self.rule_date().ok_or(PosixTzError::InvalidRule)?;
+        let time = if self.eat(b'/') {
+            self.signed_time(MAX_TRANSITION_TIME)
+                .ok_or(PosixTzError::InvalidRule)?
+        } else {
+            DEFAULT_TRANSITION_TIME
+        };
+        Ok(Transition { date, time })
+    }
+
+    fn rule_date(&mut self) -> Option<RuleDate> {
+        if self.eat(b'J') {
+            let day = self.number(3)?;
+            return (1..=365)
+                .contains(&day)
+                .then_some(RuleDate::Julian1(day as u16));
+        }
+        if self.eat(b'M') {
+            let month = self.number(2)?;
+            if !self.eat(b'.') {
+                return None;
+            }
+            let week = self.number(1)?;
+            if !self.eat(b'.') {
+                return None;
+            }
+            let weekday = self.number(1)?;
+            if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                return None;
+            }
+            return Some(RuleDate::MonthWeekDay {
+                month: month as u8,
+                week: week as u8,
+                weekday: Weekday::try_from(((weekday + 6) % 7) as u8).ok()?,
+            });
+        }
+        let day = self.number(3)?;
+        (day <= 365).then_some(RuleDate::Julian0(day as u16))
+    }
+
+    /// Parse `[+|-]hh[:mm[:ss]]` into seconds, up to `max` either way
+    fn signed_time(&mut self, max: i32) -> Option<i32> {
+        let sign = if self.eat(b'-') {
+            -1
+        } else {
+            self.eat(b'+');
+            1
+        };
+        let mut secs = self.number(3)? * 3600;
+        for unit in [60, 1] {
+            if !self.eat(b':') {
+                break;
+            }
+            let value = self.number(2)?;
+            if value > 59 {
+                return None;
+            }
+            secs += value * unit;
+        }
+        (secs <= max).then_some(sign * secs)
+    }
+
+    /// Parse up to `max_digits` decimal digits, at least one
+    fn number(&mut self, max_digits: usize) -> Option<i32> {
+        let start = self.pos;
+        let mut value = 0;
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            if self.pos - start == max_digits {
+                return None;
+            }
+            value = value * 10 + i32::from(digit - b'0');
+            self.pos += 1;
+        }
+        (self.pos > start).then_some(value)
+    }
+}
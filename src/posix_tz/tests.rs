@@ -0,0 +1,254 @@
+use chrono::{
+    Datelike, MappedLocalTime, NaiveDate, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc, Weekday,
+};
+use chrono_tz::{OffsetName, Tz};
+
+use super::{PosixTz, PosixTzError, RuleDate};
+
+/// Zones of the tz database with the TZ strings of their current rules
+const ZONES: &[(Tz, &str)] = &[
+    (Tz::Europe__Moscow, "MSK-3"),
+    (Tz::Europe__Berlin, "CET-1CEST,M3.5.0,M10.5.0/3"),
+    (Tz::Europe__London, "GMT0BST,M3.5.0/1,M10.5.0"),
+    (Tz::Europe__Dublin, "IST-1GMT0,M10.5.0,M3.5.0/1"),
+    (Tz::Europe__Lisbon, "WET0WEST,M3.5.0/1,M10.5.0"),
+    (Tz::Europe__Kyiv, "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    (Tz::Europe__Chisinau, "EET-2EEST,M3.5.0,M10.5.0/3"),
+    (Tz::Europe__Minsk, "<+03>-3"),
+    (Tz::Europe__Istanbul, "<+03>-3"),
+    (Tz::Europe__Samara, "<+04>-4"),
+    (Tz::Asia__Tokyo, "JST-9"),
+    (Tz::Asia__Kolkata, "IST-5:30"),
+    (Tz::Asia__Kathmandu, "<+0545>-5:45"),
+    (Tz::Asia__Tehran, "<+0330>-3:30"),
+    (Tz::Asia__Jerusalem, "IST-2IDT,M3.4.4/26,M10.5.0"),
+    (Tz::America__New_York, "EST5EDT,M3.2.0,M11.1.0"),
+    (Tz::America__Chicago, "CST6CDT,M3.2.0,M11.1.0"),
+    (Tz::America__Denver, "MST7MDT,M3.2.0,M11.1.0"),
+    (Tz::America__Phoenix, "MST7"),
+    (Tz::America__Los_Angeles, "PST8PDT,M3.2.0,M11.1.0"),
+    (Tz::America__Anchorage, "AKST9AKDT,M3.2.0,M11.1.0"),
+    (Tz::America__Halifax, "AST4ADT,M3.2.0,M11.1.0"),
+    (Tz::America__St_Johns, "NST3:30NDT,M3.2.0,M11.1.0"),
+    (Tz::America__Havana, "CST5CDT,M3.2.0/0,M11.1.0/1"),
+    (Tz::America__Santiago, "<-04>4<-03>,M9.1.6/24,M4.1.6/24"),
+    (Tz::America__Sao_Paulo, "<-03>3"),
+    (Tz::America__Mexico_City, "CST6"),
+    (Tz::Pacific__Honolulu, "HST10"),
+    (Tz::Pacific__Auckland, "NZST-12NZDT,M9.5.0,M4.1.0/3"),
+    (
+        Tz::Pacific__Chatham,
+        "<+1245>-12:45<+1345>,M9.5.0/2:45,M4.1.0/3:45",
+    ),
+    (Tz::Pacific__Kiritimati, "<+14>-14"),
+    (Tz::Australia__Sydney, "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    (Tz::Australia__Adelaide, "ACST-9:30ACDT,M10.1.0,M4.1.0/3"),
+    (
+        Tz::Australia__Lord_Howe,
+        "<+1030>-10:30<+11>-11,M10.1.0,M4.1.0",
+    ),
+    (Tz::Australia__Brisbane, "AEST-10"),
+    (Tz::Africa__Cairo, "EET-2EEST,M4.5.5/0,M10.5.4/24"),
+    (Tz::Atlantic__Azores, "<-01>1<+00>,M3.5.0/0,M10.5.0/1"),
+    (Tz::Antarctica__Troll, "<+00>0<+02>-2,M3.5.0/1,M10.5.0/3"),
+    (Tz::UTC, "UTC0"),
+];
+
+const YEARS: core::ops::RangeInclusive<i32> = 2026..=2037;
+
+fn parse(tz: &str) -> PosixTz {
+    tz.parse().unwrap()
+}
+
+fn datetime(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(hour, min, 0)
+        .unwrap()
+}
+
+/// Map a local time to the fixed offsets of its UTC times
+fn fixed<O: Offset>(mapped: MappedLocalTime<O>) -> MappedLocalTime<i32> {
+    mapped.map(|offset| offset.fix().local_minus_utc())
+}
+
+#[test]
+fn utc_to_local_matches_tz_database() {
+    for &(zone, posix) in ZONES {
+        let tz = parse(posix);
+        let mut utc = datetime(*YEARS.start(), 1, 1, 0, 0);
+        while utc.year() <= *YEARS.end() {
+            let expected = zone.offset_from_utc_datetime(&utc);
+            let offset = tz.offset_from_utc_datetime(&utc);
+            assert_eq!(offset.fix(), expected.fix(), "{zone} at {utc} UTC");
+            if let Some(abbr) = expected.abbreviation() {
+                assert_eq!(offset.abbreviation(), abbr, "{zone} at {utc} UTC");
+            }
+            utc += TimeDelta::minutes(15);
+        }
+    }
+}
+
+#[test]
+fn local_to_utc_matches_tz_database() {
+    for &(zone, posix) in ZONES {
+        let tz = parse(posix);
+        let mut local = datetime(*YEARS.start(), 1, 1, 0, 0);
+        while local.year() <= *YEARS.end() {
+            assert_eq!(
+                fixed(tz.offset_from_local_datetime(&local)),
+                fixed(zone.offset_from_local_datetime(&local)),
+                "{zone} at {local}"
+            );
+            local += TimeDelta::minutes(15);
+        }
+    }
+}
+
+#[test]
+fn local_dates_match_tz_database() {
+    for &(zone, posix) in ZONES {
+        let tz = parse(posix);
+        for year in YEARS {
+            for date in NaiveDate::from_yo_opt(year, 1).unwrap().iter_days() {
+                if date.year() != year {
+                    break;
+                }
+                assert_eq!(
+                    fixed(tz.offset_from_local_date(&date)),
+                    fixed(zone.offset_from_local_date(&date)),
+                    "{zone} on {date}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn converts_wall_times() {
+    let tz = parse("CET-1CEST,M3.5.0,M10.5.0/3");
+    let summer = tz
+        .from_local_datetime(&datetime(2026, 7, 1, 12, 0))
+        .unwrap();
+    assert_eq!(summer.naive_utc(), datetime(2026, 7, 1, 10, 0));
+    assert_eq!(summer.to_string(), "2026-07-01 12:00:00 CEST");
+
+    // Skipped and repeated hours
+    let skipped = tz.from_local_datetime(&datetime(2026, 3, 29, 2, 30));
+    assert_eq!(skipped, MappedLocalTime::None);
+    let repeated = tz.from_local_datetime(&datetime(2026, 10, 25, 2, 30));
+    let MappedLocalTime::Ambiguous(earliest, latest) = repeated else {
+        panic!("{repeated:?}");
+    };
+    assert_eq!(earliest.offset().abbreviation(), "CEST");
+    assert_eq!(latest.offset().abbreviation(), "CET");
+    assert_eq!(latest - earliest, TimeDelta::hours(1));
+}
+
+#[test]
+fn parses_offsets_and_abbreviations() {
+    let tz = parse("<+0545>-5:45");
+    let offset = tz.offset_from_utc_datetime(&datetime(2026, 1, 1, 0, 0));
+    assert_eq!(offset.fix().local_minus_utc(), 5 * 3600 + 45 * 60);
+    assert_eq!(offset.abbreviation(), "+0545");
+    assert!(!tz.has_dst());
+
+    let tz = parse("XYZ+1:02:03");
+    let offset = tz.offset_from_utc_datetime(&datetime(2026, 1, 1, 0, 0));
+    assert_eq!(offset.fix().local_minus_utc(), -3723);
+
+    assert_eq!(parse("UTC0"), PosixTz::UTC);
+}
+
+#[test]
+fn dst_defaults_to_an_hour_ahead_and_us_rules() {
+    let tz = parse("EST5EDT");
+    assert_eq!(tz, parse("EST5EDT4,M3.2.0/2,M11.1.0/2"));
+    let offset = tz.offset_from_utc_datetime(&datetime(2026, 7, 1, 0, 0));
+    assert!(offset.is_dst());
+    assert_eq!(offset.fix().local_minus_utc(), -4 * 3600);
+}
+
+#[test]
+fn dst_all_year() {
+    // From January 1 0:00 to December 31 25:00 in the DST offset, so there's no change
+    let tz = parse("EST5EDT,0/0,J365/25");
+    for (month, day) in [(1, 1), (2, 29), (7, 1), (12, 31)] {
+        let utc = datetime(2028, month, day, 12, 0);
+        assert_eq!(tz.offset_from_utc_datetime(&utc).abbreviation(), "EDT");
+        assert!(tz.from_local_datetime(&utc).single().is_some());
+    }
+}
+
+#[test]
+fn rule_dates() {
+    let date = |rule: RuleDate, year| rule.date(year).unwrap();
+    let ymd = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    // February 29 isn't counted by Jn but is by n
+    assert_eq!(date(RuleDate::Julian1(60), 2028), ymd(2028, 3, 1));
+    assert_eq!(date(RuleDate::Julian0(59), 2028), ymd(2028, 2, 29));
+    assert_eq!(date(RuleDate::Julian1(365), 2028), ymd(2028, 12, 31));
+    let last_sunday = RuleDate::MonthWeekDay {
+        month: 3,
+        week: 5,
+        weekday: Weekday::Sun,
+    };
+    assert_eq!(date(last_sunday, 2026), ymd(2026, 3, 29));
+    let second_sunday = RuleDate::MonthWeekDay {
+        month: 3,
+        week: 2,
+        weekday: Weekday::Sun,
+    };
+    assert_eq!(date(second_sunday, 2026), ymd(2026, 3, 8));
+}
+
+#[test]
+fn rejects_invalid_strings() {
+    let cases = [
+        ("", PosixTzError::InvalidAbbreviation),
+        ("CE-1", PosixTzError::InvalidAbbreviation),
+        ("<+03-3", PosixTzError::InvalidAbbreviation),
+        ("CET", PosixTzError::InvalidOffset),
+        ("CET-25", PosixTzError::InvalidOffset),
+        ("CET-1:60", PosixTzError::InvalidOffset),
+        ("CET-1CEST,M3.5.0", PosixTzError::InvalidRule),
+        ("CET-1CEST,M13.5.0,M10.5.0", PosixTzError::InvalidRule),
+        ("CET-1CEST,M3.6.0,M10.5.0", PosixTzError::InvalidRule),
+        ("CET-1CEST,M3.5.7,M10.5.0", PosixTzError::InvalidRule),
+        ("CET-1CEST,J0,M10.5.0", PosixTzError::InvalidRule),
+        ("CET-1CEST,M3.5.0/168,M10.5.0", PosixTzError::InvalidRule),
+        ("CET-1CEST,M3.5.0,M10.5.0/3,", PosixTzError::TrailingData),
+    ];
+    for (tz, error) in cases {
+        assert_eq!(tz.parse::<PosixTz>(), Err(error), "{tz:?}");
+    }
+}
+
+#[test]
+fn rejects_offsets_of_a_day() {
+    for tz in [
+        "XXX-24",
+        "XXX24",
+        "XXX-23YYY",
+        "XXX-1YYY-24",
+        "XXX-1YYY24:00:00",
+    ] {
+        assert_eq!(
+            tz.parse::<PosixTz>(),
+            Err(PosixTzError::InvalidOffset),
+            "{tz:?}"
+        );
+    }
+    // Just under a day is a valid chrono offset
+    let tz: PosixTz = "XXX-23:59:59YYY23:59:59".parse().unwrap();
+    let at = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(
+        at.with_timezone(&tz).offset().fix().local_minus_utc(),
+        86_399
+    );
+    let summer = Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap();
+    assert_eq!(
+        summer.with_timezone(&tz).offset().fix().local_minus_utc(),
+        -86_399
+    );
+}
@@ -0,0 +1,108 @@
+//! Estimate of the battery charge the calendar uses a day when it deep sleeps between refreshes
+//!
+//! The day is spent in deep sleep except for the wake-ups. A refresh boots, connects the Wi-Fi and
+//! fetches the data, then stops the Wi-Fi while the e-paper updates. A time synchronization that
+//! doesn't fall on a refresh boots and connects the Wi-Fi for the NTP requests. A temperature
+//! reading boots, reads the RTC and goes back to sleep without the Wi-Fi.
+//!
+//! With [`DEVKIT`], a refresh and a synchronization a day take about 4.1 mAh, and a temperature
+//! reading every half an hour adds 0.2 mAh, so a 2000 mAh cell lasts over a year. Most of it is the
+//! sleep current of the DS3231, which is powered from the supply of the board so its alarm can wake
+//! the ESP32. It's far less from its backup battery input, but the alarm doesn't work on that.
+
+/// Seconds in a day
+const DAY_SECS: u32 = 24 * 3600;
+
+/// Currents of the board in µA and the durations of the wake-ups in seconds
+#[derive(Debug, Clone, Copy)]
+pub struct PowerProfile {
+    /// Awake with the Wi-Fi on
+    pub wifi_ua: u32,
+    /// Awake with the Wi-Fi off
+    pub idle_ua: u32,
+    /// The e-paper while it updates, on top of the ESP32
+    pub epd_ua: u32,
+    /// The whole board in deep sleep: the ESP32, the DS3231, the sleeping e-paper and the regulator
+    pub sleep_ua: u32,
+    /// Awake with the Wi-Fi on for a refresh
+    pub refresh_secs: u32,
+    /// A full update of the e-paper
+    pub epd_update_secs: u32,
+    /// Awake for a time synchronization, 4 NTP requests 2 s apart after connecting
+    pub sync_secs: u32,
+    /// Awake without the Wi-Fi for a temperature reading, in ms
+    pub reading_ms: u32,
+}
+
+/// ESP32-S3 module with a low quiescent current regulator, typical datasheet currents at 3.3 V
+///
+/// The AMS1117 regulator of most devkits takes about 5 mA by itself, 30 times the sleep current.
+pub const DEVKIT: PowerProfile = PowerProfile {
+    wifi_ua: 100_000,
+    idle_ua: 40_000,
+    epd_ua: 8_000,
+    // 8 µA of the ESP32-S3, 110 µA of the DS3231, 1 µA of the e-paper and 11 µA of the regulator
+    sleep_ua: 130,
+    refresh_secs: 15,
+    epd_update_secs: 20,
+    sync_secs: 12,
+    reading_ms: 300,
+};
+
+impl PowerProfile {
+    /// Get the charge used a day in µAh, with `refreshes`, `syncs` and `readings` wake-ups
+    pub const fn daily_uah(&self, refreshes: u32, syncs: u32, readings: u32) -> u64 {
+        let refresh_secs = refreshes * (self.refresh_secs + self.epd_update_secs);
+        let reading_secs = readings * self.reading_ms / 1000;
+        let awake_secs = refresh_secs + syncs * self.sync_secs + reading_secs;
+        let sleep_secs = DAY_SECS.saturating_sub(awake_secs);
+        let micro_amp_secs = refreshes as u64 * self.refresh_secs as u64 * self.wifi_ua as u64
+            + refreshes as u64 * self.epd_update_secs as u64 * (self.idle_ua + self.epd_ua) as u64
+            + syncs as u64 * self.sync_secs as u64 * self.wifi_ua as u64
+            + readings as u64 * self.reading_ms as u64 * self.idle_ua as u64 / 1000
+            + sleep_secs as u64 * self.sleep_ua as u64;
+        micro_amp_secs / 3600
+    }
+
+    /// Get how many days a battery of `capacity_mah` lasts, with `refreshes`, `syncs` and
+    /// `readings` a day
+    pub const fn battery_days(
+        &self,
+        capacity_mah: u32,
+        refreshes: u32,
+        syncs: u32,
+        readings: u32,
+    ) -> u64 {
+        capacity_mah as u64 * 1000 / self.daily_uah(refreshes, syncs, readings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEVKIT, PowerProfile};
+
+    #[test]
+    fn daily_budget_of_devkit() {
+        assert_eq!(DEVKIT.daily_uah(1, 1, 0), 4134);
+        // Days without a synchronization, when they're a week apart
+        assert_eq!(DEVKIT.daily_uah(1, 0, 0), 3802);
+        assert_eq!(DEVKIT.battery_days(2000, 1, 1, 0), 483);
+        // A temperature reading every half an hour
+        assert_eq!(DEVKIT.daily_uah(1, 1, 48), 4294);
+        assert_eq!(DEVKIT.battery_days(2000, 1, 1, 48), 465);
+    }
+
+    #[test]
+    fn sleep_current_dominates() {
+        let always_awake = PowerProfile {
+            sleep_ua: DEVKIT.wifi_ua,
+            ..DEVKIT
+        };
+        assert_eq!(always_awake.daily_uah(0, 0, 0), 2_400_000);
+        let no_sleep_current = PowerProfile {
+            sleep_ua: 0,
+            ..DEVKIT
+        };
+        assert!(no_sleep_current.daily_uah(1, 1, 48) * 2 < DEVKIT.daily_uah(1, 1, 48));
+    }
+}
@@ -0,0 +1,250 @@
+//! When to refresh the calendar, at local times of the day
+//!
+//! The local times are resolved in the time zone day by day, so they follow the DST transitions. A
+//! time skipped by a transition is moved forward by the length of the skip, the way `mktime` does:
+//! a refresh at 00:00:05 on a day that starts at 01:00 happens at 01:00:05. A time repeated by a
+//! transition happens at its first occurrence only, so a day never gets the same refresh twice.
+
+use chrono::{
+    DateTime, MappedLocalTime, NaiveDateTime, NaiveTime, Offset, TimeDelta, TimeZone, Utc,
+};
+
+/// The offset before a skipped time is looked up this long before it, DST transitions are much
+/// further apart
+const BEFORE_GAP: TimeDelta = TimeDelta::days(1);
+
+/// Get the first of the local `times` of the day after `now`, in UTC
+///
+/// `None` when there are no times, or they're out of the range of the dates.
+pub fn next_refresh<Tz: TimeZone>(
+    now: &DateTime<Utc>,
+    times: &[NaiveTime],
+    tz: &Tz,
+) -> Option<DateTime<Utc>> {
+    let today = now.with_timezone(tz).date_naive();
+    // Yesterday's times can be moved forward past the start of today, and a day can be skipped
+    // whole by a change of the zone's offset
+    (-1..=2)
+        .filter_map(|days| today.checked_add_signed(TimeDelta::days(days)))
+        .flat_map(|date| times.iter().map(move |&time| date.and_time(time)))
+        .filter_map(|local| resolve(local, tz))
+        .filter(|at| at > now)
+        .min()
+}
+
+/// Get the instant of the `local` time in the zone, see the [module docs](self) for the skipped and
+/// repeated ones
+pub fn resolve<Tz: TimeZone>(local: NaiveDateTime, tz: &Tz) -> Option<DateTime<Utc>> {
+    match tz.from_local_datetime(&local) {
+        MappedLocalTime::Single(at) | MappedLocalTime::Ambiguous(at, _) => {
+            Some(at.with_timezone(&Utc))
+        }
+        MappedLocalTime::None => {
+            let before = tz
+                .offset_from_utc_datetime(&local.checked_sub_signed(BEFORE_GAP)?)
+                .fix();
+            Some(local.checked_sub_offset(before)?.and_utc())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use super::{next_refresh, resolve};
+    use crate::posix_tz::PosixTz;
+
+    const MIDNIGHT: NaiveTime = NaiveTime::from_hms_opt(0, 0, 5).unwrap();
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap()
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        local(y, m, d, h, min, s).and_utc()
+    }
+
+    fn time(h: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, min, 0).unwrap()
+    }
+
+    /// Follow the refreshes from `now` and get their local times
+    fn refreshes(
+        tz: &impl TimeZone,
+        times: &[NaiveTime],
+        mut now: DateTime<Utc>,
+        count: usize,
+    ) -> Vec<NaiveDateTime> {
+        (0..count)
+            .map(|_| {
+                now = next_refresh(&now, times, tz).unwrap();
+                now.with_timezone(tz).naive_local()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn daily_refresh_without_dst() {
+        let now = utc(2026, 10, 17, 9, 0, 0);
+        assert_eq!(
+            next_refresh(&now, &[MIDNIGHT], &Tz::Europe__Moscow),
+            Some(utc(2026, 10, 17, 21, 0, 5))
+        );
+        // Right at the refresh it's the next one
+        let at = utc(2026, 10, 17, 21, 0, 5);
+        assert_eq!(
+            next_refresh(&at, &[MIDNIGHT], &Tz::Europe__Moscow),
+            Some(utc(2026, 10, 18, 21, 0, 5))
+        );
+        assert_eq!(next_refresh(&now, &[], &Tz::Europe__Moscow), None);
+    }
+
+    #[test]
+    fn several_refreshes_a_day() {
+        let times = [time(18, 0), MIDNIGHT, time(6, 30)];
+        assert_eq!(
+            refreshes(&Tz::Europe__Moscow, &times, utc(2026, 10, 17, 9, 0, 0), 4),
+            [
+                local(2026, 10, 17, 18, 0, 0),
+                local(2026, 10, 18, 0, 0, 5),
+                local(2026, 10, 18, 6, 30, 0),
+                local(2026, 10, 18, 18, 0, 0),
+            ]
+        );
+        // The same time twice is one refresh
+        assert_eq!(
+            refreshes(
+                &Tz::Europe__Moscow,
+                &[MIDNIGHT, MIDNIGHT],
+                utc(2026, 10, 17, 9, 0, 0),
+                2
+            ),
+            [local(2026, 10, 18, 0, 0, 5), local(2026, 10, 19, 0, 0, 5)]
+        );
+    }
+
+    #[test]
+    fn skipped_midnight_moves_forward() {
+        // Havana springs forward from 00:00 to 01:00
+        let tz = Tz::America__Havana;
+        assert_eq!(
+            refreshes(&tz, &[MIDNIGHT], utc(2026, 3, 7, 12, 0, 0), 2),
+            [local(2026, 3, 8, 1, 0, 5), local(2026, 3, 9, 0, 0, 5)]
+        );
+        assert_eq!(
+            resolve(local(2026, 3, 8, 0, 0, 5), &tz),
+            Some(utc(2026, 3, 8, 5, 0, 5))
+        );
+    }
+
+    #[test]
+    fn repeated_midnight_refreshes_once() {
+        // Havana falls back from 01:00 to 00:00
+        let tz = Tz::America__Havana;
+        let now = utc(2026, 10, 31, 12, 0, 0);
+        let first = next_refresh(&now, &[MIDNIGHT], &tz).unwrap();
+        assert_eq!(first, utc(2026, 11, 1, 4, 0, 5));
+        // Not again at the second 00:00:05, an hour later
+        assert_eq!(
+            next_refresh(&first, &[MIDNIGHT], &tz),
+            Some(utc(2026, 11, 2, 5, 0, 5))
+        );
+    }
+
+    #[test]
+    fn skipped_and_repeated_times_in_the_night() {
+        let tz = Tz::Europe__Berlin;
+        let times = [time(2, 30)];
+        assert_eq!(
+            refreshes(&tz, &times, utc(2026, 3, 28, 12, 0, 0), 2),
+            [local(2026, 3, 29, 3, 30, 0), local(2026, 3, 30, 2, 30, 0)]
+        );
+        let first = next_refresh(&utc(2026, 10, 24, 12, 0, 0), &times, &tz).unwrap();
+        assert_eq!(first, utc(2026, 10, 25, 0, 30, 0));
+        assert_eq!(
+            next_refresh(&first, &times, &tz),
+            Some(utc(2026, 10, 26, 1, 30, 0))
+        );
+
+        // Lord Howe Island only skips half an hour
+        assert_eq!(
+            resolve(local(2026, 10, 4, 2, 15, 0), &Tz::Australia__Lord_Howe),
+            Some(utc(2026, 10, 3, 15, 45, 0))
+        );
+    }
+
+    #[test]
+    fn transitions_at_the_end_of_the_day() {
+        // Santiago changes at 24:00 of a Saturday
+        let tz = Tz::America__Santiago;
+        assert_eq!(
+            refreshes(&tz, &[MIDNIGHT], utc(2026, 9, 5, 12, 0, 0), 1),
+            [local(2026, 9, 6, 1, 0, 5)]
+        );
+        // 23:00 to 24:00 is repeated
+        let times = [time(23, 30)];
+        let first = next_refresh(&utc(2026, 4, 4, 12, 0, 0), &times, &tz).unwrap();
+        assert_eq!(first, utc(2026, 4, 5, 2, 30, 0));
+        assert_eq!(
+            next_refresh(&first, &times, &tz),
+            Some(utc(2026, 4, 6, 3, 30, 0))
+        );
+    }
+
+    #[test]
+    fn refreshes_every_day_once_across_the_year() {
+        let zones = [
+            Tz::Europe__Berlin,
+            Tz::America__Havana,
+            Tz::America__Santiago,
+            Tz::America__St_Johns,
+            Tz::Australia__Lord_Howe,
+            Tz::Pacific__Chatham,
+            Tz::Asia__Jerusalem,
+        ];
+        let times = [
+            MIDNIGHT,
+            time(1, 30),
+            time(2, 30),
+            time(12, 0),
+            time(23, 30),
+        ];
+        for tz in zones {
+            let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+            let now = resolve(start.and_time(MIDNIGHT), &tz).unwrap() - TimeDelta::seconds(1);
+            let all = refreshes(&tz, &times, now, 365 * times.len());
+            for (idx, (day, day_refreshes)) in
+                start.iter_days().zip(all.chunks(times.len())).enumerate()
+            {
+                // All the times of the day, even the skipped ones
+                let mut dates: Vec<_> = day_refreshes.iter().map(|at| at.date()).collect();
+                dates.dedup();
+                assert_eq!(dates, [day], "{tz} {idx}: {day_refreshes:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn posix_tz_like_tz_database() {
+        let zones = [
+            (Tz::America__Havana, "CST5CDT,M3.2.0/0,M11.1.0/1"),
+            (Tz::America__Santiago, "<-04>4<-03>,M9.1.6/24,M4.1.6/24"),
+            (Tz::Europe__Berlin, "CET-1CEST,M3.5.0,M10.5.0/3"),
+        ];
+        let times = [MIDNIGHT, time(2, 30), time(23, 30)];
+        for (tz, posix) in zones {
+            let posix: PosixTz = posix.parse().unwrap();
+            let mut now = utc(2026, 1, 1, 0, 0, 0);
+            for _ in 0..3 * 366 {
+                let next = next_refresh(&now, &times, &tz);
+                assert_eq!(next_refresh(&now, &times, &posix), next, "{tz} {now}");
+                now = next.unwrap();
+            }
+        }
+    }
+}
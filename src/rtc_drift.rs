@@ -0,0 +1,256 @@
+//! Drift of the DS3231 RTC, estimated from how far off it is at the NTP synchronizations
+//!
+//! The RTC is set at every synchronization, so the error it has at the next one is the drift over
+//! the time between them. The drift is compensated with the aging offset of the RTC, which changes
+//! the speed of its oscillator by about 0.1 ppm a step. Positive offsets slow it down.
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use heapless::Deque;
+
+/// Intervals between the synchronizations kept, the older ones are forgotten
+const MAX_INTERVALS: usize = 8;
+/// Shorter intervals are left out, the error measured is mostly noise over them
+const MIN_INTERVAL: TimeDelta = TimeDelta::hours(6);
+/// The drift is only estimated over this much time in total
+const MIN_TOTAL: TimeDelta = TimeDelta::hours(24);
+/// Faster drifts are not from the crystal, the RTC was stopped or set by someone else
+const MAX_DRIFT_PPB: i64 = 100_000;
+/// Change of the drift by a step of the aging offset, at 25 °C
+const PPB_PER_AGING_STEP: i64 = 100;
+/// Length of [`DriftEstimator::encode`]: the set time, the interval count and the intervals
+pub(crate) const ENCODED_LEN: usize = 8 + 1 + MAX_INTERVALS * 16;
+
+#[derive(Debug, Default)]
+pub struct DriftEstimator {
+    /// UTC time the RTC was last set to exactly
+    set_at: Option<NaiveDateTime>,
+    /// Lengths of the intervals and the errors of the RTC at their ends, in microseconds
+    intervals: Deque<(i64, i64), MAX_INTERVALS>,
+}
+
+impl DriftEstimator {
+    pub const fn new() -> Self {
+        Self {
+            set_at: None,
+            intervals: Deque::new(),
+        }
+    }
+
+    /// Remember that the RTC was set to the exact UTC time `at`
+    pub fn rtc_set(&mut self, at: NaiveDateTime) {
+        self.set_at = Some(at);
+    }
+
+    /// Record the `error` of the RTC, its time minus the true one, measured at the UTC time `at`
+    /// before it's set again
+    ///
+    /// Returns if the error was kept, it isn't when the interval since the RTC was set is too
+    /// short or the drift over it is implausible.
+    pub fn record(&mut self, at: NaiveDateTime, error: TimeDelta) -> bool {
+        let Some(set_at) = self.set_at.take() else {
+            return false;
+        };
+        let interval = at - set_at;
+        let (Some(interval_micros), Some(error_micros)) =
+            (interval.num_microseconds(), error.num_microseconds())
+        else {
+            return false;
+        };
+        if interval < MIN_INTERVAL || ppb(error_micros, interval_micros).abs() > MAX_DRIFT_PPB {
+            return false;
+        }
+        if self.intervals.is_full() {
+            self.intervals.pop_front();
+        }
+        // Can't be full, there was room made
+        self.intervals
+            .push_back((interval_micros, error_micros))
+            .unwrap();
+        true
+    }
+
+    /// Get the drift of the RTC in parts per billion, positive when it's fast
+    ///
+    /// The errors over all the intervals are averaged by their lengths, so the longer ones count
+    /// more.
+    pub fn drift_ppb(&self) -> Option<i64> {
+        let (interval, error) = self
+            .intervals
+            .iter()
+            .fold((0, 0), |(interval, error), &(i, e)| {
+                (interval + i, error + e)
+            });
+        let min_total = MIN_TOTAL.num_microseconds().unwrap_or_default();
+        (interval >= min_total).then(|| ppb(error, interval))
+    }
+
+    /// Get the aging offset that compensates the drift, `None` when `current` is right or the
+    /// drift isn't known yet
+    pub fn aging_offset(&self, current: i8) -> Option<i8> {
+        let drift = self.drift_ppb()?;
+        // Rounded to the nearest step
+        let steps = (drift + drift.signum() * PPB_PER_AGING_STEP / 2) / PPB_PER_AGING_STEP;
+        let offset = (i64::from(current) + steps).clamp(i8::MIN.into(), i8::MAX.into()) as i8;
+        (offset != current).then_some(offset)
+    }
+
+    /// Forget the drift measured so far, after the aging offset was changed
+    pub fn restart(&mut self) {
+        self.intervals.clear();
+    }
+
+    /// Write the state to bytes in little endian, the set time as microseconds since the Unix
+    /// epoch or [`i64::MIN`] when unknown
+    pub(crate) fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut buf = [0; ENCODED_LEN];
+        let set_at = self
+            .set_at
+            .map_or(i64::MIN, |at| at.and_utc().timestamp_micros());
+        buf[0..8].copy_from_slice(&set_at.to_le_bytes());
+        buf[8] = self.intervals.len() as u8;
+        for (idx, (interval, error)) in self.intervals.iter().enumerate() {
+            let offset = 9 + idx * 16;
+            buf[offset..offset + 8].copy_from_slice(&interval.to_le_bytes());
+            buf[offset + 8..offset + 16].copy_from_slice(&error.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Read back the state written by [`encode`](Self::encode), `None` if it's malformed
+    pub(crate) fn decode(buf: &[u8; ENCODED_LEN]) -> Option<Self> {
+        let i64_at =
+            |offset: usize| i64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
+        let set_at = match i64_at(0) {
+            i64::MIN => None,
+            micros => Some(DateTime::from_timestamp_micros(micros)?.naive_utc()),
+        };
+        let mut intervals = Deque::new();
+        for idx in 0..usize::from(buf[8]) {
+            let offset = 9 + idx * 16;
+            intervals
+                .push_back((i64_at(offset), i64_at(offset + 8)))
+                .ok()?;
+        }
+        Some(Self { set_at, intervals })
+    }
+}
+
+/// Get an error over an interval in parts per billion
+fn ppb(error_micros: i64, interval_micros: i64) -> i64 {
+    if interval_micros <= 0 {
+        return 0;
+    }
+    (i128::from(error_micros) * 1_000_000_000 / i128::from(interval_micros)) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+
+    use super::DriftEstimator;
+
+    fn start() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 5)
+            .unwrap()
+    }
+
+    /// Synchronize every `interval` from the start, the RTC drifts by `ppb` and is measured off by
+    /// the `noise` milliseconds in turn
+    fn synchronize(
+        estimator: &mut DriftEstimator,
+        ppb: i64,
+        interval: TimeDelta,
+        noise: &[i64],
+    ) -> Vec<bool> {
+        let mut at = start();
+        estimator.rtc_set(at);
+        let mut kept = Vec::new();
+        for &noise in noise {
+            at += interval;
+            let drift = interval.num_microseconds().unwrap() * ppb / 1_000_000_000;
+            let error = TimeDelta::microseconds(drift) + TimeDelta::milliseconds(noise);
+            kept.push(estimator.record(at, error));
+            estimator.rtc_set(at);
+        }
+        kept
+    }
+
+    #[test]
+    fn estimates_drift_over_daily_syncs() {
+        let mut estimator = DriftEstimator::new();
+        // 2.3 ppm fast is 199 ms a day
+        let kept = synchronize(&mut estimator, 2300, TimeDelta::days(1), &[3, -4, 2, -1]);
+        assert_eq!(kept, [true; 4]);
+        let drift = estimator.drift_ppb().unwrap();
+        assert!((2280..=2320).contains(&drift), "{drift}");
+        assert_eq!(estimator.aging_offset(0), Some(23));
+        assert_eq!(estimator.aging_offset(-10), Some(13));
+        assert_eq!(estimator.aging_offset(23), Some(46));
+    }
+
+    #[test]
+    fn slow_rtc_gets_negative_offset() {
+        let mut estimator = DriftEstimator::new();
+        synchronize(&mut estimator, -4160, TimeDelta::hours(12), &[0, 0, 0]);
+        assert_eq!(estimator.aging_offset(5), Some(5 - 42));
+    }
+
+    #[test]
+    fn needs_a_day_of_history() {
+        let mut estimator = DriftEstimator::new();
+        assert_eq!(estimator.drift_ppb(), None);
+        synchronize(&mut estimator, 2000, TimeDelta::hours(20), &[0]);
+        assert_eq!(estimator.drift_ppb(), None);
+        assert_eq!(estimator.aging_offset(0), None);
+    }
+
+    #[test]
+    fn keeps_offset_within_half_a_step() {
+        let mut estimator = DriftEstimator::new();
+        synchronize(&mut estimator, 40, TimeDelta::days(1), &[0, 0]);
+        assert_eq!(estimator.drift_ppb(), Some(40));
+        assert_eq!(estimator.aging_offset(7), None);
+    }
+
+    #[test]
+    fn clamps_offset_to_register_range() {
+        let mut estimator = DriftEstimator::new();
+        synchronize(&mut estimator, 30_000, TimeDelta::days(1), &[0, 0]);
+        assert_eq!(estimator.aging_offset(0), Some(i8::MAX));
+        assert_eq!(estimator.aging_offset(i8::MAX), None);
+        synchronize(&mut estimator, -30_000, TimeDelta::days(1), &[0; 8]);
+        assert_eq!(estimator.aging_offset(0), Some(i8::MIN));
+    }
+
+    #[test]
+    fn leaves_out_short_and_implausible_intervals() {
+        let mut estimator = DriftEstimator::new();
+        // Rebooted and synchronized again within minutes
+        assert_eq!(
+            synchronize(&mut estimator, 2000, TimeDelta::minutes(10), &[0]),
+            [false]
+        );
+        // The RTC lost its time
+        estimator.rtc_set(start());
+        assert!(!estimator.record(start() + TimeDelta::days(1), TimeDelta::days(-300)));
+        // Not set since the boot
+        assert!(!estimator.record(start() + TimeDelta::days(2), TimeDelta::zero()));
+        assert_eq!(estimator.intervals.len(), 0);
+    }
+
+    #[test]
+    fn forgets_old_intervals_and_restarts() {
+        let mut estimator = DriftEstimator::new();
+        synchronize(&mut estimator, 5000, TimeDelta::days(1), &[0; 8]);
+        // Only the last intervals count after the drift changed
+        synchronize(&mut estimator, 1000, TimeDelta::days(1), &[0; 8]);
+        assert_eq!(estimator.drift_ppb(), Some(1000));
+
+        estimator.restart();
+        assert_eq!(estimator.drift_ppb(), None);
+        synchronize(&mut estimator, 500, TimeDelta::days(1), &[0]);
+        assert_eq!(estimator.drift_ppb(), Some(500));
+    }
+}
@@ -0,0 +1,184 @@
+//! State kept over the deep sleep in the RTC memory of the ESP32
+//!
+//! The RTC memory keeps its content through the deep sleep, but it's random after a power loss and
+//! a reset can tear a write to it. So the state is stored with a magic number and a CRC-32 like the
+//! days off store records, and a state that fails them is replaced by the one of a fresh boot.
+//!
+//! The state is laid out as follows, in little endian:
+//!
+//! | Offset | Size | Field                                          |
+//! |--------|------|------------------------------------------------|
+//! | 0      | 2    | Magic, `0x51EE`                                |
+//! | 2      | 1    | Format version, [`FORMAT_VERSION`]             |
+//! | 3      | 1    | 1 when the RTC alarm is set for the refresh    |
+//! | 4      | 8    | Seconds of the sleep clock of the next refresh |
+//! | 12     | 28   | Time synchronization schedule                  |
+//! | 40     | 137  | RTC drift estimate                             |
+//! | 177    | 106  | Temperatures of the last day                   |
+//! | 283    | 8    | Seconds of the sleep clock of the next reading |
+//! | 291    | 1    | Zero                                           |
+//! | 292    | 4    | CRC-32 of the previous bytes                   |
+
+use crate::{
+    crc::crc32, rtc_drift, rtc_drift::DriftEstimator, sync_schedule, sync_schedule::SyncScheduler,
+    temperature_log, temperature_log::TemperatureLog,
+};
+
+/// Increase when the layout changes, the state of an older firmware is then dropped
+pub const FORMAT_VERSION: u8 = 3;
+/// Bytes of RTC memory the state takes
+pub const STATE_LEN: usize = 296;
+
+const MAGIC: u16 = 0x51EE;
+const SYNC_OFFSET: usize = 12;
+const DRIFT_OFFSET: usize = SYNC_OFFSET + sync_schedule::ENCODED_LEN;
+const TEMPERATURES_OFFSET: usize = DRIFT_OFFSET + rtc_drift::ENCODED_LEN;
+const TEMPERATURE_AT_OFFSET: usize = TEMPERATURES_OFFSET + temperature_log::ENCODED_LEN;
+const CRC_OFFSET: usize = STATE_LEN - 4;
+
+const _: () = assert!(TEMPERATURE_AT_OFFSET + 8 <= CRC_OFFSET);
+
+/// What the firmware remembers from one wake-up to the next
+///
+/// The times are seconds of a clock that keeps going through the deep sleep, like the RTC timer of
+/// the ESP32.
+#[derive(Debug, Default)]
+pub struct SleepState {
+    pub sync: SyncScheduler,
+    pub drift: DriftEstimator,
+    pub temperatures: TemperatureLog,
+    /// When the temperature is read next, it's due right away at 0
+    pub temperature_at: u64,
+    /// When the calendar is refreshed next, it's due right away at 0
+    pub refresh_at: u64,
+    /// Whether the RTC alarm is set to wake up for the refresh
+    pub alarm_set: bool,
+}
+
+impl SleepState {
+    pub fn encode(&self) -> [u8; STATE_LEN] {
+        let mut buf = [0; STATE_LEN];
+        buf[0..2].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[2] = FORMAT_VERSION;
+        buf[3] = self.alarm_set.into();
+        buf[4..12].copy_from_slice(&self.refresh_at.to_le_bytes());
+        buf[SYNC_OFFSET..DRIFT_OFFSET].copy_from_slice(&self.sync.encode());
+        buf[DRIFT_OFFSET..TEMPERATURES_OFFSET].copy_from_slice(&self.drift.encode());
+        buf[TEMPERATURES_OFFSET..TEMPERATURE_AT_OFFSET]
+            .copy_from_slice(&self.temperatures.encode());
+        buf[TEMPERATURE_AT_OFFSET..TEMPERATURE_AT_OFFSET + 8]
+            .copy_from_slice(&self.temperature_at.to_le_bytes());
+        let crc = crc32(&buf[..CRC_OFFSET]);
+        buf[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    /// Read back the state written by [`encode`](Self::encode), `None` if it's not a valid one
+    pub fn decode(buf: &[u8; STATE_LEN]) -> Option<Self> {
+        let crc = u32::from_le_bytes(buf[CRC_OFFSET..].try_into().unwrap());
+        if u16::from_le_bytes([buf[0], buf[1]]) != MAGIC
+            || buf[2] != FORMAT_VERSION
+            || crc != crc32(&buf[..CRC_OFFSET])
+        {
+            return None;
+        }
+        Some(Self {
+            sync: SyncScheduler::decode(buf[SYNC_OFFSET..DRIFT_OFFSET].try_into().unwrap()),
+            drift: DriftEstimator::decode(
+                buf[DRIFT_OFFSET..TEMPERATURES_OFFSET].try_into().unwrap(),
+            )?,
+            temperatures: TemperatureLog::decode(
+                buf[TEMPERATURES_OFFSET..TEMPERATURE_AT_OFFSET]
+                    .try_into()
+                    .unwrap(),
+            ),
+            temperature_at: u64::from_le_bytes(
+                buf[TEMPERATURE_AT_OFFSET..TEMPERATURE_AT_OFFSET + 8]
+                    .try_into()
+                    .unwrap(),
+            ),
+            refresh_at: u64::from_le_bytes(buf[4..12].try_into().unwrap()),
+            alarm_set: buf[3] == 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta};
+
+    use super::{CRC_OFFSET, STATE_LEN, SleepState};
+
+    fn state() -> SleepState {
+        let mut state = SleepState {
+            refresh_at: 86_405,
+            temperature_at: 1800,
+            alarm_set: true,
+            ..SleepState::default()
+        };
+        let day = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        for days in 0..4 {
+            let at = (day + TimeDelta::days(days)).and_hms_opt(0, 0, 5).unwrap();
+            state.drift.record(at, TimeDelta::milliseconds(150));
+            state.drift.rtc_set(at);
+        }
+        state.sync.succeeded(100, None);
+        state
+            .sync
+            .succeeded(86_500, Some(TimeDelta::milliseconds(150)));
+        state.sync.failed(2 * 86_500, u32::MAX);
+        state
+            .temperatures
+            .record(Some(day.and_hms_opt(3, 0, 0).unwrap()), 70);
+        state
+            .temperatures
+            .record(Some(day.and_hms_opt(8, 0, 0).unwrap()), 86);
+        state
+    }
+
+    #[test]
+    fn round_trips() {
+        let state = state();
+        let decoded = SleepState::decode(&state.encode()).unwrap();
+        assert_eq!(decoded.encode(), state.encode());
+        assert_eq!(decoded.refresh_at, 86_405);
+        assert_eq!(decoded.temperature_at, 1800);
+        assert!(decoded.alarm_set);
+        assert_eq!(decoded.drift.drift_ppb(), state.drift.drift_ppb());
+        assert!(decoded.drift.drift_ppb().is_some());
+        assert_eq!(decoded.sync.next_at(), state.sync.next_at());
+        assert_eq!(decoded.sync.interval_secs(), 2 * 86_400);
+        assert_eq!(decoded.sync.sync_age(86_600), Some(100));
+        let morning = NaiveDate::from_ymd_opt(2026, 10, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0);
+        assert_eq!(
+            decoded.temperatures.label(morning).unwrap(),
+            "21.5° 17.5°..21.5°"
+        );
+
+        let fresh = SleepState::decode(&SleepState::default().encode()).unwrap();
+        assert_eq!(fresh.refresh_at, 0);
+        assert_eq!(fresh.temperature_at, 0);
+        assert!(!fresh.alarm_set);
+        assert_eq!(fresh.sync.sync_age(0), None);
+        assert!(fresh.sync.is_due(0));
+        assert_eq!(fresh.drift.drift_ppb(), None);
+        assert_eq!(fresh.temperatures.latest(), None);
+    }
+
+    #[test]
+    fn rejects_power_on_and_torn_content() {
+        assert!(SleepState::decode(&[0; STATE_LEN]).is_none());
+        assert!(SleepState::decode(&[0xA5; STATE_LEN]).is_none());
+        let state = state().encode();
+        for at in [3, 20, CRC_OFFSET - 1, CRC_OFFSET] {
+            let mut torn = state;
+            torn[at] ^= 0x10;
+            assert!(SleepState::decode(&torn).is_none(), "{at}");
+        }
+        let mut other_version = state;
+        other_version[2] += 1;
+        assert!(SleepState::decode(&other_version).is_none());
+    }
+}
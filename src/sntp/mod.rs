@@ -0,0 +1,253 @@
+//! SNTP (RFC 4330) client that measures the offset of a monotonic clock from the servers
+//!
+//! Every server is asked a few times. A single answer can be off by half of the time the request
+//! spent on the network, so the answers that came back the fastest are trusted the most, and the
+//! servers that disagree with the others are left out.
+//!
+//! The UDP sockets are the ones of sntpc, only its packets are not used: sntpc doesn't tell the
+//! Kiss-o'-Death responses, which ask clients to stop or slow down, from other invalid ones.
+
+use core::{future::Future, net::SocketAddr};
+
+use chrono::{DateTime, NaiveDateTime};
+use heapless::Vec;
+use log::warn;
+use sntpc::NtpUdpSocket;
+
+#[cfg(test)]
+mod tests;
+
+pub const NTP_PORT: u16 = 123;
+/// Servers asked by [`synchronize`], more are ignored
+pub const MAX_SERVERS: usize = 4;
+/// Requests to every server
+pub const SAMPLES_PER_SERVER: usize = 4;
+/// Time between the requests to a server, the same as ntpd's `iburst`
+const SAMPLE_INTERVAL_MICROS: u64 = 2_000_000;
+const RESPONSE_TIMEOUT_MICROS: u64 = 1_000_000;
+/// Answers that took longer on the network can be off by half of it, they're dropped
+const MAX_DELAY_MICROS: i64 = 1_000_000;
+/// Servers that are further from their reference clock are not synchronized, like ntpd's `maxdist`
+const MAX_ROOT_DISTANCE_MICROS: i64 = 1_500_000;
+/// Samples further than this from the median offset of all are from a wrong server
+const MAX_OFFSET_SPREAD_MICROS: i64 = 500_000;
+/// The median offset of these many samples with the least delay is used
+const BEST_SAMPLES: usize = 3;
+
+const PACKET_LEN: usize = 48;
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+/// Seconds from the NTP epoch, 1900, to the Unix one
+const NTP_UNIX_OFFSET_SECS: i64 = 2_208_988_800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SntpError {
+    /// Sending or receiving failed
+    Network,
+    /// The server didn't answer in time
+    Timeout,
+    /// The response is malformed or not an answer to the request
+    InvalidResponse,
+    /// The server isn't synchronized to a reference clock
+    Unsynchronized,
+    /// The answer took too long on the network to be accurate
+    DelayTooLong,
+    /// The server asked to stop (`DENY`, `RSTR`) or slow down (`RATE`) with this code
+    KissOfDeath([u8; 4]),
+    /// No server was given
+    NoServers,
+}
+
+/// Monotonic clock of the device, the offsets are measured from it
+pub trait SntpClock {
+    /// Get the microseconds since some start, like the uptime
+    fn now_micros(&self) -> u64;
+
+    /// Wait for `fut` up to `micros`, `None` when it didn't finish in time
+    fn timeout<F: Future>(&self, micros: u64, fut: F) -> impl Future<Output = Option<F::Output>>;
+
+    fn sleep(&self, micros: u64) -> impl Future<Output = ()>;
+}
+
+/// Offset of the clock measured by a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub server: SocketAddr,
+    /// Microseconds of UTC since the Unix epoch minus the ones of the clock
+    pub offset: i64,
+    /// Round trip of the request on the network, without the time on the server, in microseconds
+    pub delay: i64,
+    pub stratum: u8,
+}
+
+impl Sample {
+    /// Get the UTC time at `clock_micros` of the clock
+    pub fn utc_at(&self, clock_micros: u64) -> Option<NaiveDateTime> {
+        let micros = i64::try_from(clock_micros).ok()?.checked_add(self.offset)?;
+        DateTime::from_timestamp_micros(micros).map(|time| time.naive_utc())
+    }
+}
+
+/// Ask the `servers` [`SAMPLES_PER_SERVER`] times each and pick the best sample
+///
+/// A server is not asked again after a Kiss-o'-Death. The last error is returned when there's no
+/// valid sample.
+pub async fn synchronize<U: NtpUdpSocket>(
+    socket: &U,
+    servers: &[SocketAddr],
+    clock: &impl SntpClock,
+) -> Result<Sample, SntpError> {
+    let servers = &servers[..servers.len().min(MAX_SERVERS)];
+    let mut stopped = [false; MAX_SERVERS];
+    let mut samples = Vec::<Sample, { MAX_SERVERS * SAMPLES_PER_SERVER }>::new();
+    let mut last_error = SntpError::NoServers;
+    for round in 0..SAMPLES_PER_SERVER {
+        let round_start = clock.now_micros();
+        for (&server, is_stopped) in servers.iter().zip(&mut stopped) {
+            if *is_stopped {
+                continue;
+            }
+            match query(socket, server, clock).await {
+                // Can't be full, there are as many places as requests
+                Ok(sample) => samples.push(sample).unwrap(),
+                Err(e) => {
+                    if let SntpError::KissOfDeath(code) = e {
+                        warn!(
+                            "NTP server {server} sent Kiss-o'-Death {:?}",
+                            core::str::from_utf8(&code)
+                        );
+                        *is_stopped = true;
+                    }
+                    last_error = e;
+                }
+            }
+        }
+        let elapsed = clock.now_micros().saturating_sub(round_start);
+        if round + 1 < SAMPLES_PER_SERVER && stopped[..servers.len()].contains(&false) {
+            clock
+                .sleep(SAMPLE_INTERVAL_MICROS.saturating_sub(elapsed))
+                .await;
+        }
+    }
+    best_sample(&samples).ok_or(last_error)
+}
+
+/// Measure the offset of the clock from `server` with a single request
+pub async fn query<U: NtpUdpSocket>(
+    socket: &U,
+    server: SocketAddr,
+    clock: &impl SntpClock,
+) -> Result<Sample, SntpError> {
+    // The transmit time of the request is echoed back by the server, the clock's time tells the
+    // answer apart from the ones to the other requests
+    let sent_at = clock.now_micros();
+    let mut packet = [0; PACKET_LEN];
+    packet[0] = (VERSION << 3) | MODE_CLIENT;
+    packet[40..].copy_from_slice(&micros_to_ntp(sent_at).to_be_bytes());
+    let sent = socket
+        .send_to(&packet, server)
+        .await
+        .map_err(|_| SntpError::Network)?;
+    if sent != PACKET_LEN {
+        return Err(SntpError::Network);
+    }
+
+    let (len, from) = clock
+        .timeout(RESPONSE_TIMEOUT_MICROS, socket.recv_from(&mut packet))
+        .await
+        .ok_or(SntpError::Timeout)?
+        .map_err(|_| SntpError::Network)?;
+    let received_at = clock.now_micros();
+    if from != server || len != PACKET_LEN {
+        return Err(SntpError::InvalidResponse);
+    }
+    let (offset, delay, stratum) = parse_response(&packet, sent_at, received_at)?;
+    Ok(Sample {
+        server,
+        offset,
+        delay,
+        stratum,
+    })
+}
+
+/// Get the offset, delay and stratum of the server from its answer to the request sent at the
+/// `sent_at` microseconds of the clock
+fn parse_response(
+    packet: &[u8; PACKET_LEN],
+    sent_at: u64,
+    received_at: u64,
+) -> Result<(i64, i64, u8), SntpError> {
+    let field = |at: usize| u32::from_be_bytes(packet[at..at + 4].try_into().unwrap());
+    let timestamp = |at: usize| (u64::from(field(at)) << 32) | u64::from(field(at + 4));
+    let leap = packet[0] >> 6;
+    let version = (packet[0] >> 3) & 0b111;
+    let mode = packet[0] & 0b111;
+    let stratum = packet[1];
+
+    if mode != MODE_SERVER || version != VERSION || timestamp(24) != micros_to_ntp(sent_at) {
+        return Err(SntpError::InvalidResponse);
+    }
+    if stratum == 0 {
+        return Err(SntpError::KissOfDeath(field(12).to_be_bytes()));
+    }
+    // Root delay and dispersion are in seconds with 16 bits of fraction
+    let root_distance = i64::from(field(4)) / 2 + i64::from(field(8));
+    if leap == LEAP_UNSYNCHRONIZED
+        || stratum > 15
+        || (root_distance * 1_000_000) >> 16 > MAX_ROOT_DISTANCE_MICROS
+    {
+        return Err(SntpError::Unsynchronized);
+    }
+    let server_received = ntp_to_unix_micros(timestamp(32));
+    let server_sent = ntp_to_unix_micros(timestamp(40));
+    if timestamp(40) == 0 || server_sent < server_received {
+        return Err(SntpError::InvalidResponse);
+    }
+
+    let sent_at = i64::try_from(sent_at).map_err(|_| SntpError::InvalidResponse)?;
+    let received_at = i64::try_from(received_at).map_err(|_| SntpError::InvalidResponse)?;
+    let delay = (received_at - sent_at) - (server_sent - server_received);
+    if !(0..=MAX_DELAY_MICROS).contains(&delay) {
+        return Err(SntpError::DelayTooLong);
+    }
+    let offset = ((server_received - sent_at) + (server_sent - received_at)) / 2;
+    Ok((offset, delay, stratum))
+}
+
+/// Pick the sample of the median offset out of the ones with the least delay
+///
+/// The samples too far from the median offset of all are left out first, their servers are wrong.
+pub fn best_sample(samples: &[Sample]) -> Option<Sample> {
+    let mut samples = Vec::<Sample, { MAX_SERVERS * SAMPLES_PER_SERVER }>::from_slice(
+        &samples[..samples.len().min(MAX_SERVERS * SAMPLES_PER_SERVER)],
+    )
+    .unwrap();
+    samples.sort_unstable_by_key(|sample| sample.offset);
+    let median = samples.get(samples.len() / 2)?.offset;
+    samples.retain(|sample| (sample.offset - median).abs() <= MAX_OFFSET_SPREAD_MICROS);
+
+    samples.sort_unstable_by_key(|sample| sample.delay);
+    samples.truncate(BEST_SAMPLES);
+    samples.sort_unstable_by_key(|sample| sample.offset);
+    samples.get(samples.len() / 2).copied()
+}
+
+/// Convert microseconds to an NTP timestamp, 32 bits of seconds and 32 of fraction
+fn micros_to_ntp(micros: u64) -> u64 {
+    let fraction = ((micros % 1_000_000) << 32) / 1_000_000;
+    ((micros / 1_000_000) << 32) | fraction
+}
+
+/// Convert an NTP timestamp to the microseconds since the Unix epoch
+///
+/// The seconds wrap in 2036, the timestamps from before 1968 are taken as ones after it.
+fn ntp_to_unix_micros(timestamp: u64) -> i64 {
+    let mut secs = (timestamp >> 32) as i64 - NTP_UNIX_OFFSET_SECS;
+    if timestamp >> 63 == 0 {
+        secs += 1 << 32;
+    }
+    let micros = ((timestamp & u64::from(u32::MAX)) * 1_000_000) >> 32;
+    secs * 1_000_000 + micros as i64
+}
@@ -0,0 +1,322 @@
+use std::{
+    cell::Cell,
+    net::{SocketAddr, UdpSocket},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use embassy_futures::block_on;
+use sntpc::NtpUdpSocket;
+
+use super::{
+    NTP_UNIX_OFFSET_SECS, PACKET_LEN, SAMPLE_INTERVAL_MICROS, SAMPLES_PER_SERVER, Sample,
+    SntpClock, SntpError, best_sample, micros_to_ntp, ntp_to_unix_micros, parse_response,
+    synchronize,
+};
+
+/// Answer of the stub server to a request
+#[derive(Debug, Clone, Copy)]
+enum Reply {
+    /// The time off by `offset`, with the request held up by `delay` on its way
+    Time {
+        offset: TimeDelta,
+        delay: Duration,
+    },
+    KissOfDeath(&'static [u8; 4]),
+    /// An answer to another request
+    WrongOrigin,
+    Silent,
+}
+
+const ON_TIME: Reply = Reply::Time {
+    offset: TimeDelta::zero(),
+    delay: Duration::ZERO,
+};
+
+/// NTP server on localhost that answers the requests in the order of its replies, the last one is
+/// repeated
+struct StubNtpServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<usize>>,
+}
+
+impl StubNtpServer {
+    fn start(replies: Vec<Reply>) -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(0));
+        let received = requests.clone();
+        thread::spawn(move || {
+            let mut request = [0; PACKET_LEN];
+            while let Ok((_, client)) = socket.recv_from(&mut request) {
+                let count = {
+                    let mut received = received.lock().unwrap();
+                    *received += 1;
+                    *received
+                };
+                let reply = replies[(count - 1).min(replies.len() - 1)];
+                if let Some(response) = respond(reply, &request) {
+                    socket.send_to(&response, client).unwrap();
+                }
+            }
+        });
+        Self { addr, requests }
+    }
+
+    fn requests(&self) -> usize {
+        *self.requests.lock().unwrap()
+    }
+}
+
+fn respond(reply: Reply, request: &[u8; PACKET_LEN]) -> Option<[u8; PACKET_LEN]> {
+    let mut response = [0; PACKET_LEN];
+    // Version 4, server mode, stratum 2
+    response[0] = (4 << 3) | 4;
+    response[1] = 2;
+    response[24..32].copy_from_slice(&request[40..48]);
+    match reply {
+        Reply::Time { offset, delay } => {
+            thread::sleep(delay);
+            let timestamp = || unix_to_ntp(now() + offset).to_be_bytes();
+            response[32..40].copy_from_slice(&timestamp());
+            response[40..48].copy_from_slice(&timestamp());
+        }
+        Reply::KissOfDeath(code) => {
+            response[1] = 0;
+            response[12..16].copy_from_slice(code);
+        }
+        Reply::WrongOrigin => {
+            response[24] ^= 1;
+            response[32..48].fill(1);
+        }
+        Reply::Silent => return None,
+    }
+    Some(response)
+}
+
+fn now() -> NaiveDateTime {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    DateTime::from_timestamp_micros(since_epoch.as_micros() as i64)
+        .unwrap()
+        .naive_utc()
+}
+
+fn unix_to_ntp(time: NaiveDateTime) -> u64 {
+    let micros = time.and_utc().timestamp_micros() + NTP_UNIX_OFFSET_SECS * 1_000_000;
+    micros_to_ntp(micros as u64)
+}
+
+/// Client socket on localhost, a missing answer is a network error
+struct StdSocket(UdpSocket);
+
+impl StdSocket {
+    fn new() -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(300)))
+            .unwrap();
+        Self(socket)
+    }
+}
+
+impl NtpUdpSocket for StdSocket {
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> sntpc::Result<usize> {
+        self.0.send_to(buf, addr).map_err(|_| sntpc::Error::Network)
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> sntpc::Result<(usize, SocketAddr)> {
+        self.0.recv_from(buf).map_err(|_| sntpc::Error::Network)
+    }
+}
+
+/// Uptime clock that only adds up the sleeps instead of waiting, the servers don't wait either
+struct StdClock {
+    start: Instant,
+    slept: Cell<u64>,
+}
+
+impl StdClock {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            slept: Cell::new(0),
+        }
+    }
+}
+
+impl SntpClock for StdClock {
+    fn now_micros(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+
+    async fn timeout<F: Future>(&self, _micros: u64, fut: F) -> Option<F::Output> {
+        // The socket has a timeout of its own
+        Some(fut.await)
+    }
+
+    async fn sleep(&self, micros: u64) {
+        self.slept.set(self.slept.get() + micros);
+    }
+}
+
+fn sync(servers: &[&StubNtpServer]) -> (Result<Sample, SntpError>, StdClock) {
+    let clock = StdClock::new();
+    let addrs: Vec<_> = servers.iter().map(|server| server.addr).collect();
+    let res = block_on(synchronize(&StdSocket::new(), &addrs, &clock));
+    (res, clock)
+}
+
+/// Check that the time of the sample is the true time off by `offset`
+fn assert_time(sample: &Sample, clock: &StdClock, offset: TimeDelta, tolerance: TimeDelta) {
+    let expected = now() + offset;
+    let time = sample.utc_at(clock.now_micros()).unwrap();
+    assert!(
+        (time - expected).abs() <= tolerance,
+        "{time} is not {expected}"
+    );
+}
+
+#[test]
+fn measures_offset_of_server() {
+    let offset = TimeDelta::seconds(90);
+    let server = StubNtpServer::start(vec![Reply::Time {
+        offset,
+        delay: Duration::ZERO,
+    }]);
+    let (res, clock) = sync(&[&server]);
+
+    let sample = res.unwrap();
+    assert_eq!(sample.server, server.addr);
+    assert_eq!(sample.stratum, 2);
+    assert!(sample.delay < 20_000, "{sample:?}");
+    assert_time(&sample, &clock, offset, TimeDelta::milliseconds(20));
+    assert_eq!(server.requests(), SAMPLES_PER_SERVER);
+    // The requests to the server are spaced out
+    let intervals = (SAMPLES_PER_SERVER as u64 - 1) * SAMPLE_INTERVAL_MICROS;
+    assert!(clock.slept.get() <= intervals);
+    assert!(clock.slept.get() + clock.now_micros() >= intervals);
+}
+
+#[test]
+fn prefers_samples_with_least_delay() {
+    // A request held up on the way to the server makes its time 100 ms late
+    let held_up = Reply::Time {
+        offset: TimeDelta::zero(),
+        delay: Duration::from_millis(200),
+    };
+    let server = StubNtpServer::start(vec![held_up, ON_TIME, held_up, ON_TIME]);
+    let (res, clock) = sync(&[&server]);
+
+    let sample = res.unwrap();
+    assert!(sample.delay < 100_000, "{sample:?}");
+    assert_time(
+        &sample,
+        &clock,
+        TimeDelta::zero(),
+        TimeDelta::milliseconds(50),
+    );
+}
+
+#[test]
+fn leaves_out_server_that_disagrees() {
+    let wrong = StubNtpServer::start(vec![Reply::Time {
+        offset: TimeDelta::hours(1),
+        delay: Duration::ZERO,
+    }]);
+    let first = StubNtpServer::start(vec![ON_TIME]);
+    let second = StubNtpServer::start(vec![ON_TIME]);
+    let (res, clock) = sync(&[&wrong, &first, &second]);
+
+    let sample = res.unwrap();
+    assert_ne!(sample.server, wrong.addr);
+    assert_time(
+        &sample,
+        &clock,
+        TimeDelta::zero(),
+        TimeDelta::milliseconds(20),
+    );
+}
+
+#[test]
+fn stops_asking_server_after_kiss_of_death() {
+    let limited = StubNtpServer::start(vec![Reply::KissOfDeath(b"RATE"), ON_TIME]);
+    let other = StubNtpServer::start(vec![ON_TIME]);
+    let (res, _) = sync(&[&limited, &other]);
+
+    assert_eq!(res.unwrap().server, other.addr);
+    assert_eq!(limited.requests(), 1);
+    assert_eq!(other.requests(), SAMPLES_PER_SERVER);
+}
+
+#[test]
+fn kiss_of_death_of_only_server() {
+    let server = StubNtpServer::start(vec![Reply::KissOfDeath(b"DENY")]);
+    let (res, clock) = sync(&[&server]);
+
+    assert_eq!(res, Err(SntpError::KissOfDeath(*b"DENY")));
+    assert_eq!(server.requests(), 1);
+    assert_eq!(clock.slept.get(), 0);
+}
+
+#[test]
+fn skips_invalid_and_missing_answers() {
+    let server = StubNtpServer::start(vec![Reply::WrongOrigin, Reply::Silent, ON_TIME]);
+    let (res, clock) = sync(&[&server]);
+    assert_time(
+        &res.unwrap(),
+        &clock,
+        TimeDelta::zero(),
+        TimeDelta::milliseconds(20),
+    );
+
+    let server = StubNtpServer::start(vec![Reply::WrongOrigin]);
+    assert_eq!(sync(&[&server]).0, Err(SntpError::InvalidResponse));
+    assert_eq!(sync(&[]).0, Err(SntpError::NoServers));
+}
+
+#[test]
+fn rejects_unsynchronized_server() {
+    let sent_at = 5_000_000;
+    let mut request = [0; PACKET_LEN];
+    request[40..].copy_from_slice(&micros_to_ntp(sent_at).to_be_bytes());
+    let response = respond(ON_TIME, &request).unwrap();
+    assert!(parse_response(&response, sent_at, sent_at + 1000).is_ok());
+
+    let mut alarm = response;
+    alarm[0] |= 3 << 6;
+    let mut far_from_reference = response;
+    // 2 s of root dispersion
+    far_from_reference[8..12].copy_from_slice(&(2u32 << 16).to_be_bytes());
+    let mut no_stratum = response;
+    no_stratum[1] = 16;
+    for response in [alarm, far_from_reference, no_stratum] {
+        assert_eq!(
+            parse_response(&response, sent_at, sent_at + 1000),
+            Err(SntpError::Unsynchronized)
+        );
+    }
+    // Too long on the network
+    assert_eq!(
+        parse_response(&response, sent_at, sent_at + 2_000_000),
+        Err(SntpError::DelayTooLong)
+    );
+}
+
+#[test]
+fn converts_ntp_timestamps() {
+    let unix_epoch = (NTP_UNIX_OFFSET_SECS as u64) << 32;
+    assert_eq!(ntp_to_unix_micros(unix_epoch), 0);
+    assert_eq!(ntp_to_unix_micros(unix_epoch | (1 << 31)), 500_000);
+    // The seconds wrap on 2036-02-07 06:28:16
+    let wrapped = UNIX_EPOCH + Duration::from_secs((1 << 32) - NTP_UNIX_OFFSET_SECS as u64);
+    let wrapped_micros = wrapped.duration_since(UNIX_EPOCH).unwrap().as_micros() as i64;
+    assert_eq!(ntp_to_unix_micros(0), wrapped_micros);
+    assert_eq!(micros_to_ntp(1_500_000), (1 << 32) | (1 << 31));
+}
+
+#[test]
+fn best_sample_of_none() {
+    assert_eq!(best_sample(&[]), None);
+}
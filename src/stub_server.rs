@@ -0,0 +1,219 @@
+//! Local HTTP server with canned responses, to test the web service clients on the host
+//!
+//! It also serves `https` with the test certificates of [`crate::http`], which are issued for
+//! `localhost`.
+#![cfg_attr(not(feature = "tls"), allow(dead_code))]
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    string::String,
+    sync::{Arc, Mutex},
+    thread,
+    vec::Vec,
+};
+
+use embedded_io::ErrorKind;
+use embedded_nal::AddrType;
+use embedded_nal_async::{Dns, TcpConnect};
+use rustls::{
+    ServerConfig, ServerConnection, StreamOwned,
+    pki_types::{CertificateDer, PrivateKeyDer},
+};
+
+/// Certificate of the server, issued for `localhost` by [`TEST_CA`]
+const SERVER_CERT: &[u8] = include_bytes!("http/testdata/leaf.der");
+const SERVER_KEY: &[u8] = include_bytes!("http/testdata/leaf.key.der");
+/// CA the `https` server is verified with
+pub const TEST_CA: &[u8] = include_bytes!("http/testdata/ca.der");
+/// CA that didn't issue the certificate of the server
+pub const OTHER_CA: &[u8] = include_bytes!("http/testdata/other_ca.der");
+
+/// Serves a response for each known request path, 404 for the others
+pub struct StubServer {
+    addr: SocketAddr,
+    tls: bool,
+    /// Paths of the received requests, with the query
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StubServer {
+    /// Serve `routes` of request paths, with the query, and the bodies to answer them with
+    pub fn start(routes: Vec<(String, u16, Vec<u8>)>) -> Self {
+        Self::spawn(routes, None)
+    }
+
+    /// Serve the `routes` over TLS 1.3, to be reached by `localhost`
+    pub fn start_tls(routes: Vec<(String, u16, Vec<u8>)>) -> Self {
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_protocol_versions(&[&rustls::version::TLS13])
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(
+                    std::vec![CertificateDer::from(SERVER_CERT)],
+                    PrivateKeyDer::try_from(SERVER_KEY).unwrap(),
+                )
+                .unwrap();
+        Self::spawn(routes, Some(Arc::new(config)))
+    }
+
+    fn spawn(routes: Vec<(String, u16, Vec<u8>)>, tls: Option<Arc<ServerConfig>>) -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        let is_tls = tls.is_some();
+        // The thread is left blocked on accept when the test ends
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                // Failed connections are left out of the requests
+                let _ = match &tls {
+                    Some(config) => ServerConnection::new(config.clone())
+                        .map_err(io::Error::other)
+                        .and_then(|conn| serve(StreamOwned::new(conn, stream), &routes, &received)),
+                    None => serve(stream, &routes, &received),
+                };
+            }
+        });
+        Self {
+            addr,
+            tls: is_tls,
+            requests,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        if self.tls {
+            std::format!("https://localhost:{}", self.addr.port())
+        } else {
+            std::format!("http://{}", self.addr)
+        }
+    }
+
+    /// Get the paths of the requests served so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Answer a request, it's recorded before the response is sent so the client sees it when done
+fn serve(
+    stream: impl Read + Write,
+    routes: &[(String, u16, Vec<u8>)],
+    received: &Mutex<Vec<String>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let path = request_line
+        .split(' ')
+        .nth(1)
+        .unwrap_or_default()
+        .to_owned();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    let mut body = std::vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    received.lock().unwrap().push(path.clone());
+
+    let (status, body) = routes
+        .iter()
+        .find(|(route, _, _)| *route == path)
+        .map_or((404, &[][..]), |(_, status, body)| (*status, &body[..]));
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {status} Stub\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// TCP stack of the host, blocking on every operation
+pub struct StdTcp;
+
+pub struct StdConnection(TcpStream);
+
+impl TcpConnect for StdTcp {
+    type Error = ErrorKind;
+    type Connection<'a> = StdConnection;
+
+    async fn connect(&self, remote: SocketAddr) -> Result<StdConnection, ErrorKind> {
+        TcpStream::connect(remote)
+            .map(StdConnection)
+            .map_err(error_kind)
+    }
+}
+
+impl embedded_io::ErrorType for StdConnection {
+    type Error = ErrorKind;
+}
+
+impl embedded_io_async::Read for StdConnection {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        self.0.read(buf).map_err(error_kind)
+    }
+}
+
+impl embedded_io_async::Write for StdConnection {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        self.0.write(buf).map_err(error_kind)
+    }
+}
+
+fn error_kind(e: io::Error) -> ErrorKind {
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused => ErrorKind::ConnectionRefused,
+        _ => ErrorKind::Other,
+    }
+}
+
+/// Resolves only IP addresses and `localhost`, the stub server is reached by them
+pub struct StdDns;
+
+impl Dns for StdDns {
+    type Error = ErrorKind;
+
+    async fn get_host_by_name(
+        &self,
+        host: &str,
+        _addr_type: AddrType,
+    ) -> Result<IpAddr, ErrorKind> {
+        if host == "localhost" {
+            return Ok(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        host.parse().map_err(|_| ErrorKind::NotFound)
+    }
+
+    async fn get_host_by_address(
+        &self,
+        _addr: IpAddr,
+        _result: &mut [u8],
+    ) -> Result<usize, ErrorKind> {
+        Err(ErrorKind::Unsupported)
+    }
+}
+
+/// Address nothing listens on, to test failed connections
+pub fn closed_port_url() -> String {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    std::format!("http://{addr}")
+}
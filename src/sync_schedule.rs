@@ -0,0 +1,229 @@
+//! When to synchronize the time over NTP
+//!
+//! The time is synchronized at the boot, then less and less often while the RTC keeps being close
+//! to the NTP time, and more often again when it drifted far. Failed synchronizations are tried
+//! again sooner and sooner after each other, with some randomness so that devices that failed
+//! together don't try again together.
+//!
+//! The times are seconds of uptime.
+
+use chrono::TimeDelta;
+
+/// Interval after the first synchronization
+const INITIAL_INTERVAL_SECS: u64 = 24 * 3600;
+const MIN_INTERVAL_SECS: u64 = 3600;
+const MAX_INTERVAL_SECS: u64 = 7 * 24 * 3600;
+/// The interval is doubled when the RTC drifted less than this over it
+const SMALL_ERROR: TimeDelta = TimeDelta::milliseconds(250);
+/// The interval is halved when the RTC drifted more than this over it
+const LARGE_ERROR: TimeDelta = TimeDelta::seconds(1);
+/// Wait before the first retry, doubled with every failure after it
+const RETRY_BASE_SECS: u64 = 60;
+const MAX_RETRY_SECS: u64 = 6 * 3600;
+/// Length of [`SyncScheduler::encode`]
+pub(crate) const ENCODED_LEN: usize = 28;
+
+#[derive(Debug)]
+pub struct SyncScheduler {
+    interval_secs: u64,
+    next_at: u64,
+    /// Failures since the last success
+    failures: u32,
+    last_good_at: Option<u64>,
+}
+
+impl Default for SyncScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyncScheduler {
+    /// Create a scheduler that is due right away
+    pub const fn new() -> Self {
+        Self {
+            interval_secs: INITIAL_INTERVAL_SECS,
+            next_at: 0,
+            failures: 0,
+            last_good_at: None,
+        }
+    }
+
+    pub const fn is_due(&self, now: u64) -> bool {
+        now >= self.next_at
+    }
+
+    /// Get the uptime of the next synchronization
+    pub const fn next_at(&self) -> u64 {
+        self.next_at
+    }
+
+    pub const fn interval_secs(&self) -> u64 {
+        self.interval_secs
+    }
+
+    /// Get the seconds since the time was last synchronized, `None` if it never was
+    pub fn sync_age(&self, now: u64) -> Option<u64> {
+        self.last_good_at.map(|at| now.saturating_sub(at))
+    }
+
+    /// Schedule the next synchronization after one succeeded at `now`
+    ///
+    /// `rtc_error` is how far off the RTC was, it's only taken into account when the RTC was set
+    /// by the previous synchronization.
+    pub fn succeeded(&mut self, now: u64, rtc_error: Option<TimeDelta>) {
+        if self.last_good_at.is_some()
+            && let Some(error) = rtc_error.map(|error| error.abs())
+        {
+            if error < SMALL_ERROR {
+                self.interval_secs = (self.interval_secs * 2).min(MAX_INTERVAL_SECS);
+            } else if error > LARGE_ERROR {
+                self.interval_secs = (self.interval_secs / 2).max(MIN_INTERVAL_SECS);
+            }
+        }
+        self.failures = 0;
+        self.last_good_at = Some(now);
+        self.next_at = now + self.interval_secs;
+    }
+
+    /// Schedule a retry after a synchronization failed at `now`
+    ///
+    /// `random` spreads the retry over ±25% of the backoff.
+    pub fn failed(&mut self, now: u64, random: u32) {
+        let backoff = RETRY_BASE_SECS
+            .saturating_mul(1 << self.failures.min(16))
+            .min(MAX_RETRY_SECS);
+        let jitter = backoff / 2 * u64::from(random) / (1 << 32);
+        self.failures = self.failures.saturating_add(1);
+        self.next_at = now + backoff * 3 / 4 + jitter;
+    }
+
+    /// Write the state to bytes in little endian, a missing last success as [`u64::MAX`]
+    pub(crate) fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut buf = [0; ENCODED_LEN];
+        buf[0..8].copy_from_slice(&self.interval_secs.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.next_at.to_le_bytes());
+        buf[16..20].copy_from_slice(&self.failures.to_le_bytes());
+        buf[20..28].copy_from_slice(&self.last_good_at.unwrap_or(u64::MAX).to_le_bytes());
+        buf
+    }
+
+    /// Read back the state written by [`encode`](Self::encode)
+    pub(crate) fn decode(buf: &[u8; ENCODED_LEN]) -> Self {
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
+        Self {
+            interval_secs: u64_at(0).clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS),
+            next_at: u64_at(8),
+            failures: u32::from_le_bytes(buf[16..20].try_into().unwrap()),
+            last_good_at: Some(u64_at(20)).filter(|&at| at != u64::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::{
+        INITIAL_INTERVAL_SECS, MAX_INTERVAL_SECS, MAX_RETRY_SECS, MIN_INTERVAL_SECS, SyncScheduler,
+    };
+
+    const DAY: u64 = 24 * 3600;
+
+    fn millis(millis: i64) -> Option<TimeDelta> {
+        Some(TimeDelta::milliseconds(millis))
+    }
+
+    #[test]
+    fn due_at_boot_then_after_interval() {
+        let mut scheduler = SyncScheduler::new();
+        assert!(scheduler.is_due(0));
+        assert_eq!(scheduler.sync_age(10), None);
+
+        // The RTC wasn't set by a synchronization before, its error says nothing about the drift
+        scheduler.succeeded(10, millis(40_000));
+        assert_eq!(scheduler.interval_secs(), INITIAL_INTERVAL_SECS);
+        assert!(!scheduler.is_due(DAY));
+        assert!(scheduler.is_due(DAY + 10));
+        assert_eq!(scheduler.sync_age(DAY + 10), Some(DAY));
+    }
+
+    #[test]
+    fn interval_grows_while_drift_is_small() {
+        let mut scheduler = SyncScheduler::new();
+        scheduler.succeeded(0, None);
+        let mut intervals = Vec::new();
+        for _ in 0..4 {
+            let now = scheduler.next_at();
+            scheduler.succeeded(now, millis(-30));
+            intervals.push(scheduler.interval_secs() / DAY);
+        }
+        assert_eq!(intervals, [2, 4, 7, 7]);
+        assert_eq!(scheduler.interval_secs(), MAX_INTERVAL_SECS);
+
+        // Moderate drift keeps it
+        scheduler.succeeded(scheduler.next_at(), millis(600));
+        assert_eq!(scheduler.interval_secs(), MAX_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn interval_shrinks_when_drift_is_large() {
+        let mut scheduler = SyncScheduler::new();
+        scheduler.succeeded(0, None);
+        for _ in 0..6 {
+            scheduler.succeeded(scheduler.next_at(), millis(-2500));
+        }
+        assert_eq!(scheduler.interval_secs(), MIN_INTERVAL_SECS);
+        // A failed measurement of the RTC keeps it
+        scheduler.succeeded(scheduler.next_at(), None);
+        assert_eq!(scheduler.interval_secs(), MIN_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn failures_back_off_with_jitter() {
+        let mut scheduler = SyncScheduler::new();
+        let mut now = 0;
+        let mut waits = Vec::new();
+        for _ in 0..11 {
+            scheduler.failed(now, u32::MAX / 2);
+            waits.push(scheduler.next_at() - now);
+            now = scheduler.next_at();
+        }
+        assert_eq!(
+            waits,
+            [
+                59, 119, 239, 479, 959, 1919, 3839, 7679, 15359, 21599, 21599
+            ]
+        );
+        assert_eq!(scheduler.sync_age(now), None);
+
+        // The jitter is within a quarter of the backoff
+        let mut low = SyncScheduler::new();
+        low.failed(0, 0);
+        let mut high = SyncScheduler::new();
+        high.failed(0, u32::MAX);
+        assert_eq!((low.next_at(), high.next_at()), (45, 74));
+        let mut capped = SyncScheduler::new();
+        for _ in 0..40 {
+            capped.failed(0, u32::MAX);
+        }
+        assert!(capped.next_at() < MAX_RETRY_SECS * 5 / 4);
+    }
+
+    #[test]
+    fn success_resets_backoff() {
+        let mut scheduler = SyncScheduler::new();
+        scheduler.succeeded(0, None);
+        scheduler.succeeded(DAY, millis(10));
+        let now = scheduler.next_at();
+        for _ in 0..5 {
+            scheduler.failed(now, 0);
+        }
+        scheduler.succeeded(now + 600, millis(100));
+        assert_eq!(scheduler.interval_secs(), 4 * DAY);
+        assert_eq!(scheduler.next_at(), now + 600 + 4 * DAY);
+        scheduler.failed(scheduler.next_at(), 0);
+        assert_eq!(scheduler.next_at(), now + 600 + 4 * DAY + 45);
+    }
+}
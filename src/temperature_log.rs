@@ -0,0 +1,235 @@
+//! Temperatures of the room, from the sensor of the DS3231
+//!
+//! The DS3231 measures its temperature for the compensation of its oscillator, in quarters of a
+//! degree Celsius. The readings are kept as the lowest and the highest of every hour of the last
+//! day, so the screen drawn in the morning shows how cold it got overnight.
+
+use core::fmt::Write;
+
+use chrono::NaiveDateTime;
+use heapless::String;
+
+/// Hours the lowest and highest temperatures are kept for
+pub const HOURS: usize = 24;
+/// Fits the longest label, like `-12.3° -15.0°..-12.3°`
+pub const LABEL_LEN: usize = 32;
+/// Length of [`TemperatureLog::encode`]: the newest hour, the latest reading and the hours
+pub(crate) const ENCODED_LEN: usize = 8 + 2 + HOURS * 4;
+
+/// Stands for a missing reading or hour in the encoded log
+const MISSING: i16 = i16::MIN;
+
+/// Convert degrees Celsius to the nearest quarter of a degree
+pub fn quarters_from_celsius(celsius: f32) -> i16 {
+    let quarters = celsius * 4.0;
+    (quarters + if quarters < 0.0 { -0.5 } else { 0.5 }) as i16
+}
+
+/// Format quarters of a degree as degrees with a decimal, like `-3.5°`
+pub fn format_celsius(quarters: i16) -> String<10> {
+    let tenths = (i32::from(quarters) * 10 + i32::from(quarters.signum()) * 2) / 4;
+    let mut res = String::new();
+    let sign = if tenths < 0 { "-" } else { "" };
+    // Can't be too long, an i16 of quarters is at most 4 digits of degrees
+    let _ = write!(res, "{sign}{}.{}°", tenths.abs() / 10, tenths.abs() % 10);
+    res
+}
+
+/// Lowest and highest temperatures of every hour of the last day
+#[derive(Debug, Default)]
+pub struct TemperatureLog {
+    /// Hour of the newest reading, in hours since the Unix epoch
+    newest_hour: Option<i64>,
+    /// The latest reading, in quarters of a degree
+    latest: Option<i16>,
+    /// The lowest and highest readings of the hours, the one of an hour at its number modulo
+    /// [`HOURS`]
+    hours: [Option<(i16, i16)>; HOURS],
+}
+
+impl TemperatureLog {
+    pub const fn new() -> Self {
+        Self {
+            newest_hour: None,
+            latest: None,
+            hours: [None; HOURS],
+        }
+    }
+
+    /// Record a reading in quarters of a degree taken at the UTC time `at`, `None` when the time
+    /// is not known
+    ///
+    /// The readings are expected in order, one from before the newest hour only counts as the
+    /// latest.
+    pub fn record(&mut self, at: Option<NaiveDateTime>, quarters: i16) {
+        self.latest = Some(quarters);
+        let Some(hour) = at.map(|at| at.and_utc().timestamp().div_euclid(3600)) else {
+            return;
+        };
+        let newest = self.newest_hour.unwrap_or(hour);
+        if hour < newest {
+            return;
+        }
+        // The hours passed since the newest reading have none
+        for skipped in (newest + 1..hour).rev().take(HOURS) {
+            self.hours[slot(skipped)] = None;
+        }
+        let range = match self.hours[slot(hour)] {
+            Some((min, max)) if hour == newest => (min.min(quarters), max.max(quarters)),
+            _ => (quarters, quarters),
+        };
+        self.hours[slot(hour)] = Some(range);
+        self.newest_hour = Some(hour);
+    }
+
+    /// Get the latest reading in quarters of a degree
+    pub const fn latest(&self) -> Option<i16> {
+        self.latest
+    }
+
+    /// Get the lowest and highest readings of the last day before the UTC time `now`
+    pub fn last_day(&self, now: NaiveDateTime) -> Option<(i16, i16)> {
+        let now = now.and_utc().timestamp().div_euclid(3600);
+        let newest = self.newest_hour?;
+        (now - HOURS as i64 + 1..=now.min(newest))
+            .filter_map(|hour| self.hours[slot(hour)])
+            .reduce(|(min, max), (hour_min, hour_max)| (min.min(hour_min), max.max(hour_max)))
+    }
+
+    /// Get the text shown on the screen: the latest reading, then the range of the last day when
+    /// there's more than one temperature in it
+    pub fn label(&self, now: Option<NaiveDateTime>) -> Option<String<LABEL_LEN>> {
+        let mut res = String::new();
+        let _ = res.push_str(&format_celsius(self.latest?));
+        if let Some((min, max)) = now.and_then(|now| self.last_day(now))
+            && min != max
+        {
+            let _ = write!(res, " {}..{}", format_celsius(min), format_celsius(max));
+        }
+        Some(res)
+    }
+
+    /// Write the log to bytes in little endian, the missing readings and hours as [`i16::MIN`]
+    pub(crate) fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut buf = [0; ENCODED_LEN];
+        buf[0..8].copy_from_slice(&self.newest_hour.unwrap_or(i64::MIN).to_le_bytes());
+        buf[8..10].copy_from_slice(&self.latest.unwrap_or(MISSING).to_le_bytes());
+        for (idx, range) in self.hours.iter().enumerate() {
+            let (min, max) = range.unwrap_or((MISSING, MISSING));
+            let offset = 10 + idx * 4;
+            buf[offset..offset + 2].copy_from_slice(&min.to_le_bytes());
+            buf[offset + 2..offset + 4].copy_from_slice(&max.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Read back the log written by [`encode`](Self::encode)
+    pub(crate) fn decode(buf: &[u8; ENCODED_LEN]) -> Self {
+        let i16_at = |offset: usize| i16::from_le_bytes([buf[offset], buf[offset + 1]]);
+        let newest_hour = i64::from_le_bytes(buf[0..8].try_into().unwrap());
+        Self {
+            newest_hour: Some(newest_hour).filter(|&hour| hour != i64::MIN),
+            latest: Some(i16_at(8)).filter(|&latest| latest != MISSING),
+            hours: core::array::from_fn(|idx| {
+                let offset = 10 + idx * 4;
+                Some((i16_at(offset), i16_at(offset + 2))).filter(|&(min, _)| min != MISSING)
+            }),
+        }
+    }
+}
+
+/// Get the place of the range of an hour in the log
+const fn slot(hour: i64) -> usize {
+    hour.rem_euclid(HOURS as i64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+
+    use super::{TemperatureLog, format_celsius, quarters_from_celsius};
+
+    fn at(day: u32, hour: u32, minute: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+    }
+
+    /// Record a reading every 10 minutes from `start`, the temperatures in degrees
+    fn record_every_10_min(log: &mut TemperatureLog, start: NaiveDateTime, celsius: &[f32]) {
+        for (idx, &celsius) in celsius.iter().enumerate() {
+            let time = start + TimeDelta::minutes(10 * idx as i64);
+            log.record(Some(time), quarters_from_celsius(celsius));
+        }
+    }
+
+    #[test]
+    fn converts_and_formats_degrees() {
+        assert_eq!(quarters_from_celsius(21.25), 85);
+        assert_eq!(quarters_from_celsius(-3.75), -15);
+        assert_eq!(quarters_from_celsius(0.0), 0);
+        assert_eq!(format_celsius(85), "21.3°");
+        assert_eq!(format_celsius(86), "21.5°");
+        assert_eq!(format_celsius(-15), "-3.8°");
+        assert_eq!(format_celsius(-1), "-0.3°");
+        assert_eq!(format_celsius(0), "0.0°");
+        assert_eq!(format_celsius(i16::MIN), "-8192.0°");
+    }
+
+    #[test]
+    fn keeps_the_lows_and_highs_of_the_night() {
+        let mut log = TemperatureLog::new();
+        assert_eq!(log.label(at(17, 0, 0)), None);
+        // Cools down over the night and warms up in the morning
+        let night: Vec<f32> = (0..60).map(|idx| 22.0 - idx as f32 * 0.25).collect();
+        record_every_10_min(&mut log, at(16, 22, 0).unwrap(), &night);
+        record_every_10_min(&mut log, at(17, 8, 0).unwrap(), &[8.0, 12.5, 16.0, 19.25]);
+        assert_eq!(log.latest(), Some(77));
+        assert_eq!(log.last_day(at(17, 9, 0).unwrap()), Some((29, 88)));
+        assert_eq!(log.label(at(17, 9, 0)).unwrap(), "19.3° 7.3°..22.0°");
+        // Without the time only the latest is known
+        assert_eq!(log.label(None).unwrap(), "19.3°");
+    }
+
+    #[test]
+    fn forgets_hours_older_than_a_day() {
+        let mut log = TemperatureLog::new();
+        log.record(at(16, 6, 0), -40);
+        log.record(at(16, 12, 30), 80);
+        log.record(at(17, 5, 59), 60);
+        assert_eq!(log.last_day(at(17, 5, 59).unwrap()), Some((-40, 80)));
+        assert_eq!(log.last_day(at(17, 6, 0).unwrap()), Some((60, 80)));
+        // Without readings for a long time
+        log.record(at(19, 7, 0), 70);
+        assert_eq!(log.last_day(at(19, 7, 0).unwrap()), Some((70, 70)));
+        assert_eq!(log.label(at(19, 7, 0)).unwrap(), "17.5°");
+        assert_eq!(log.last_day(at(20, 7, 0).unwrap()), None);
+    }
+
+    #[test]
+    fn ignores_readings_back_in_time() {
+        let mut log = TemperatureLog::new();
+        log.record(at(17, 9, 0), 80);
+        // The clock was set back
+        log.record(at(17, 7, 0), 20);
+        assert_eq!(log.latest(), Some(20));
+        assert_eq!(log.last_day(at(17, 9, 0).unwrap()), Some((80, 80)));
+        log.record(None, 30);
+        assert_eq!(log.last_day(at(17, 9, 0).unwrap()), Some((80, 80)));
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut log = TemperatureLog::new();
+        assert_eq!(TemperatureLog::decode(&log.encode()).latest(), None);
+        record_every_10_min(
+            &mut log,
+            at(17, 1, 0).unwrap(),
+            &[-2.5, 3.0, 18.75, 21.0, 20.5, -30.0],
+        );
+        let decoded = TemperatureLog::decode(&log.encode());
+        assert_eq!(decoded.encode(), log.encode());
+        assert_eq!(decoded.latest(), Some(-120));
+        assert_eq!(decoded.last_day(at(17, 12, 0).unwrap()), Some((-120, 84)));
+    }
+}